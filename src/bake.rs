@@ -0,0 +1,461 @@
+use image::ImageResult;
+use rand::prelude::*;
+use rand_xoshiro::rand_core::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::{Hit, Surface};
+use crate::ray::Ray;
+use crate::sampling::cosine_hemisphere;
+use crate::scene::Scene;
+use crate::texture::{ColorSpace, Texture};
+
+// How far rays leave the surface before looking for other objects, so that
+// they don't find the surface they start on.
+const OFFSET: f64 = 1e-6;
+
+// What to record at each point of a surface.
+pub enum Bake {
+    // The colour of a texture, such as a procedural pattern.
+    Texture(Arc<dyn Texture>),
+    // The share of the hemisphere above the surface that is open to at
+    // least `distance`, from zero in a crevice to one in the open.
+    Occlusion { distance: f64 },
+    // The light arriving at the surface over pi, so that scaling it by an
+    // albedo gives the light a matte surface reflects.
+    Irradiance,
+}
+
+// An image in the uv space of a surface. Texels where none of the surface
+// was found, such as in the holes of a cut out material, are empty.
+pub struct Baked {
+    pub width: usize,
+    pub height: usize,
+    // Row by row from the top, where v is one.
+    pub texels: Vec<Option<Color>>,
+}
+
+impl Baked {
+    // Records `bake` over `surface` at `samples` points jittered within each
+    // texel. Lighting is traced through `scene` by `camera`.
+    pub fn new(
+        camera: &Camera,
+        scene: &Scene,
+        surface: &dyn Surface,
+        bake: &Bake,
+        (width, height): (usize, usize),
+        samples: u32,
+    ) -> Self {
+        let texels = (0..width * height)
+            .into_par_iter()
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                // Seeded by texel, so that a bake is repeatable.
+                let mut rng = Xoshiro256StarStar::seed_from_u64(index as u64);
+                let mut color_acc = Color::new(0., 0., 0.);
+                let mut found = 0;
+
+                for _ in 0..samples {
+                    let (jitter_x, jitter_y): (f64, f64) = rng.gen();
+                    let u = (x as f64 + jitter_x) / width as f64;
+                    let v = 1. - (y as f64 + jitter_y) / height as f64;
+                    let mut hit = match surface.at(u, v) {
+                        Some(hit) if hit.material.alpha(&hit) > 0. => hit,
+                        _ => continue,
+                    };
+
+                    // Textures are filtered over the texel.
+                    hit.dudx = 1. / width as f64;
+                    hit.dvdy = 1. / height as f64;
+
+                    color_acc = color_acc.add(Self::sample(camera, scene, &hit, bake, &mut rng));
+                    found += 1;
+                }
+
+                if found > 0 {
+                    Some(color_acc.scale(1. / f64::from(found)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    fn sample(
+        camera: &Camera,
+        scene: &Scene,
+        hit: &Hit,
+        bake: &Bake,
+        rng: &mut Xoshiro256StarStar,
+    ) -> Color {
+        match bake {
+            Bake::Texture(texture) => texture.color_at(hit),
+            Bake::Occlusion { distance } => {
+                let ray = Self::scattered(hit, rng);
+                let open = if scene.hit(&ray, 0., *distance).is_some() {
+                    0.
+                } else {
+                    1.
+                };
+
+                Color::new(open, open, open)
+            }
+            // Cosine weighted rays make the average of the light they find
+            // the irradiance over pi.
+            Bake::Irradiance => {
+                let ray = Self::scattered(hit, rng);
+
+                camera.radiance(scene, ray, rng)
+            }
+        }
+    }
+
+    // A cosine weighted ray leaving the surface at `hit`.
+    fn scattered(hit: &Hit, rng: &mut Xoshiro256StarStar) -> Ray {
+        let direction = Frame::from_normal(hit.normal).to_world(cosine_hemisphere(rng.gen()));
+
+        Ray::new(hit.p + hit.normal * OFFSET, direction)
+    }
+
+    // Spreads the texels at the edges of what was found outwards by up to
+    // `texels`, each empty texel taking the average of its found neighbours,
+    // so that filtering near the edges doesn't bring in the empty texels.
+    pub fn dilate(&mut self, texels: u32) {
+        for _ in 0..texels {
+            let previous = self.texels.clone();
+
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    if previous[y * self.width + x].is_some() {
+                        continue;
+                    }
+
+                    let mut color_acc = Color::new(0., 0., 0.);
+                    let mut count = 0;
+
+                    for ny in y.saturating_sub(1)..(y + 2).min(self.height) {
+                        for nx in x.saturating_sub(1)..(x + 2).min(self.width) {
+                            if let Some(color) = previous[ny * self.width + nx] {
+                                color_acc = color_acc.add(color);
+                                count += 1;
+                            }
+                        }
+                    }
+
+                    if count > 0 {
+                        self.texels[y * self.width + x] = Some(color_acc.scale(1. / count as f64));
+                    }
+                }
+            }
+        }
+    }
+
+    // Saves the image as linear Radiance HDR if `path` ends in `.hdr`, which
+    // keeps lighting brighter than one, and otherwise as 8-bit sRGB with
+    // empty texels transparent if any are left. HDR has no alpha, so empty
+    // texels are black there.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let path = path.as_ref();
+        let (width, height) = (self.width as u32, self.height as u32);
+
+        if is_hdr(path) {
+            let pixels: Vec<image::Rgb<f32>> = self
+                .texels
+                .iter()
+                .map(|texel| {
+                    let color = texel.unwrap_or(Color::new(0., 0., 0.));
+
+                    image::Rgb([color.r as f32, color.g as f32, color.b as f32])
+                })
+                .collect();
+
+            return image::codecs::hdr::HdrEncoder::new(BufWriter::new(File::create(path)?))
+                .encode(&pixels, self.width, self.height);
+        }
+
+        let encode = |color: Color| {
+            let channel = |value: f64| {
+                (ColorSpace::Srgb.encode(value.clamp(0., 1.)) * f64::from(u8::MAX)).round() as u8
+            };
+
+            [channel(color.r), channel(color.g), channel(color.b)]
+        };
+
+        if self.texels.iter().all(Option::is_some) {
+            image::RgbImage::from_fn(width, height, |x, y| {
+                image::Rgb(encode(self.texels[(y * width + x) as usize].unwrap()))
+            })
+            .save(path)
+        } else {
+            image::RgbaImage::from_fn(width, height, |x, y| {
+                match self.texels[(y * width + x) as usize] {
+                    Some(color) => {
+                        let [r, g, b] = encode(color);
+
+                        image::Rgba([r, g, b, u8::MAX])
+                    }
+                    None => image::Rgba([0, 0, 0, 0]),
+                }
+            })
+            .save(path)
+        }
+    }
+}
+
+pub fn is_hdr(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Library;
+    use crate::material::{AlphaMaterial, LambertianMaterial};
+    use crate::mipmap::{Filter, Wrap};
+    use crate::object::plane::Plane;
+    use crate::object::sphere::Sphere;
+    use crate::texture::{
+        CheckerboardTexture, ConstantTexture, Gradient, GradientTexture, ImageTexture,
+    };
+
+    fn camera() -> Camera {
+        Camera::new(
+            Point3::new(0., 0., -1.),
+            Point3::new(0., 0., 0.),
+            60.,
+            0.,
+            1.,
+            1,
+            1,
+        )
+    }
+
+    fn empty_scene() -> Scene {
+        Scene {
+            objects: vec![],
+            override_material: None,
+            surfaces: Default::default(),
+        }
+    }
+
+    fn grey(value: f64) -> LambertianMaterial<ConstantTexture> {
+        LambertianMaterial {
+            texture: ConstantTexture::scalar(value),
+        }
+    }
+
+    fn bake(scene: &Scene, surface: &dyn Surface, bake: &Bake, size: usize) -> Baked {
+        Baked::new(&camera(), scene, surface, bake, (size, size), 4)
+    }
+
+    #[test]
+    fn it_finds_the_surface_at_each_texel() {
+        let sphere = Sphere::new(Point3::new(1., 2., 3.), 2., grey(0.5));
+
+        for &(u, v) in &[(0.1, 0.5), (0.5, 0.25), (0.9, 0.8)] {
+            let hit = sphere.at(u, v).unwrap();
+
+            assert!(((hit.p - Point3::new(1., 2., 3.)).length() - 2.).abs() < 1e-9);
+            assert!((hit.u - u).abs() < 1e-9);
+            assert!((hit.v - v).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_bakes_textures_in_uv_space() {
+        let plane = Plane::new(
+            Point3::new(0., 0., 0.),
+            Unit3::new(1., 0., 0.),
+            Unit3::new(0., 1., 0.),
+            grey(0.5),
+        );
+        let gradient = Bake::Texture(Arc::new(GradientTexture {
+            gradient: Gradient::U,
+        }));
+        let baked = bake(&empty_scene(), &plane, &gradient, 8);
+        let left = baked.texels[0].unwrap();
+        let right = baked.texels[7].unwrap();
+
+        assert!(left.r < 0.125 && right.r > 0.875);
+        assert!(baked.texels.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn it_is_open_without_other_objects() {
+        let sphere = Sphere::new(Point3::new(0., 0., 0.), 1., grey(0.5));
+        let baked = bake(
+            &empty_scene(),
+            &sphere,
+            &Bake::Occlusion { distance: 1. },
+            4,
+        );
+
+        assert!(baked.texels.iter().all(|texel| texel.unwrap().r == 1.));
+    }
+
+    #[test]
+    fn it_is_half_occluded_beside_a_wall() {
+        let floor = Plane::new(
+            Point3::new(0., 0., 0.),
+            Unit3::new(1., 0., 0.),
+            Unit3::new(0., 0., -1.),
+            grey(0.5),
+        );
+        let mut scene = empty_scene();
+
+        // A wall along the middle of the floor, facing along x.
+        scene.objects.push(Box::new(Plane::new(
+            Point3::new(0.5, 0., 0.),
+            Unit3::new(0., 0., -1.),
+            Unit3::new(0., 1., 0.),
+            grey(0.5),
+        )));
+
+        let baked = Baked::new(
+            &camera(),
+            &scene,
+            &floor,
+            &Bake::Occlusion { distance: 1e6 },
+            (1, 1),
+            4000,
+        );
+        let open = baked.texels[0].unwrap().r;
+
+        assert!((open - 0.5).abs() < 0.05, "{}", open);
+    }
+
+    #[test]
+    fn it_finds_the_irradiance_of_the_sky() {
+        let floor = Plane::new(
+            Point3::new(0., 0., 0.),
+            Unit3::new(1., 0., 0.),
+            Unit3::new(0., 0., -1.),
+            grey(0.5),
+        );
+        let baked = bake(&empty_scene(), &floor, &Bake::Irradiance, 1);
+        let irradiance = baked.texels[0].unwrap();
+
+        assert!(irradiance.b > irradiance.r);
+        assert!(irradiance.b <= 1.);
+    }
+
+    #[test]
+    fn it_leaves_holes_in_cut_out_materials_empty() {
+        let plane = Plane::new(
+            Point3::new(0., 0., 0.),
+            Unit3::new(1., 0., 0.),
+            Unit3::new(0., 1., 0.),
+            AlphaMaterial {
+                material: grey(0.5),
+                alpha: CheckerboardTexture {
+                    odd: ConstantTexture::scalar(0.),
+                    even: ConstantTexture::scalar(1.),
+                    width: 0.5,
+                },
+            },
+        );
+        let white = Bake::Texture(Arc::new(ConstantTexture::scalar(1.)));
+        let baked = bake(&empty_scene(), &plane, &white, 8);
+
+        assert!(baked.texels.iter().any(Option::is_none));
+        assert!(baked.texels.iter().any(Option::is_some));
+    }
+
+    // Saves `baked` and opens it again as an image texture.
+    fn round_trip(baked: &Baked, extension: &str, color_space: ColorSpace) -> ImageTexture {
+        let path = std::env::temp_dir().join(format!(
+            "raygrass-bake-{}.{}",
+            std::process::id(),
+            extension
+        ));
+
+        baked.save(&path).unwrap();
+
+        let texture = ImageTexture::open(&path, color_space, Filter::Nearest, Wrap::Clamp);
+
+        std::fs::remove_file(&path).unwrap();
+        texture.unwrap()
+    }
+
+    #[test]
+    fn it_reads_back_baked_colours_as_srgb() {
+        let plane = Plane::new(
+            Point3::new(0., 0., 0.),
+            Unit3::new(1., 0., 0.),
+            Unit3::new(0., 1., 0.),
+            grey(0.5),
+        );
+        let gradient = Bake::Texture(Arc::new(GradientTexture {
+            gradient: Gradient::V,
+        }));
+        let baked = Baked::new(&camera(), &empty_scene(), &plane, &gradient, (8, 8), 256);
+        let texture = round_trip(&baked, "png", ColorSpace::Srgb);
+
+        for &v in &[0.0625, 0.3125, 0.9375] {
+            let read = texture.color(0.5, v, &Point3::new(0., 0., 0.));
+
+            assert!((read.g - v).abs() < 0.02, "{} != {}", read.g, v);
+        }
+    }
+
+    #[test]
+    fn it_keeps_bright_lighting_in_hdr() {
+        let baked = Baked {
+            width: 1,
+            height: 2,
+            texels: vec![
+                Some(Color::new(3., 2., 0.5)),
+                Some(Color::new(0.25, 0.25, 0.25)),
+            ],
+        };
+        let texture = round_trip(&baked, "hdr", ColorSpace::Linear);
+        let top = texture.color(0.5, 0.75, &Point3::new(0., 0., 0.));
+
+        assert!((top.r - 3.).abs() < 0.05, "{}", top.r);
+        assert!((top.b - 0.5).abs() < 0.01, "{}", top.b);
+        assert!((texture.color(0.5, 0.25, &Point3::new(0., 0., 0.)).r - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn it_dilates_into_empty_texels() {
+        let mut baked = Baked {
+            width: 4,
+            height: 1,
+            texels: vec![Some(Color::new(1., 0., 0.)), None, None, None],
+        };
+
+        baked.dilate(2);
+
+        assert_eq!(baked.texels[1].unwrap().r, 1.);
+        assert_eq!(baked.texels[2].unwrap().r, 1.);
+        assert!(baked.texels[3].is_none());
+    }
+
+    #[test]
+    fn it_finds_named_objects_in_the_scene() {
+        let mut library = Library::new();
+
+        library.add_texture("earth", ConstantTexture::scalar(0.5));
+        library.add_texture("moon", ConstantTexture::scalar(0.5));
+        library.add_texture("moon_height", ConstantTexture::scalar(0.5));
+
+        let scene = Scene::new(&mut library, false);
+
+        assert!(scene.surface_names().contains(&"marble"));
+        assert!(scene.surfaces["moon"].at(0.5, 0.5).is_some());
+    }
+}
@@ -60,7 +60,7 @@ impl<'a> Bvh<'a> {
 }
 
 impl<'a> Hittable for Bvh<'a> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
         if self.bounding_box.hit(ray, t_min, t_max) {
             if let Some(l_hit) = self.left.hit(ray, t_min, t_max) {
                 if let Some(r_hit) = self.right.hit(ray, t_min, t_max) {
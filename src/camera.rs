@@ -2,11 +2,14 @@ extern crate image;
 extern crate indicatif;
 extern crate rayon;
 
+use image::buffer::ConvertBuffer;
+use image::ImageFormat;
 use rand::prelude::*;
 use rand::seq::SliceRandom;
 use rand_xoshiro::rand_core::SeedableRng;
 use rand_xoshiro::Xoshiro256StarStar;
 use rayon::prelude::*;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 use indicatif::{ProgressBar, ProgressStyle};
@@ -14,9 +17,33 @@ use indicatif::{ProgressBar, ProgressStyle};
 use crate::color::Color;
 use crate::geometry::*;
 use crate::hittable::*;
-use crate::ray::Ray;
+use crate::material::{BsdfFlags, Matte};
+use crate::medium::MediumStack;
+use crate::ray::{Differentials, Ray};
 use crate::scene::Scene;
 
+const MAX_BOUNCES: u32 = 50;
+const MAX_SCATTERING_EVENTS: u32 = 1000;
+
+// Whether images saved to `path` can be transparent, judged by its extension.
+pub fn supports_alpha(path: &str) -> bool {
+    let path = Path::new(path);
+
+    match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Png)
+        | Ok(ImageFormat::Tiff)
+        | Ok(ImageFormat::Tga)
+        | Ok(ImageFormat::Bmp)
+        | Ok(ImageFormat::Ico)
+        | Ok(ImageFormat::Gif) => true,
+        // Of the PNM formats, only PAM has an alpha channel.
+        Ok(ImageFormat::Pnm) => path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pam")),
+        _ => false,
+    }
+}
+
 struct Film {
     top_left: Point3,
     u: Unit3,
@@ -78,7 +105,7 @@ impl Camera {
         }
     }
 
-    pub fn capture(&self, scene: &Scene, samples: u32, outfile: &str) {
+    pub fn capture(&self, scene: &Scene, samples: u32, transparent: bool, outfile: &str) {
         let buf = Arc::new(Mutex::new(image::ImageBuffer::new(self.img_x, self.img_y)));
         let pb = ProgressBar::new((self.img_x * self.img_y).into());
         pb.set_style(
@@ -93,6 +120,7 @@ impl Camera {
         let pixel_width = 1. / f64::from(self.img_x);
         let pixel_height = 1. / f64::from(self.img_y);
         let color_scale = 1. / f64::from(samples);
+        let differential_scale = (1. / f64::from(samples).sqrt()).max(0.125);
 
         let mut pixel_rng = Xoshiro256StarStar::seed_from_u64(0);
         let mut pixels: Vec<u32> = (0..(self.img_x * self.img_y)).collect();
@@ -108,6 +136,7 @@ impl Camera {
             pb.inc(1);
 
             let mut color_acc = Color::new(0., 0., 0.);
+            let mut alpha_acc = 0.;
 
             let x_min = f64::from(x) * pixel_width;
             let y_min = f64::from(y) * pixel_height;
@@ -116,24 +145,46 @@ impl Camera {
                 let x_max = x_min + pixel_width;
                 let y_max = y_min + pixel_height;
 
-                let ray = self.ray_for_pixel(&mut rng, (x_min, x_max), (y_min, y_max));
+                let mut ray = self.ray_for_pixel(&mut rng, (x_min, x_max), (y_min, y_max));
 
-                let color = self.trace(scene, ray, 50, &mut rng);
+                // Samples spread over the pixel each cover a share of it.
+                ray.scale_differentials(differential_scale);
+
+                let (color, alpha) = self.trace_primary(scene, ray, transparent, &mut rng);
 
                 color_acc = color_acc.add(color);
+                alpha_acc += alpha;
             }
 
             color_acc = color_acc.scale(color_scale);
+            alpha_acc *= color_scale;
 
             buf.lock()
                 .unwrap()
-                .put_pixel(x, y, image::Rgb(color_acc.into()));
+                .put_pixel(x, y, Self::unpremultiply(color_acc, alpha_acc));
         });
 
-        buf.lock()
-            .unwrap()
-            .save(outfile)
-            .expect("Saving image failed");
+        let buf = buf.lock().unwrap();
+
+        if buf.pixels().all(|pixel| pixel[3] == u8::MAX) {
+            ConvertBuffer::<image::RgbImage>::convert(&*buf).save(outfile)
+        } else {
+            buf.save(outfile)
+        }
+        .expect("Saving image failed");
+    }
+
+    // Converts a colour premultiplied by `alpha` to a straight alpha pixel.
+    fn unpremultiply(color: Color, alpha: f64) -> image::Rgba<u8> {
+        let alpha = alpha.clamp(0., 1.);
+        let straight = if alpha > 0. {
+            color.scale(1. / alpha)
+        } else {
+            color
+        };
+        let [r, g, b]: [u8; 3] = straight.into();
+
+        image::Rgba([r, g, b, (alpha * f64::from(u8::MAX)) as u8])
     }
 
     fn random_in_unit_disc(rng: &mut Xoshiro256StarStar) -> Vector3 {
@@ -164,25 +215,34 @@ impl Camera {
         let ray_origin = self.origin + offset;
 
         let direction = (self.film.project(x_pos, y_pos) - ray_origin).normalize();
-
-        Ray::new(ray_origin, direction)
+        let pixel_width = x_range.1 - x_range.0;
+        let pixel_height = y_range.1 - y_range.0;
+
+        Ray {
+            differentials: Some(Differentials {
+                x_origin: ray_origin,
+                x_direction: (self.film.project(x_pos + pixel_width, y_pos) - ray_origin)
+                    .normalize(),
+                y_origin: ray_origin,
+                y_direction: (self.film.project(x_pos, y_pos + pixel_height) - ray_origin)
+                    .normalize(),
+            }),
+            ..Ray::new(ray_origin, direction)
+        }
     }
 
-    fn ray_hit<'a>(&'a self, objects: &'a [Box<dyn Hittable>], ray: Ray) -> Option<Hit> {
-        let mut result: Option<Hit> = None;
+    fn ray_hit<'a>(&'a self, scene: &'a Scene, ray: Ray) -> Option<Hit<'a>> {
+        let mut result = scene.hit(&ray, 1e-10, f64::INFINITY);
 
-        for o in objects {
-            if let Some(hit) = o.hit(&ray, 1e-10, std::f64::INFINITY) {
-                if let Some(min_hit) = result {
-                    if hit.t < min_hit.t {
-                        result = Some(hit);
-                    } else {
-                        result = Some(min_hit);
-                    }
-                } else {
-                    result = Some(hit);
-                }
-            }
+        // Objects decide where their alpha masks let rays through, so those
+        // keep the original material. Everything after the hit, including
+        // which media the ray enters, uses the override.
+        if let (Some(hit), Some(material)) = (result.as_mut(), &scene.override_material) {
+            hit.material = material.as_ref();
+        }
+
+        if let Some(hit) = result.as_mut() {
+            hit.differentiate(&ray);
         }
 
         result
@@ -194,10 +254,100 @@ impl Camera {
         Color::new(1.0 - 0.4 * t, 1.0 - 0.2 * t, 1.0)
     }
 
-    fn trace(
+    // The colour seen along a camera ray, premultiplied by its alpha, and the
+    // alpha itself.
+    fn trace_primary(
         &self,
         scene: &Scene,
         ray: Ray,
+        transparent: bool,
+        rng: &mut Xoshiro256StarStar,
+    ) -> (Color, f64) {
+        match self.ray_hit(scene, ray) {
+            Some(hit) => match hit.material.matte() {
+                Some(Matte::Holdout) => (Color::new(0., 0., 0.), 0.),
+                Some(Matte::ShadowCatcher) => self.catch_shadow(scene, ray, hit, rng),
+                None => {
+                    let media = MediumStack::default();
+
+                    (
+                        self.shade(scene, ray, Some(hit), &media, MAX_BOUNCES, rng),
+                        1.,
+                    )
+                }
+            },
+            None if transparent => (Color::new(0., 0., 0.), 0.),
+            None => (Self::ray_color(&ray), 1.),
+        }
+    }
+
+    // Compares the light reflected by a shadow catcher with the light it
+    // would reflect from the background alone. Both the shadows and anything
+    // brighter than the background, such as reflections, are recorded so that
+    // compositing over the background gives back the light reflected.
+    fn catch_shadow<'a>(
+        &'a self,
+        scene: &'a Scene,
+        ray: Ray,
+        hit: Hit<'a>,
+        rng: &mut Xoshiro256StarStar,
+    ) -> (Color, f64) {
+        let frame = hit.material.frame(&hit);
+        let wo = frame.to_local(ray.direction.reverse());
+        let sample = match hit.material.sample(&hit, wo, rng.gen()) {
+            Some(sample) => sample,
+            None => return (Color::new(0., 0., 0.), 0.),
+        };
+        let scattered = Ray::new(hit.p, frame.to_world(sample.wi));
+        let weight = sample.f.scale(sample.wi.z.abs() / sample.pdf);
+        let media = MediumStack::default();
+        let shadowed = self
+            .trace(scene, scattered, &media, MAX_BOUNCES - 1, rng)
+            .mul(weight);
+        let unshadowed = Self::ray_color(&scattered).mul(weight);
+
+        let ratio = if unshadowed.luminance() > 0. {
+            shadowed.luminance() / unshadowed.luminance()
+        } else if shadowed.luminance() > 0. {
+            f64::INFINITY
+        } else {
+            1.
+        };
+
+        if ratio < 1. {
+            // A shadow darkens the background by the ratio.
+            (Color::new(0., 0., 0.), 1. - ratio)
+        } else {
+            // Light added to the background covers it by the share of the
+            // light reflected that it makes up.
+            (shadowed.add(unshadowed.scale(-1. / ratio)), 1. - 1. / ratio)
+        }
+    }
+
+    // The light arriving along `ray` from outside any medium.
+    pub fn radiance(&self, scene: &Scene, ray: Ray, rng: &mut Xoshiro256StarStar) -> Color {
+        self.trace(scene, ray, &MediumStack::default(), MAX_BOUNCES, rng)
+    }
+
+    fn trace<'a>(
+        &'a self,
+        scene: &'a Scene,
+        ray: Ray,
+        media: &MediumStack<'a>,
+        remaining_calls: u32,
+        rng: &mut Xoshiro256StarStar,
+    ) -> Color {
+        let hit = self.ray_hit(scene, ray);
+
+        self.shade(scene, ray, hit, media, remaining_calls, rng)
+    }
+
+    fn shade<'a>(
+        &'a self,
+        scene: &'a Scene,
+        ray: Ray,
+        hit: Option<Hit<'a>>,
+        media: &MediumStack<'a>,
         remaining_calls: u32,
         rng: &mut Xoshiro256StarStar,
     ) -> Color {
@@ -205,22 +355,105 @@ impl Camera {
             return Color::new(0., 0., 0.);
         }
 
-        if let Some(hit) = self.ray_hit(&scene.objects, ray) {
-            match hit.material.scatter(&ray, &hit, rng) {
-                Some((reflection_ray, attenuation)) => {
+        let mut ray = ray;
+        let mut hit = hit;
+        let mut transmittance = Color::new(1., 1., 1.);
+
+        if let Some(medium) = media.medium() {
+            let mut scattering_events = 0;
+
+            loop {
+                let t_max = hit.as_ref().map_or(f64::INFINITY, |hit| hit.t);
+                let (scattered, weight) = medium.sample_distance(t_max, rng.gen());
+
+                transmittance = transmittance.mul(weight);
+
+                match scattered {
+                    Some(t) if scattering_events < MAX_SCATTERING_EVENTS => {
+                        ray = Ray::new(ray.at(t), medium.sample_phase(ray.direction, rng.gen()));
+                        hit = self.ray_hit(scene, ray);
+                        scattering_events += 1;
+                    }
+                    Some(_) => return Color::new(0., 0., 0.),
+                    None => break,
+                }
+            }
+        }
+
+        if let Some(mut hit) = hit {
+            let entering = ray.direction.dot(hit.normal) < 0.;
+            let (exterior, crossed) = if entering {
+                (media.clone(), media.entered(hit.material))
+            } else {
+                let exterior = media.exited(hit.material);
+
+                (exterior.clone(), exterior)
+            };
+
+            if exterior.overrides(hit.material) {
+                let passed = Ray::new(hit.p, ray.direction);
+
+                return self
+                    .trace(scene, passed, &crossed, remaining_calls - 1, rng)
+                    .mul(transmittance);
+            }
+
+            hit.exterior_refractive_index = exterior.refractive_index();
+
+            let frame = hit.material.frame(&hit);
+            let wo = frame.to_local(ray.direction.reverse());
+            let u: [f64; 3] = rng.gen();
+
+            match hit.material.sample(&hit, wo, u) {
+                Some(sample) => {
+                    let direction = frame.to_world(sample.wi);
+                    let scattered = Ray::new(hit.p, direction);
+                    let weight = sample.f.scale(sample.wi.z.abs() / sample.pdf);
+                    let scattered_media = if sample.flags.contains(BsdfFlags::TRANSMISSION) {
+                        &crossed
+                    } else {
+                        media
+                    };
                     let incoming_color =
-                        self.trace(scene, reflection_ray, remaining_calls - 1, rng);
+                        self.trace(scene, scattered, scattered_media, remaining_calls - 1, rng);
 
-                    Color::new(
-                        incoming_color.r * attenuation.r,
-                        incoming_color.g * attenuation.g,
-                        incoming_color.b * attenuation.b,
-                    )
+                    incoming_color.mul(weight).mul(transmittance)
                 }
                 None => Color::new(0., 0., 0.),
             }
         } else {
-            Self::ray_color(&ray)
+            Self::ray_color(&ray).mul(transmittance)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_only_makes_formats_with_alpha_transparent() {
+        assert!(supports_alpha("out.png"));
+        assert!(supports_alpha("out.TIF"));
+        assert!(supports_alpha("out.pam"));
+        assert!(!supports_alpha("out.jpg"));
+        assert!(!supports_alpha("out.ppm"));
+        assert!(!supports_alpha("out"));
+    }
+
+    #[test]
+    fn it_unpremultiplies_by_the_alpha_alone() {
+        assert_eq!(
+            Camera::unpremultiply(Color::new(0.25, 0.25, 0.), 0.5),
+            image::Rgba([127, 127, 0, 127])
+        );
+        assert_eq!(
+            Camera::unpremultiply(Color::new(0.05, 0.05, 0.05), 0.1),
+            image::Rgba([127, 127, 127, 25])
+        );
+        assert_eq!(
+            Camera::unpremultiply(Color::new(0., 0., 0.), 0.),
+            image::Rgba([0, 0, 0, 0])
+        );
+    }
+}
@@ -1,23 +1,120 @@
 extern crate clap;
 
+use crate::camera;
+use crate::color::Color;
 use crate::config::*;
+use crate::mipmap::{Filter, Wrap};
+use crate::texture::{tiled, ColorSpace};
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 const PKG_DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 const PKG_AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
-pub fn config() -> Config {
+pub fn command() -> Command {
     let cli = Cli::new();
 
-    Config {
+    if let Some(tile) = cli.matches.subcommand_matches("tile") {
+        return Command::Tile(TileConfig {
+            infile: tile.value_of("INFILE").unwrap().to_string(),
+            outfile: tile.value_of("OUTFILE").unwrap().to_string(),
+            color_space: color_space(tile.value_of("color-space").unwrap_or("srgb")),
+            tile_size: tile_size(tile.value_of("tile-size")),
+        });
+    }
+
+    if let Some(bake) = cli.matches.subcommand_matches("bake") {
+        let bake = Cli {
+            matches: bake.clone(),
+        };
+
+        return Command::Bake(BakeConfig {
+            object: bake.matches.value_of("OBJECT").unwrap().to_string(),
+            bake: bake.matches.value_of("BAKE").unwrap().to_string(),
+            resolution: bake.resolution(),
+            samples: bake.samples(),
+            dilation: bake.dilation(),
+            occlusion_distance: bake.occlusion_distance(),
+            time: bake.time(),
+            texture_cache: bake.texture_cache(),
+            outfile: bake.outfile().to_string(),
+        });
+    }
+
+    Command::Render(Config {
         samples: cli.samples(),
         resolution: cli.resolution(),
         time: cli.time(),
+        transparent: cli.transparent(),
+        override_material: cli.override_material(),
+        texture_filter: cli.texture_filter(),
+        texture_wrap: cli.texture_wrap(),
+        texture_cache: cli.texture_cache(),
         outfile: cli.outfile().to_string(),
+    })
+}
+
+fn color_space(val: &str) -> ColorSpace {
+    match val {
+        "srgb" => ColorSpace::Srgb,
+        "linear" => ColorSpace::Linear,
+        "raw" => ColorSpace::Raw,
+        val => {
+            println!("Invalid colour space '{}'", val);
+            ColorSpace::Srgb
+        }
     }
 }
 
+fn tile_size(val: Option<&str>) -> usize {
+    let default = tiled::DEFAULT_TILE_SIZE;
+
+    match val.map(str::parse) {
+        None => default,
+        Some(Ok(size)) if size > 0 => size,
+        Some(_) => {
+            println!("Invalid tile size '{}'", val.unwrap());
+            default
+        }
+    }
+}
+
+// Options shared by rendering and baking.
+fn samples_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("samples")
+        .short("s")
+        .long("samples")
+        .value_name("SAMPLES")
+        .help("The number of samples per pixel (default: 100)")
+        .takes_value(true)
+}
+
+fn resolution_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("resolution")
+        .short("r")
+        .long("resolution")
+        .value_name("RESOLUTION")
+        .help("The size of the image (WxH, default: 1600x1200)")
+        .takes_value(true)
+}
+
+fn time_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("time")
+        .short("t")
+        .long("time")
+        .value_name("TIME")
+        .help("The simulation time (seconds, default: 0.0)")
+        .takes_value(true)
+}
+
+fn texture_cache_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("texture-cache")
+        .long("texture-cache")
+        .value_name("MEGABYTES")
+        .help("The memory budget for tiled textures (default: 1024)")
+        .takes_value(true)
+}
+
 struct Cli<'a> {
     matches: clap::ArgMatches<'a>,
 }
@@ -28,36 +125,119 @@ impl<'a> Cli<'a> {
             .version(PKG_VERSION)
             .about(PKG_DESCRIPTION)
             .author(PKG_AUTHORS)
+            .setting(clap::AppSettings::SubcommandsNegateReqs)
             .arg(
                 clap::Arg::with_name("OUTFILE")
                     .help("The output filename")
                     .required(true)
                     .index(1),
             )
+            .arg(samples_arg())
+            .arg(resolution_arg())
+            .arg(time_arg())
+            .arg(
+                clap::Arg::with_name("transparent")
+                    .long("transparent")
+                    .help(
+                        "Render the background as transparent, with the floor catching shadows. \
+                         The output must be a format with alpha, such as PNG",
+                    ),
+            )
             .arg(
-                clap::Arg::with_name("samples")
-                    .short("s")
-                    .long("samples")
-                    .value_name("SAMPLES")
-                    .help("The number of samples per pixel (default: 100)")
+                clap::Arg::with_name("override-material")
+                    .long("override-material")
+                    .value_name("MATERIAL")
+                    .help(
+                        "Render every object in the named material, or a MERL BRDF file. \
+                         Objects keep the alpha masks of their own materials",
+                    )
                     .takes_value(true),
             )
             .arg(
-                clap::Arg::with_name("resolution")
-                    .short("r")
-                    .long("resolution")
-                    .value_name("RESOLUTION")
-                    .help("The size of the image (WxH, default: 1600x1200)")
+                clap::Arg::with_name("texture-filter")
+                    .long("texture-filter")
+                    .value_name("FILTER")
+                    .help("The filter for image textures (nearest, bilinear, bicubic, trilinear or ewa, default: ewa)")
                     .takes_value(true),
             )
             .arg(
-                clap::Arg::with_name("time")
-                    .short("t")
-                    .long("time")
-                    .value_name("TIME")
-                    .help("The simulation time (seconds, default: 0.0)")
+                clap::Arg::with_name("texture-wrap")
+                    .long("texture-wrap")
+                    .value_name("WRAP")
+                    .help("How image textures continue past their edges (repeat, clamp, mirror or black, default: repeat)")
                     .takes_value(true),
             )
+            .arg(texture_cache_arg())
+            .subcommand(
+                clap::SubCommand::with_name("tile")
+                    .about("Converts an image to a tiled texture, which renders use in place of an image with the same name")
+                    .arg(
+                        clap::Arg::with_name("INFILE")
+                            .help("The image to convert")
+                            .required(true)
+                            .index(1),
+                    )
+                    .arg(
+                        clap::Arg::with_name("OUTFILE")
+                            .help("The tiled texture filename, usually ending .tx")
+                            .required(true)
+                            .index(2),
+                    )
+                    .arg(
+                        clap::Arg::with_name("color-space")
+                            .long("color-space")
+                            .value_name("SPACE")
+                            .help("The colour space of the image (srgb, linear or raw, default: srgb)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        clap::Arg::with_name("tile-size")
+                            .long("tile-size")
+                            .value_name("TEXELS")
+                            .help("The width and height of each tile (default: 64)")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(
+                clap::SubCommand::with_name("bake")
+                    .about("Bakes a texture or the lighting of an object in the scene into an image in its uv space")
+                    .arg(
+                        clap::Arg::with_name("OBJECT")
+                            .help("The object to bake")
+                            .required(true)
+                            .index(1),
+                    )
+                    .arg(
+                        clap::Arg::with_name("BAKE")
+                            .help("What to bake: occlusion, irradiance or the name of a texture")
+                            .required(true)
+                            .index(2),
+                    )
+                    .arg(
+                        clap::Arg::with_name("OUTFILE")
+                            .help("The output filename. Textures are written as sRGB, and lighting as Radiance HDR (.hdr)")
+                            .required(true)
+                            .index(3),
+                    )
+                    .arg(samples_arg())
+                    .arg(resolution_arg())
+                    .arg(time_arg())
+                    .arg(texture_cache_arg())
+                    .arg(
+                        clap::Arg::with_name("dilation")
+                            .long("dilation")
+                            .value_name("TEXELS")
+                            .help("How far to spread the edges of the bake into empty texels (default: 8)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        clap::Arg::with_name("distance")
+                            .long("distance")
+                            .value_name("DISTANCE")
+                            .help("How far away objects occlude a point (default: 1.0)")
+                            .takes_value(true),
+                    ),
+            )
             .get_matches();
 
         Self { matches }
@@ -102,6 +282,87 @@ impl<'a> Cli<'a> {
         })
     }
 
+    pub fn transparent(&self) -> bool {
+        let outfile = self.outfile();
+
+        if !self.matches.is_present("transparent") {
+            false
+        } else if camera::supports_alpha(outfile) {
+            true
+        } else {
+            println!(
+                "'{}' can't be transparent, so the background is rendered",
+                outfile
+            );
+            false
+        }
+    }
+
+    pub fn override_material(&self) -> Option<String> {
+        self.matches
+            .value_of("override-material")
+            .map(|val| val.to_string())
+    }
+
+    pub fn texture_filter(&self) -> Filter {
+        match self.matches.value_of("texture-filter").unwrap_or("ewa") {
+            "nearest" => Filter::Nearest,
+            "bilinear" => Filter::Bilinear,
+            "bicubic" => Filter::Bicubic,
+            "trilinear" => Filter::Trilinear,
+            "ewa" => Filter::Ewa,
+            val => {
+                println!("Invalid texture filter '{}'", val);
+                Filter::Ewa
+            }
+        }
+    }
+
+    pub fn texture_wrap(&self) -> Wrap {
+        match self.matches.value_of("texture-wrap").unwrap_or("repeat") {
+            "repeat" => Wrap::Repeat,
+            "clamp" => Wrap::Clamp,
+            "mirror" => Wrap::Mirror,
+            "black" => Wrap::Border(Color::new(0., 0., 0.)),
+            val => {
+                println!("Invalid texture wrap '{}'", val);
+                Wrap::Repeat
+            }
+        }
+    }
+
+    pub fn texture_cache(&self) -> usize {
+        let val = self.matches.value_of("texture-cache").unwrap_or("1024");
+        val.parse::<usize>()
+            .ok()
+            .and_then(|megabytes| megabytes.checked_mul(1024 * 1024))
+            .unwrap_or_else(|| {
+                println!("Invalid texture cache size '{}'", val);
+                1024 * 1024 * 1024
+            })
+    }
+
+    pub fn dilation(&self) -> u32 {
+        let val = self.matches.value_of("dilation").unwrap_or("8");
+
+        val.parse().unwrap_or_else(|_| {
+            println!("Invalid dilation '{}'", val);
+            8
+        })
+    }
+
+    pub fn occlusion_distance(&self) -> f64 {
+        let val = self.matches.value_of("distance").unwrap_or("1.0");
+
+        match val.parse() {
+            Ok(distance) if distance > 0. => distance,
+            _ => {
+                println!("Invalid occlusion distance '{}'", val);
+                1.0
+            }
+        }
+    }
+
     pub fn outfile(&self) -> &str {
         self.matches.value_of("OUTFILE").unwrap()
     }
@@ -17,6 +17,55 @@ impl Color {
     pub fn add(&self, other: Self) -> Self {
         Self::new(self.r + other.r, self.g + other.g, self.b + other.b)
     }
+
+    pub fn mul(&self, other: Self) -> Self {
+        Self::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.r == 0. && self.g == 0. && self.b == 0.
+    }
+
+    // Hue in degrees, saturation and value.
+    pub fn hsv(&self) -> (f64, f64, f64) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+
+        let hue = if chroma == 0. {
+            0.
+        } else if max == self.r {
+            60. * ((self.g - self.b) / chroma).rem_euclid(6.)
+        } else if max == self.g {
+            60. * ((self.b - self.r) / chroma + 2.)
+        } else {
+            60. * ((self.r - self.g) / chroma + 4.)
+        };
+        let saturation = if max > 0. { chroma / max } else { 0. };
+
+        (hue, saturation, max)
+    }
+
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Self {
+        let chroma = value * saturation;
+        let sector = hue.rem_euclid(360.) / 60.;
+        let x = chroma * (1. - (sector % 2. - 1.).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.),
+            1 => (x, chroma, 0.),
+            2 => (0., chroma, x),
+            3 => (0., x, chroma),
+            4 => (x, 0., chroma),
+            _ => (chroma, 0., x),
+        };
+        let m = value - chroma;
+
+        Self::new(r + m, g + m, b + m)
+    }
 }
 
 impl From<[f64; 3]> for Color {
@@ -27,7 +76,7 @@ impl From<[f64; 3]> for Color {
 
 impl From<Color> for [u8; 3] {
     fn from(color: Color) -> Self {
-        let max = f64::from(std::u8::MAX);
+        let max = f64::from(u8::MAX);
 
         [
             (color.r * max).min(max) as u8,
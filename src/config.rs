@@ -1,6 +1,45 @@
+use crate::mipmap::{Filter, Wrap};
+use crate::texture::ColorSpace;
+
+// What to do, chosen by the subcommand.
+pub enum Command {
+    Render(Config),
+    Tile(TileConfig),
+    Bake(BakeConfig),
+}
+
 pub struct Config {
     pub samples: u32,
     pub resolution: (u32, u32),
     pub time: f64,
+    pub transparent: bool,
+    pub override_material: Option<String>,
+    pub texture_filter: Filter,
+    pub texture_wrap: Wrap,
+    // The memory budget for tiled textures, in bytes.
+    pub texture_cache: usize,
+    pub outfile: String,
+}
+
+pub struct TileConfig {
+    pub infile: String,
+    pub outfile: String,
+    pub color_space: ColorSpace,
+    pub tile_size: usize,
+}
+
+pub struct BakeConfig {
+    // The name of the object in the scene.
+    pub object: String,
+    // `occlusion`, `irradiance` or the name of a texture.
+    pub bake: String,
+    pub resolution: (u32, u32),
+    pub samples: u32,
+    // How far to spread the edges of the bake into empty texels.
+    pub dilation: u32,
+    // How far away an object occludes a point.
+    pub occlusion_distance: f64,
+    pub time: f64,
+    pub texture_cache: usize,
     pub outfile: String,
 }
@@ -0,0 +1,46 @@
+use super::{Unit3, Vector3};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame {
+    pub s: Unit3,
+    pub t: Unit3,
+    pub n: Unit3,
+}
+
+impl Frame {
+    pub fn from_normal(n: Unit3) -> Self {
+        let sign = 1f64.copysign(n.z);
+        let a = -1. / (sign + n.z);
+        let b = n.x * n.y * a;
+
+        Self {
+            s: Unit3::new(1. + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            t: Unit3::new(b, sign + n.y * n.y * a, -n.y),
+            n,
+        }
+    }
+
+    pub fn from_normal_and_tangent(n: Unit3, tangent: Vector3) -> Self {
+        let projected = tangent - n * Vector3::from(n).dot(tangent);
+
+        if projected.length_squared() < 1e-12 {
+            return Self::from_normal(n);
+        }
+
+        let s = projected.normalize();
+
+        Self {
+            s,
+            t: (n * s).normalize(),
+            n,
+        }
+    }
+
+    pub fn to_local(self, v: Unit3) -> Unit3 {
+        Unit3::new(v.dot(self.s), v.dot(self.t), v.dot(self.n))
+    }
+
+    pub fn to_world(self, v: Unit3) -> Unit3 {
+        (self.s * v.x + self.t * v.y + self.n * v.z).into()
+    }
+}
@@ -1,7 +1,9 @@
+pub mod frame;
 pub mod point;
 pub mod unit;
 pub mod vector;
 
+pub use frame::*;
 pub use point::*;
 pub use unit::*;
 pub use vector::*;
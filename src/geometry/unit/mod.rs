@@ -24,21 +24,6 @@ impl Unit3 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
-    pub fn reflect(&self, other: Unit3) -> Unit3 {
-        let dot = self.dot(other);
-        let Unit3 {
-            x: ox,
-            y: oy,
-            z: oz,
-        } = other;
-
-        Self {
-            x: self.x - 2. * dot * ox,
-            y: self.y - 2. * dot * oy,
-            z: self.z - 2. * dot * oz,
-        }
-    }
-
     pub fn reverse(&self) -> Unit3 {
         Self {
             x: -self.x,
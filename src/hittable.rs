@@ -1,5 +1,9 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::mem;
+use std::sync::Arc;
 
+use crate::color::Color;
 use crate::geometry::*;
 use crate::material::Material;
 use crate::ray::Ray;
@@ -10,11 +14,167 @@ pub struct Hit<'a> {
     pub u: f64,
     pub v: f64,
     pub normal: Unit3,
+    pub dpdu: Vector3,
+    pub dpdv: Vector3,
+    // The change in texture coordinates over one pixel on the film in x and
+    // in y, or zero where the footprint of the ray is unknown.
+    pub dudx: f64,
+    pub dudy: f64,
+    pub dvdx: f64,
+    pub dvdy: f64,
+    // The index of refraction on the outside of the surface, filled in by
+    // the tracer from the media the ray is travelling through.
+    pub exterior_refractive_index: f64,
     pub material: &'a dyn Material,
+    pub attributes: &'a Attributes,
+}
+
+// Values attached to an object, so that one material shared between many
+// objects can vary between them through textures that read them.
+#[derive(Clone, Debug, Default)]
+pub struct Attributes {
+    // A number identifying the object among those sharing a material.
+    pub id: u64,
+    pub floats: Vec<(String, f64)>,
+    pub colors: Vec<(String, Color)>,
+}
+
+// The attributes of objects that haven't been given any.
+pub static NO_ATTRIBUTES: Attributes = Attributes {
+    id: 0,
+    floats: Vec::new(),
+    colors: Vec::new(),
+};
+
+impl Attributes {
+    pub fn float(&self, name: &str) -> Option<f64> {
+        self.floats
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|&(_, value)| value)
+    }
+
+    pub fn color(&self, name: &str) -> Option<Color> {
+        self.colors
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|&(_, color)| color)
+    }
+}
+
+impl<'a> Hit<'a> {
+    pub fn frame(&self) -> Frame {
+        Frame::from_normal_and_tangent(self.normal, self.dpdu)
+    }
+
+    // Finds the texture coordinate derivatives from where the differentials
+    // of `ray` meet the tangent plane, after Igehy 1999, "Tracing Ray
+    // Differentials".
+    pub fn differentiate(&mut self, ray: &Ray) {
+        let differentials = match ray.differentials {
+            Some(differentials) => differentials,
+            None => return,
+        };
+        let normal = Vector3::from(self.normal);
+        let d = normal.dot(self.p - Point3::new(0., 0., 0.));
+        let offset = |origin: Point3, direction: Unit3| {
+            let t =
+                (d - normal.dot(origin - Point3::new(0., 0., 0.))) / normal.dot(direction.into());
+
+            (origin + direction * t) - self.p
+        };
+        let dpdx = offset(differentials.x_origin, differentials.x_direction);
+        let dpdy = offset(differentials.y_origin, differentials.y_direction);
+
+        if !(dpdx.length_squared().is_finite() && dpdy.length_squared().is_finite()) {
+            return;
+        }
+
+        // Solve the overdetermined system in the two axes least aligned with
+        // the normal.
+        let (a, b) = if self.normal.x.abs() > self.normal.y.abs()
+            && self.normal.x.abs() > self.normal.z.abs()
+        {
+            (1, 2)
+        } else if self.normal.y.abs() > self.normal.z.abs() {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
+        let (dpdu, dpdv) = (self.dpdu, self.dpdv);
+        let determinant = dpdu[a] * dpdv[b] - dpdv[a] * dpdu[b];
+
+        if determinant.abs() < 1e-12 {
+            return;
+        }
+
+        let solve = |delta: Vector3| {
+            (
+                (dpdv[b] * delta[a] - dpdv[a] * delta[b]) / determinant,
+                (dpdu[a] * delta[b] - dpdu[b] * delta[a]) / determinant,
+            )
+        };
+
+        (self.dudx, self.dvdx) = solve(dpdx);
+        (self.dudy, self.dvdy) = solve(dpdy);
+    }
+
+    // Whether the ray finds the surface here, rather than passing through a
+    // masked part of it. Partial alpha is resolved with a hash of the ray and
+    // hit point, so the decision is stable for a given ray.
+    pub fn is_opaque(&self, ray: &Ray) -> bool {
+        let alpha = self.material.alpha(self);
+
+        if alpha >= 1. {
+            return true;
+        } else if alpha <= 0. {
+            return false;
+        }
+
+        let mut hasher = DefaultHasher::new();
+
+        for coord in &[
+            ray.origin.x,
+            ray.origin.y,
+            ray.origin.z,
+            ray.direction.x,
+            ray.direction.y,
+            ray.direction.z,
+            self.p.x,
+            self.p.y,
+            self.p.z,
+        ] {
+            coord.to_bits().hash(&mut hasher);
+        }
+
+        let u = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+
+        u < alpha
+    }
 }
 
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>>;
+}
+
+// A surface whose points can be found from their texture coordinates, so that
+// textures and lighting can be baked in its uv space.
+pub trait Surface: Hittable {
+    // The surface at `u`, `v`, or none where no part of it has them.
+    fn at(&self, u: f64, v: f64) -> Option<Hit<'_>>;
+}
+
+// Objects shared between the scene and a list of named surfaces.
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        self.as_ref().hit(ray, t_min, t_max)
+    }
+}
+
+impl<T: Bounded + ?Sized> Bounded for Arc<T> {
+    fn bounding_box(&self) -> BoundingBox {
+        self.as_ref().bounding_box()
+    }
 }
 
 pub trait Bounded {
@@ -88,7 +248,7 @@ mod tests {
         };
 
         fn is_hit(ray: &Ray) -> bool {
-            SUBJECT.hit(ray, 0., std::f64::INFINITY)
+            SUBJECT.hit(ray, 0., f64::INFINITY)
         }
 
         #[test]
@@ -96,6 +256,7 @@ mod tests {
             assert!(is_hit(&Ray {
                 origin: Point3::new(0.5, 0.5, -1.),
                 direction: Unit3::new(0., 0., 1.),
+                differentials: None,
             }))
         }
 
@@ -104,6 +265,7 @@ mod tests {
             assert!(is_hit(&Ray {
                 origin: Point3::new(0.5, 0.5, 0.5),
                 direction: Unit3::new(0., 0., 1.),
+                differentials: None,
             }))
         }
 
@@ -112,6 +274,7 @@ mod tests {
             assert!(!is_hit(&Ray {
                 origin: Point3::new(0.5, 0.5, -1.),
                 direction: Unit3::new(0., 0., -1.),
+                differentials: None,
             }))
         }
 
@@ -120,6 +283,7 @@ mod tests {
             assert!(is_hit(&Ray {
                 origin: Point3::new(0., 0., -1.),
                 direction: Unit3::new(0., 0., 1.),
+                differentials: None,
             }))
         }
 
@@ -128,7 +292,77 @@ mod tests {
             assert!(is_hit(&Ray {
                 origin: Point3::new(0., 0., 0.),
                 direction: Unit3::new(1., 1., 1.),
+                differentials: None,
             }))
         }
     }
+
+    mod differentials {
+        use super::*;
+        use crate::material::HoldoutMaterial;
+        use crate::ray::Differentials;
+
+        // A hit on the z = 0 plane, where u and v run along x and y, from a
+        // ray straight down with differentials offset by 0.1 along x and 0.2
+        // along y.
+        fn differentiate(scale: f64) -> Hit<'static> {
+            let mut ray = Ray {
+                differentials: Some(Differentials {
+                    x_origin: Point3::new(0., 0., 1.),
+                    x_direction: Vector3::new(0.1, 0., -1.).normalize(),
+                    y_origin: Point3::new(0., 0., 1.),
+                    y_direction: Vector3::new(0., 0.2, -1.).normalize(),
+                }),
+                ..Ray::new(Point3::new(0., 0., 1.), Unit3::new(0., 0., -1.))
+            };
+            let mut hit = Hit {
+                t: 1.,
+                p: Point3::new(0., 0., 0.),
+                u: 0.,
+                v: 0.,
+                normal: Unit3::new(0., 0., 1.),
+                dpdu: Vector3::new(1., 0., 0.),
+                dpdv: Vector3::new(0., 1., 0.),
+                dudx: 0.,
+                dudy: 0.,
+                dvdx: 0.,
+                dvdy: 0.,
+                exterior_refractive_index: 1.,
+                material: &HoldoutMaterial,
+                attributes: &NO_ATTRIBUTES,
+            };
+
+            ray.scale_differentials(scale);
+            hit.differentiate(&ray);
+            hit
+        }
+
+        #[test]
+        fn it_finds_the_texture_footprint_of_a_pixel() {
+            let hit = differentiate(1.);
+
+            assert!((hit.dudx - 0.1).abs() < 1e-9);
+            assert!((hit.dvdy - 0.2).abs() < 1e-9);
+            assert!(hit.dvdx.abs() < 1e-9);
+            assert!(hit.dudy.abs() < 1e-9);
+        }
+
+        #[test]
+        fn it_shrinks_with_scaled_differentials() {
+            let hit = differentiate(0.5);
+
+            assert!((hit.dudx - 0.05).abs() < 1e-3);
+            assert!((hit.dvdy - 0.1).abs() < 1e-3);
+        }
+
+        #[test]
+        fn it_is_a_point_without_differentials() {
+            let mut hit = differentiate(1.);
+
+            hit.dudx = 0.;
+            hit.differentiate(&Ray::new(Point3::new(0., 0., 1.), Unit3::new(0., 0., -1.)));
+
+            assert_eq!(hit.dudx, 0.);
+        }
+    }
 }
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::material::*;
+use crate::texture::*;
+
+// Named materials and textures, shared between the objects that use them.
+pub struct Library {
+    materials: HashMap<String, Arc<dyn Material>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+}
+
+impl Library {
+    // A library of built-in materials and textures, such as a neutral grey
+    // `clay` for lighting reviews.
+    pub fn new() -> Self {
+        let mut library = Self {
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+        };
+
+        library.add_texture("black", ConstantTexture::scalar(0.));
+        library.add_texture("grey", ConstantTexture::scalar(0.5));
+        library.add_texture("white", ConstantTexture::scalar(1.));
+        let noise = library.add_texture("noise", NoiseTexture::new(4.));
+        let marble = library.add_texture("marble", MarbleTexture::new(10.));
+
+        // Patterns built from the textures above.
+        let rust = library.add_texture(
+            "rust",
+            MixTexture {
+                first: ConstantTexture {
+                    color: Color::new(0.45, 0.2, 0.08),
+                },
+                second: ConstantTexture {
+                    color: Color::new(0.15, 0.06, 0.03),
+                },
+                factor: noise.clone(),
+            },
+        );
+        library.add_texture(
+            "verdigris",
+            HsvTexture {
+                texture: rust,
+                hue: 150.,
+                saturation: 0.8,
+                value: 1.2,
+            },
+        );
+        library.add_texture(
+            "veined_marble",
+            RampTexture {
+                texture: InvertTexture { texture: marble },
+                ramp: ColorRamp {
+                    stops: vec![
+                        (0., Color::new(0.9, 0.9, 0.88)),
+                        (0.7, Color::new(0.8, 0.8, 0.78)),
+                        (1., Color::new(0.1, 0.2, 0.15)),
+                    ],
+                },
+            },
+        );
+        library.add_texture(
+            "grime",
+            MultiplyTexture {
+                first: ConstantTexture::scalar(0.8),
+                second: AddTexture {
+                    first: ConstantTexture::scalar(0.5),
+                    second: MixTexture {
+                        first: ConstantTexture::scalar(0.5),
+                        second: noise,
+                        factor: GradientTexture {
+                            gradient: Gradient::V,
+                        },
+                    },
+                },
+            },
+        );
+        let wood = library.add_texture(
+            "wood",
+            WoodTexture::new(
+                0,
+                8.,
+                Color::new(0.6, 0.4, 0.2),
+                Color::new(0.3, 0.15, 0.05),
+            ),
+        );
+        let granite = library.add_texture(
+            "granite",
+            GraniteTexture::new(
+                0,
+                8.,
+                ColorRamp {
+                    stops: vec![
+                        (0., Color::new(0.75, 0.6, 0.55)),
+                        (0.5, Color::new(0.55, 0.5, 0.5)),
+                        (1., Color::new(0.08, 0.08, 0.08)),
+                    ],
+                },
+            ),
+        );
+        library.add_texture(
+            "sky",
+            RampTexture {
+                texture: GradientTexture {
+                    gradient: Gradient::Linear {
+                        from: Point3::new(0., 0., 0.),
+                        to: Point3::new(0., 10., 0.),
+                    },
+                },
+                ramp: ColorRamp::linear(
+                    0.,
+                    Color::new(0.9, 0.8, 0.7),
+                    1.,
+                    Color::new(0.3, 0.5, 0.9),
+                ),
+            },
+        );
+
+        // Ramps for masking and mixing other textures.
+        library.add_texture(
+            "gradient_u",
+            GradientTexture {
+                gradient: Gradient::U,
+            },
+        );
+        library.add_texture(
+            "gradient_v",
+            GradientTexture {
+                gradient: Gradient::V,
+            },
+        );
+        library.add_texture(
+            "radial",
+            InvertTexture {
+                texture: GradientTexture {
+                    gradient: Gradient::Spherical {
+                        centre: Point3::new(0., 0., 0.),
+                        radius: 1.,
+                    },
+                },
+            },
+        );
+
+        // A world-space checker for objects without texture coordinates.
+        let checker = library.add_texture(
+            "checker",
+            ProjectionTexture::new(
+                CheckerboardTexture {
+                    odd: ConstantTexture::scalar(0.2),
+                    even: ConstantTexture::scalar(0.8),
+                    width: 0.25,
+                },
+                Projection::Triplanar { sharpness: 8. },
+            ),
+        );
+        // A random colour for each object with an id, such as to tell
+        // apart scattered objects.
+        let object_random = library.add_texture("object_random", ObjectRandomTexture { seed: 0 });
+        // The same as a grey, such as to vary roughness between objects.
+        library.add_texture(
+            "object_random_grey",
+            GreyTexture {
+                texture: object_random.clone(),
+            },
+        );
+
+        library.add_material(
+            "clay",
+            LambertianMaterial {
+                texture: ConstantTexture::scalar(0.5),
+            },
+        );
+        library.add_material("checker", LambertianMaterial { texture: checker });
+        library.add_material(
+            "object_random",
+            LambertianMaterial {
+                texture: object_random,
+            },
+        );
+        library.add_material("wood", LambertianMaterial { texture: wood });
+        library.add_material("granite", LambertianMaterial { texture: granite });
+        library.add_material(
+            "white",
+            LambertianMaterial {
+                texture: ConstantTexture::scalar(0.8),
+            },
+        );
+        library.add_material(
+            "mirror",
+            ReflectiveMaterial {
+                texture: ConstantTexture::scalar(1.),
+            },
+        );
+        library.add_material(
+            "glass",
+            DielectricMaterial {
+                texture: ConstantTexture {
+                    color: Color::new(1., 1., 1.),
+                },
+                refractive_index: 1.5,
+                priority: 0,
+                thin_film: None,
+                thin_walled: false,
+            },
+        );
+        library.add_material("gold", ConductorMaterial::gold(0.3));
+        library.add_material("copper", ConductorMaterial::copper(0.3));
+        library.add_material("silver", ConductorMaterial::silver(0.3));
+        library.add_material("aluminium", ConductorMaterial::aluminium(0.3));
+        library.add_material(
+            "brushed_aluminium",
+            BrushedMetalMaterial::new(ConductorMaterial::aluminium(0.), 0.1, 0.5),
+        );
+        library.add_material(
+            "velvet",
+            SheenMaterial {
+                diffuse: ConstantTexture {
+                    color: Color::new(0.3, 0.02, 0.05),
+                },
+                sheen: ConstantTexture {
+                    color: Color::new(1., 0.6, 0.6),
+                },
+                roughness: ConstantTexture::scalar(0.6),
+            },
+        );
+        library.add_material("hair", HairMaterial::from_melanin(1.3, 0., 0.3, 0.3));
+        library.add_material(
+            "auburn_hair",
+            HairMaterial::from_color(Color::new(0.45, 0.15, 0.05), 0.3, 0.3),
+        );
+
+        library
+    }
+
+    pub fn add_material(
+        &mut self,
+        name: &str,
+        material: impl Material + 'static,
+    ) -> Arc<dyn Material> {
+        let material: Arc<dyn Material> = Arc::new(material);
+
+        self.materials.insert(name.to_string(), material.clone());
+
+        material
+    }
+
+    pub fn add_texture(&mut self, name: &str, texture: impl Texture + 'static) -> Arc<dyn Texture> {
+        let texture: Arc<dyn Texture> = Arc::new(texture);
+
+        self.textures.insert(name.to_string(), texture.clone());
+
+        texture
+    }
+
+    pub fn material(&self, name: &str) -> Option<Arc<dyn Material>> {
+        self.materials.get(name).cloned()
+    }
+
+    pub fn texture(&self, name: &str) -> Option<Arc<dyn Texture>> {
+        self.textures.get(name).cloned()
+    }
+
+    pub fn material_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.materials.keys().map(String::as_str).collect();
+
+        names.sort_unstable();
+        names
+    }
+
+    pub fn texture_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.textures.keys().map(String::as_str).collect();
+
+        names.sort_unstable();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_looks_up_entries_by_name() {
+        let library = Library::new();
+
+        assert!(library.material("clay").is_some());
+        assert!(library.texture("marble").is_some());
+        assert!(library.material("marble").is_none());
+        assert!(library.material_names().contains(&"gold"));
+    }
+
+    #[test]
+    fn it_shares_entries() {
+        let mut library = Library::new();
+        let added = library.add_texture("red", ConstantTexture::scalar(0.25));
+        let found = library.texture("red").unwrap();
+
+        assert!(Arc::ptr_eq(&added, &found));
+        assert_eq!(found.value(0., 0., &Point3::new(0., 0., 0.)), 0.25);
+    }
+
+    #[test]
+    fn it_replaces_entries_with_the_same_name() {
+        let mut library = Library::new();
+        let replacement = library.add_material("clay", HoldoutMaterial);
+
+        assert!(Arc::ptr_eq(
+            &replacement,
+            &library.material("clay").unwrap()
+        ));
+        assert_eq!(
+            library.material("clay").unwrap().matte(),
+            Some(Matte::Holdout)
+        );
+    }
+}
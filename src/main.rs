@@ -1,3 +1,4 @@
+mod bake;
 mod bvh;
 mod camera;
 mod cli;
@@ -5,27 +6,132 @@ mod color;
 mod config;
 mod geometry;
 mod hittable;
+mod library;
 mod material;
+mod medium;
+mod mipmap;
+mod noise;
 mod object;
 mod perlin;
 mod ray;
+mod sampling;
 mod scene;
 mod texture;
+mod tile_cache;
 
+use std::path::Path;
+use std::sync::Arc;
+
+use bake::{Bake, Baked};
 use camera::Camera;
+use config::{BakeConfig, Command, Config};
 use geometry::*;
+use library::Library;
+use material::{LambertianMaterial, Material, MeasuredMaterial, NormalMapMaterial};
+use mipmap::{Filter, Wrap};
 use scene::Scene;
+use texture::{CloudsTexture, ColorSpace, ImageTexture, ScalarImageTexture, Texture, TiledTexture};
+use tile_cache::TileCache;
 
 #[allow(dead_code)]
 fn main() {
-    let config = cli::config();
+    match cli::command() {
+        Command::Render(config) => render(&config),
+        Command::Tile(config) => {
+            if let Err(error) = texture::tiled::convert(
+                &config.infile,
+                &config.outfile,
+                config.color_space,
+                config.tile_size,
+            ) {
+                println!("Couldn't convert '{}': {}", config.infile, error);
+            }
+        }
+        Command::Bake(config) => bake(&config),
+    }
+}
+
+fn render(config: &Config) {
+    let camera = camera(config.time, config.resolution);
+    let mut library = library(
+        config.time,
+        config.texture_filter,
+        config.texture_wrap,
+        config.texture_cache,
+    );
+    let mut scene: Scene = Scene::new(&mut library, config.transparent);
+
+    scene.override_material = config
+        .override_material
+        .as_ref()
+        .and_then(|name| override_material(&library, name));
+
+    camera.capture(&scene, config.samples, config.transparent, &config.outfile)
+}
 
+fn bake(config: &BakeConfig) {
+    let camera = camera(config.time, config.resolution);
+    let mut library = library(config.time, Filter::Ewa, Wrap::Repeat, config.texture_cache);
+    let scene = Scene::new(&mut library, false);
+
+    let surface = match scene.surfaces.get(&config.object) {
+        Some(surface) => surface,
+        None => {
+            println!(
+                "Unknown object '{}' (expected one of {})",
+                config.object,
+                scene.surface_names().join(", ")
+            );
+            return;
+        }
+    };
+    let bake = match config.bake.as_str() {
+        "occlusion" => Bake::Occlusion {
+            distance: config.occlusion_distance,
+        },
+        "irradiance" => Bake::Irradiance,
+        name => match library.texture(name) {
+            Some(texture) => Bake::Texture(texture),
+            None => {
+                println!(
+                    "Unknown bake '{}' (expected occlusion, irradiance or one of {})",
+                    name,
+                    library.texture_names().join(", ")
+                );
+                return;
+            }
+        },
+    };
+
+    let (width, height) = config.resolution;
+    let mut baked = Baked::new(
+        &camera,
+        &scene,
+        surface.as_ref(),
+        &bake,
+        (width as usize, height as usize),
+        config.samples,
+    );
+
+    // Lighting can be brighter than one, so it is kept in floats.
+    let mut outfile = Path::new(&config.outfile).to_path_buf();
+
+    if !matches!(bake, Bake::Texture(_)) && !bake::is_hdr(&outfile) {
+        outfile.set_extension("hdr");
+        println!(
+            "Writing lighting as Radiance HDR to '{}'",
+            outfile.display()
+        );
+    }
+
+    baked.dilate(config.dilation);
+    baked.save(&outfile).expect("Saving image failed");
+}
+
+fn camera(time: f64, (img_x, img_y): (u32, u32)) -> Camera {
     let look_from = Point3::new(0.0, 2.8, 0.3);
     let look_at = Point3::new(1.0, 0.8, 5.0);
 
-    let (img_x, img_y) = config.resolution;
-
-    let time = config.time;
     let theta = 2. * std::f64::consts::PI * time / 10.;
     let cos = theta.cos();
     let sin = theta.sin();
@@ -34,7 +140,7 @@ fn main() {
     let rotated_look_from =
         look_at + Vector3::new(c.x * cos - c.z * sin, c.y - sin, c.x * sin + c.z * cos);
 
-    let camera = Camera::new(
+    Camera::new(
         rotated_look_from,
         look_at,
         60.0,
@@ -42,12 +148,78 @@ fn main() {
         (look_at - look_from).length(),
         img_x,
         img_y,
+    )
+}
+
+// The library with the textures the scene expects, looked up with `filter`
+// and extended past their edges by `wrap`. `cache_size` is the memory
+// budget for tiled textures in bytes.
+fn library(time: f64, filter: Filter, wrap: Wrap, cache_size: usize) -> Library {
+    let cache = Arc::new(TileCache::new(cache_size));
+    let mut library = Library::new();
+
+    library.add_texture(
+        "earth",
+        open_texture("resources/earth.png", &cache, filter, wrap),
+    );
+    let moon = library.add_texture(
+        "moon",
+        open_texture("resources/moon.jpg", &cache, filter, wrap),
+    );
+    // The brightness of the moon's surface is a fair stand in for its height.
+    library.add_texture(
+        "moon_height",
+        ScalarImageTexture::open("resources/moon.jpg", 0, ColorSpace::Raw, filter, wrap).unwrap(),
     );
 
-    let earth = image::open("resources/earth.png").unwrap();
-    let moon = image::open("resources/moon.jpg").unwrap();
+    library.add_texture("clouds", CloudsTexture::new(0, 0.5, time));
+    // The moon's surface with its craters shaded from a normal map.
+    library.add_material(
+        "moon_rock",
+        NormalMapMaterial::open(
+            LambertianMaterial { texture: moon },
+            "resources/moon_normal.png",
+            filter,
+        )
+        .unwrap(),
+    );
+
+    library
+}
+
+// Opens the sRGB image at `path`, or the tiled texture beside it with the same
+// name if one has been made with `raygrass tile`.
+fn open_texture(
+    path: &str,
+    cache: &Arc<TileCache>,
+    filter: Filter,
+    wrap: Wrap,
+) -> Arc<dyn Texture> {
+    let tiled = Path::new(path).with_extension(texture::tiled::EXTENSION);
+
+    if tiled.exists() {
+        Arc::new(TiledTexture::open(tiled, cache, filter, wrap).unwrap())
+    } else {
+        Arc::new(ImageTexture::open(path, ColorSpace::Srgb, filter, wrap).unwrap())
+    }
+}
 
-    let scene: Scene = Scene::new(earth, moon);
+// Looks up a material by name in the library, or loads a measured BRDF from
+// a file of that name.
+fn override_material(library: &Library, name: &str) -> Option<Arc<dyn Material>> {
+    if let Some(material) = library.material(name) {
+        return Some(material);
+    }
 
-    camera.capture(&scene, config.samples, &config.outfile)
+    match MeasuredMaterial::open(name) {
+        Ok(material) => Some(Arc::new(material)),
+        Err(_) => {
+            println!(
+                "Unknown material '{}' (expected one of {} or a MERL BRDF file)",
+                name,
+                library.material_names().join(", ")
+            );
+            None
+        }
+    }
 }
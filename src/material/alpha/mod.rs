@@ -0,0 +1,51 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfSample, Material, Matte};
+use crate::medium::Medium;
+use crate::texture::Texture;
+
+// Masks out parts of `material` where `alpha` is below one. Partial values
+// are treated as the probability that a ray finds the surface there.
+pub struct AlphaMaterial<M: Material, T: Texture> {
+    pub material: M,
+    pub alpha: T,
+}
+
+impl<M: Material, T: Texture> Material for AlphaMaterial<M, T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.material.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.material.sample(hit, wo, u)
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.material.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.material.interior()
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.material.refractive_index()
+    }
+
+    fn priority(&self) -> u32 {
+        self.material.priority()
+    }
+
+    fn matte(&self) -> Option<Matte> {
+        self.material.matte()
+    }
+
+    fn frame(&self, hit: &Hit) -> Frame {
+        self.material.frame(hit)
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        self.alpha.value_at(hit) * self.material.alpha(hit)
+    }
+}
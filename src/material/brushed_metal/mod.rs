@@ -0,0 +1,54 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfSample, ConductorMaterial, Material};
+use crate::texture::Texture;
+
+// An anisotropic conductor whose roughness along x of the shading frame runs
+// with the brushing direction. The direction follows `dpdu`, or a flow map
+// where red and green encode it along `dpdu` and `dpdv`, as in a normal map.
+pub struct BrushedMetalMaterial {
+    pub metal: ConductorMaterial,
+    pub flow: Option<Box<dyn Texture>>,
+}
+
+impl BrushedMetalMaterial {
+    // Brushing leaves grooves along the direction of the strokes, so the
+    // surface is smoother along them than across them.
+    pub fn new(metal: ConductorMaterial, roughness_along: f64, roughness_across: f64) -> Self {
+        Self {
+            metal: ConductorMaterial::anisotropic(
+                metal.eta,
+                metal.k,
+                roughness_along,
+                roughness_across,
+            ),
+            flow: None,
+        }
+    }
+}
+
+impl Material for BrushedMetalMaterial {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.metal.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.metal.sample(hit, wo, u)
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.metal.pdf(hit, wo, wi)
+    }
+
+    fn frame(&self, hit: &Hit) -> Frame {
+        let frame = hit.frame();
+        let flow = match &self.flow {
+            Some(flow) => flow.color_at(hit),
+            None => return frame,
+        };
+        let tangent = frame.s * (2. * flow.r - 1.) + frame.t * (2. * flow.g - 1.);
+
+        Frame::from_normal_and_tangent(frame.n, tangent)
+    }
+}
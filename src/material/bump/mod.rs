@@ -0,0 +1,160 @@
+use image::{DynamicImage, ImageResult};
+use std::path::Path;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfSample, Material, Matte};
+use crate::medium::Medium;
+use crate::mipmap::{Filter, Wrap};
+use crate::texture::{ColorSpace, ImageTexture, Texture};
+
+const BUMP_DELTA: f64 = 0.0005;
+
+// Perturbs the shading normal of `material` as if the surface were displaced
+// along its normal by `scale` times the value of `bump`.
+pub struct BumpMaterial<M: Material, T: Texture> {
+    pub material: M,
+    pub bump: T,
+    pub scale: f64,
+}
+
+impl<M: Material, T: Texture> BumpMaterial<M, T> {
+    // The displacement at `hit` moved by `du` and `dv`, looked up with the
+    // hit's footprint so that filtered and attribute textures bump too.
+    fn displacement(&self, hit: &Hit, du: f64, dv: f64) -> f64 {
+        let moved = Hit {
+            u: hit.u + du,
+            v: hit.v + dv,
+            p: hit.p + hit.dpdu * du + hit.dpdv * dv,
+            ..*hit
+        };
+
+        self.scale * self.bump.value_at(&moved)
+    }
+}
+
+impl<M: Material, T: Texture> Material for BumpMaterial<M, T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.material.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.material.sample(hit, wo, u)
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.material.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.material.interior()
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.material.refractive_index()
+    }
+
+    fn priority(&self) -> u32 {
+        self.material.priority()
+    }
+
+    fn matte(&self) -> Option<Matte> {
+        self.material.matte()
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        self.material.alpha(hit)
+    }
+
+    fn frame(&self, hit: &Hit) -> Frame {
+        let displacement = self.displacement(hit, 0., 0.);
+        let u_displacement = self.displacement(hit, BUMP_DELTA, 0.);
+        let v_displacement = self.displacement(hit, 0., BUMP_DELTA);
+
+        let dpdu = hit.dpdu + hit.normal * ((u_displacement - displacement) / BUMP_DELTA);
+        let dpdv = hit.dpdv + hit.normal * ((v_displacement - displacement) / BUMP_DELTA);
+        let perturbed = dpdu * dpdv;
+
+        if perturbed.length_squared() == 0. {
+            return hit.frame();
+        }
+
+        let mut normal = perturbed.normalize();
+
+        if normal.dot(hit.normal) < 0. {
+            normal = normal.reverse();
+        }
+
+        Frame::from_normal_and_tangent(normal, dpdu)
+    }
+}
+
+// Replaces the shading normal of `material` with one read from a
+// tangent-space normal map, where red, green and blue encode the offsets
+// along `dpdu`, `dpdv` and the surface normal, scaled from [-1, 1] to [0, 1].
+pub struct NormalMapMaterial<M: Material> {
+    pub material: M,
+    pub normal_map: ImageTexture,
+}
+
+impl<M: Material> NormalMapMaterial<M> {
+    // Normal maps hold directions rather than colours, so they are read raw.
+    pub fn new(material: M, image: &DynamicImage, filter: Filter) -> Self {
+        Self {
+            material,
+            normal_map: ImageTexture::new(image, ColorSpace::Raw, filter, Wrap::Repeat),
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(material: M, path: P, filter: Filter) -> ImageResult<Self> {
+        Ok(Self::new(material, &image::open(path)?, filter))
+    }
+}
+
+impl<M: Material> Material for NormalMapMaterial<M> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.material.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.material.sample(hit, wo, u)
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.material.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.material.interior()
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.material.refractive_index()
+    }
+
+    fn priority(&self) -> u32 {
+        self.material.priority()
+    }
+
+    fn matte(&self) -> Option<Matte> {
+        self.material.matte()
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        self.material.alpha(hit)
+    }
+
+    // Normals pointing below the surface are kept just above it.
+    fn frame(&self, hit: &Hit) -> Frame {
+        let color = self.normal_map.color_at(hit);
+        let tangent_space = Unit3::new(
+            2. * color.r - 1.,
+            2. * color.g - 1.,
+            (2. * color.b - 1.).max(1e-3),
+        );
+        let normal = hit.frame().to_world(tangent_space);
+
+        Frame::from_normal_and_tangent(normal, hit.dpdu)
+    }
+}
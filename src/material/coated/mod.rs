@@ -0,0 +1,181 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{
+    fresnel_dielectric, reflect, reflect_about, same_hemisphere, upper, BsdfFlags, BsdfSample,
+    Material, Matte, MicrofacetDielectric, TrowbridgeReitz,
+};
+use crate::medium::Medium;
+use crate::texture::Texture;
+
+// A dielectric coating over an arbitrary base material. Light reaching the
+// base is weighted by the Fresnel transmittance through the coating on the
+// way in and out, and by absorption along the refracted paths through a
+// coating of the given `thickness`. Inter-reflection between the coating
+// and the base is ignored, so the layer never gains energy.
+pub struct CoatedMaterial<M: Material, T: Texture> {
+    pub base: M,
+    pub roughness: T,
+    pub refractive_index: f64,
+    pub thickness: f64,
+    pub absorption: Option<Medium>,
+}
+
+impl<M: Material, T: Texture> CoatedMaterial<M, T> {
+    fn coating(&self, hit: &Hit) -> MicrofacetDielectric {
+        MicrofacetDielectric {
+            distribution: TrowbridgeReitz::new(self.roughness.value_at(hit)),
+            refractive_index: self.refractive_index,
+        }
+    }
+
+    fn fresnel(&self, w: Unit3) -> f64 {
+        fresnel_dielectric(w.z.abs(), self.refractive_index)
+    }
+
+    fn path_length(&self, w: Unit3) -> f64 {
+        let sin2_t = (1. - w.z * w.z) / self.refractive_index.powi(2);
+
+        self.thickness / (1. - sin2_t).max(1e-6).sqrt()
+    }
+
+    fn base_weight(&self, wo: Unit3, wi: Unit3) -> Color {
+        let transmission = (1. - self.fresnel(wo)) * (1. - self.fresnel(wi));
+        let absorption = match self.absorption {
+            Some(medium) => medium.transmittance(self.path_length(wo) + self.path_length(wi)),
+            None => Color::new(1., 1., 1.),
+        };
+
+        absorption.scale(transmission)
+    }
+
+    fn coating_eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let coating = self.coating(hit);
+
+        if coating.distribution.is_smooth() || !same_hemisphere(wo, wi) {
+            0.
+        } else {
+            coating.eval(upper(wo), upper(wi))
+        }
+    }
+
+    fn coating_pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let coating = self.coating(hit);
+        let half = Vector3::from(upper(wo)) + Vector3::from(upper(wi));
+
+        if coating.distribution.is_smooth()
+            || !same_hemisphere(wo, wi)
+            || half.length_squared() == 0.
+        {
+            return 0.;
+        }
+
+        let wm = half.normalize();
+
+        coating.distribution.pdf(upper(wo), wm) / (4. * upper(wo).dot(wm).abs())
+    }
+}
+
+impl<M: Material, T: Texture> Material for CoatedMaterial<M, T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        let coating = self.coating_eval(hit, wo, wi);
+
+        self.base
+            .eval(hit, wo, wi)
+            .mul(self.base_weight(wo, wi))
+            .add(Color::new(coating, coating, coating))
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let coating_probability = self.fresnel(wo);
+
+        if u[0] < coating_probability {
+            let coating = self.coating(hit);
+
+            if coating.distribution.is_smooth() {
+                let wi = reflect(wo);
+
+                return Some(BsdfSample {
+                    wi,
+                    f: Color::new(1., 1., 1.).scale(coating_probability / wi.z.abs()),
+                    pdf: coating_probability,
+                    flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+                });
+            }
+
+            let wm = coating.distribution.sample_wm(upper(wo), (u[1], u[2]));
+            let mut wi = reflect_about(upper(wo), wm);
+
+            if wo.z < 0. {
+                wi = Unit3 { z: -wi.z, ..wi };
+            }
+
+            let pdf = self.pdf(hit, wo, wi);
+
+            return if pdf > 0. {
+                Some(BsdfSample {
+                    wi,
+                    f: self.eval(hit, wo, wi),
+                    pdf,
+                    flags: BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+                })
+            } else {
+                None
+            };
+        }
+
+        let u0 = ((u[0] - coating_probability) / (1. - coating_probability)).min(1. - f64::EPSILON);
+        let sample = self.base.sample(hit, wo, [u0, u[1], u[2]])?;
+
+        if sample.flags.contains(BsdfFlags::SPECULAR) {
+            return Some(BsdfSample {
+                f: sample.f.mul(self.base_weight(wo, sample.wi)),
+                pdf: sample.pdf * (1. - coating_probability),
+                ..sample
+            });
+        }
+
+        let pdf = self.pdf(hit, wo, sample.wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                f: self.eval(hit, wo, sample.wi),
+                pdf,
+                ..sample
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let coating_probability = self.fresnel(wo);
+
+        coating_probability * self.coating_pdf(hit, wo, wi)
+            + (1. - coating_probability) * self.base.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.base.interior()
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.base.refractive_index()
+    }
+
+    fn priority(&self) -> u32 {
+        self.base.priority()
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        self.base.alpha(hit)
+    }
+
+    fn frame(&self, hit: &Hit) -> Frame {
+        self.base.frame(hit)
+    }
+
+    fn matte(&self) -> Option<Matte> {
+        self.base.matte()
+    }
+}
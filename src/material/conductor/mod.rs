@@ -0,0 +1,150 @@
+use num_complex::Complex64;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{
+    fresnel_complex, reflect, reflect_about, same_hemisphere, BsdfFlags, BsdfSample, Material,
+    TrowbridgeReitz,
+};
+
+pub struct ConductorMaterial {
+    pub eta: Color,
+    pub k: Color,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl ConductorMaterial {
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Self {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::new(roughness),
+        }
+    }
+
+    pub fn anisotropic(eta: Color, k: Color, roughness_u: f64, roughness_v: f64) -> Self {
+        Self {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::anisotropic(roughness_u, roughness_v),
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.386, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.146),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn fresnel(&self, cos_i: f64) -> Color {
+        Color::new(
+            fresnel_complex(cos_i, Complex64::new(self.eta.r, self.k.r)),
+            fresnel_complex(cos_i, Complex64::new(self.eta.g, self.k.g)),
+            fresnel_complex(cos_i, Complex64::new(self.eta.b, self.k.b)),
+        )
+    }
+}
+
+impl Material for ConductorMaterial {
+    fn eval(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        if !same_hemisphere(wo, wi) || self.distribution.is_smooth() {
+            return Color::new(0., 0., 0.);
+        }
+
+        let cos_o = wo.z.abs();
+        let cos_i = wi.z.abs();
+        let half = Vector3::from(wo) + Vector3::from(wi);
+
+        if cos_o == 0. || cos_i == 0. || half.length_squared() == 0. {
+            return Color::new(0., 0., 0.);
+        }
+
+        let wm = half.normalize();
+
+        self.fresnel(wo.dot(wm).abs())
+            .scale(self.distribution.d(wm) * self.distribution.g(wo, wi) / (4. * cos_i * cos_o))
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        if self.distribution.is_smooth() {
+            let wi = reflect(wo);
+
+            if wi.z == 0. {
+                return None;
+            }
+
+            return Some(BsdfSample {
+                wi,
+                f: self.fresnel(wi.z.abs()).scale(1. / wi.z.abs()),
+                pdf: 1.,
+                flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+            });
+        }
+
+        if wo.z == 0. {
+            return None;
+        }
+
+        let wm = self.distribution.sample_wm(wo, (u[1], u[2]));
+        let wi = reflect_about(wo, wm);
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags: BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        if !same_hemisphere(wo, wi) || self.distribution.is_smooth() {
+            return 0.;
+        }
+
+        let half = Vector3::from(wo) + Vector3::from(wi);
+
+        if half.length_squared() == 0. {
+            return 0.;
+        }
+
+        let mut wm = half.normalize();
+
+        if wm.z < 0. {
+            wm = wm.reverse();
+        }
+
+        self.distribution.pdf(wo, wm) / (4. * wo.dot(wm).abs())
+    }
+}
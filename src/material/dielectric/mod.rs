@@ -0,0 +1,131 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{
+    fresnel_dielectric, reflect, refract, BsdfFlags, BsdfSample, Material, ThinFilm,
+};
+use crate::texture::Texture;
+
+pub struct DielectricMaterial<T: Texture> {
+    pub texture: T,
+    pub refractive_index: f64,
+    pub priority: u32,
+    pub thin_film: Option<ThinFilm>,
+    pub thin_walled: bool,
+}
+
+// The reflectance of a dielectric interface of relative index `eta`. A
+// thin-walled interface is a slab with two parallel faces, and reflects the
+// sum of the inter-reflections between them.
+pub fn dielectric_reflectance(
+    hit: &Hit,
+    cos_i: f64,
+    eta: f64,
+    thin_film: &Option<ThinFilm>,
+    thin_walled: bool,
+) -> Color {
+    let reflectance = match thin_film {
+        Some(film) => film.reflectance(hit, cos_i, eta),
+        None => {
+            let reflectance = fresnel_dielectric(cos_i, eta);
+
+            Color::new(reflectance, reflectance, reflectance)
+        }
+    };
+
+    if thin_walled {
+        let slab = |r: f64| 2. * r / (1. + r);
+
+        Color::new(
+            slab(reflectance.r),
+            slab(reflectance.g),
+            slab(reflectance.b),
+        )
+    } else {
+        reflectance
+    }
+}
+
+// Samples specular reflection or transmission through a smooth dielectric
+// interface, choosing between them in proportion to the luminance of the
+// reflectance. Thin-walled interfaces transmit without refraction.
+pub fn sample_smooth_dielectric(
+    wo: Unit3,
+    eta: f64,
+    reflectance: Color,
+    thin_walled: bool,
+    u: f64,
+) -> Option<BsdfSample> {
+    let probability = reflectance.luminance();
+    let transmitted = if thin_walled {
+        Some(wo.reverse())
+    } else {
+        refract(wo, eta).map(|(wi, _)| wi)
+    };
+
+    match transmitted {
+        Some(wi) if u >= probability => Some(BsdfSample {
+            wi,
+            f: Color::new(1., 1., 1.)
+                .add(reflectance.scale(-1.))
+                .scale(1. / wi.z.abs()),
+            pdf: 1. - probability,
+            flags: BsdfFlags::SPECULAR | BsdfFlags::TRANSMISSION,
+        }),
+        _ => {
+            let wi = reflect(wo);
+
+            if wi.z == 0. {
+                return None;
+            }
+
+            // Without a transmitted direction every sample reflects.
+            let pdf = if transmitted.is_some() {
+                probability
+            } else {
+                1.
+            };
+
+            Some(BsdfSample {
+                wi,
+                f: reflectance.scale(1. / wi.z.abs()),
+                pdf,
+                flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+            })
+        }
+    }
+}
+
+impl<T: Texture> Material for DielectricMaterial<T> {
+    fn eval(&self, _hit: &Hit, _wo: Unit3, _wi: Unit3) -> Color {
+        Color::new(0., 0., 0.)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let color = self.texture.color_at(hit);
+        let eta = self.refractive_index / hit.exterior_refractive_index;
+        let reflectance = dielectric_reflectance(hit, wo.z, eta, &self.thin_film, self.thin_walled);
+        let sample = sample_smooth_dielectric(wo, eta, reflectance, self.thin_walled, u[0])?;
+
+        Some(BsdfSample {
+            f: sample.f.mul(color),
+            ..sample
+        })
+    }
+
+    fn pdf(&self, _hit: &Hit, _wo: Unit3, _wi: Unit3) -> f64 {
+        0.
+    }
+
+    fn refractive_index(&self) -> f64 {
+        if self.thin_walled {
+            1.
+        } else {
+            self.refractive_index
+        }
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+}
@@ -0,0 +1,424 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{fresnel_dielectric, same_hemisphere, BsdfFlags, BsdfSample, Material};
+
+// The number of scattering paths modelled individually: reflection (R),
+// transmission through the fibre (TT) and internal reflection (TRT). Longer
+// paths are gathered into one final, isotropic term.
+const P_MAX: usize = 3;
+
+// Scattering from hair and fur after Marschner et al. 2003, "Light Scattering
+// from Human Hair Fibers", in the energy-conserving form of Chiang et al.
+// 2016, "A Practical and Controllable Hair and Fur Model for Production Path
+// Tracing".
+//
+// The fibre runs along x of the shading frame, and `v` gives the offset of the
+// hit across its width, from -1 at one edge to 1 at the other. `beta_m` and
+// `beta_n` are the longitudinal and azimuthal roughness, and `alpha` is the
+// tilt of the cuticle scales in degrees.
+pub struct HairMaterial {
+    pub sigma_a: Color,
+    pub beta_m: f64,
+    pub beta_n: f64,
+    pub alpha: f64,
+    pub refractive_index: f64,
+}
+
+impl HairMaterial {
+    pub fn new(sigma_a: Color, beta_m: f64, beta_n: f64) -> Self {
+        Self {
+            sigma_a,
+            beta_m,
+            beta_n,
+            alpha: 2.,
+            refractive_index: 1.55,
+        }
+    }
+
+    // Absorption from concentrations of the pigments in human hair, from
+    // about 0 for blonde to 8 for black eumelanin, and 0 to 1 pheomelanin for
+    // red hair.
+    pub fn from_melanin(eumelanin: f64, pheomelanin: f64, beta_m: f64, beta_n: f64) -> Self {
+        Self::new(
+            Color::new(0.419, 0.697, 1.37)
+                .scale(eumelanin)
+                .add(Color::new(0.187, 0.4, 1.05).scale(pheomelanin)),
+            beta_m,
+            beta_n,
+        )
+    }
+
+    // Absorption that gives roughly the colour `color` after multiple
+    // scattering between many fibres.
+    pub fn from_color(color: Color, beta_m: f64, beta_n: f64) -> Self {
+        let beta_n2 = beta_n * beta_n;
+        let sigma_a = |c: f64| {
+            (c.ln()
+                / (5.969 - 0.215 * beta_n + 2.532 * beta_n2 - 10.73 * beta_n2 * beta_n
+                    + 5.574 * beta_n2 * beta_n2
+                    + 0.245 * beta_n2 * beta_n2 * beta_n))
+                .powi(2)
+        };
+
+        Self::new(
+            Color::new(sigma_a(color.r), sigma_a(color.g), sigma_a(color.b)),
+            beta_m,
+            beta_n,
+        )
+    }
+
+    fn fibre(&self, hit: &Hit) -> Fibre {
+        let h = (2. * hit.v - 1.).clamp(-1., 1.);
+        let beta_m = self.beta_m;
+        let beta_n = self.beta_n;
+        let v0 = (0.726 * beta_m + 0.812 * beta_m.powi(2) + 3.7 * beta_m.powi(20)).powi(2);
+        let mut sin_2k_alpha = [0.; 3];
+        let mut cos_2k_alpha = [0.; 3];
+
+        sin_2k_alpha[0] = self.alpha.to_radians().sin();
+        cos_2k_alpha[0] = safe_sqrt(1. - sin_2k_alpha[0].powi(2));
+
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2. * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+
+        Fibre {
+            h,
+            gamma_o: h.asin(),
+            eta: self.refractive_index,
+            sigma_a: self.sigma_a,
+            v: [v0, 0.25 * v0, 4. * v0, 4. * v0],
+            s: (PI / 8.).sqrt()
+                * (0.265 * beta_n + 1.194 * beta_n.powi(2) + 5.372 * beta_n.powi(22)),
+            sin_2k_alpha,
+            cos_2k_alpha,
+        }
+    }
+}
+
+// The parameters of a hair at a particular offset across its width.
+struct Fibre {
+    h: f64,
+    gamma_o: f64,
+    eta: f64,
+    sigma_a: Color,
+    v: [f64; P_MAX + 1],
+    s: f64,
+    sin_2k_alpha: [f64; 3],
+    cos_2k_alpha: [f64; 3],
+}
+
+impl Fibre {
+    // The angle of the refracted ray inside the fibre, seen along it, and the
+    // transmittance along one pass through it.
+    fn transmission(&self, sin_theta_o: f64, cos_theta_o: f64) -> (f64, Color) {
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = safe_sqrt(1. - sin_theta_t.powi(2));
+        let eta_p = (self.eta * self.eta - sin_theta_o.powi(2)).sqrt() / cos_theta_o;
+        let sin_gamma_t = (self.h / eta_p).clamp(-1., 1.);
+        let cos_gamma_t = safe_sqrt(1. - sin_gamma_t.powi(2));
+        let distance = 2. * cos_gamma_t / cos_theta_t;
+        let transmittance = Color::new(
+            (-self.sigma_a.r * distance).exp(),
+            (-self.sigma_a.g * distance).exp(),
+            (-self.sigma_a.b * distance).exp(),
+        );
+
+        (sin_gamma_t.asin(), transmittance)
+    }
+
+    // The attenuation along each path, from Fresnel reflection and absorption.
+    fn attenuation(&self, cos_theta_o: f64, transmittance: Color) -> [Color; P_MAX + 1] {
+        let cos_gamma_o = safe_sqrt(1. - self.h * self.h);
+        let fresnel = fresnel_dielectric(cos_theta_o * cos_gamma_o, self.eta);
+        let f = Color::new(fresnel, fresnel, fresnel);
+        let mut attenuation = [Color::new(0., 0., 0.); P_MAX + 1];
+
+        attenuation[0] = f;
+        attenuation[1] = transmittance.scale((1. - fresnel).powi(2));
+
+        for p in 2..P_MAX {
+            attenuation[p] = attenuation[p - 1].mul(transmittance).mul(f);
+        }
+
+        let tf = transmittance.mul(f);
+
+        attenuation[P_MAX] = attenuation[P_MAX - 1].mul(Color::new(
+            tf.r / (1. - tf.r),
+            tf.g / (1. - tf.g),
+            tf.b / (1. - tf.b),
+        ));
+
+        attenuation
+    }
+
+    // The probability of sampling each path, in proportion to its attenuation.
+    fn path_pdf(&self, cos_theta_o: f64) -> [f64; P_MAX + 1] {
+        let sin_theta_o = safe_sqrt(1. - cos_theta_o * cos_theta_o);
+        let (_, transmittance) = self.transmission(sin_theta_o, cos_theta_o);
+        let attenuation = self.attenuation(cos_theta_o, transmittance);
+        let total: f64 = attenuation.iter().map(Color::luminance).sum();
+        let mut pdf = [0.; P_MAX + 1];
+
+        for p in 0..=P_MAX {
+            pdf[p] = attenuation[p].luminance() / total;
+        }
+
+        pdf
+    }
+
+    // The outgoing longitudinal angle tilted by the cuticle scales for each
+    // path, which shift the lobes in alternating directions.
+    fn tilt(&self, p: usize, sin_theta_o: f64, cos_theta_o: f64) -> (f64, f64) {
+        let (sin_theta_op, cos_theta_op) = match p {
+            0 => (
+                sin_theta_o * self.cos_2k_alpha[1] - cos_theta_o * self.sin_2k_alpha[1],
+                cos_theta_o * self.cos_2k_alpha[1] + sin_theta_o * self.sin_2k_alpha[1],
+            ),
+            1 => (
+                sin_theta_o * self.cos_2k_alpha[0] + cos_theta_o * self.sin_2k_alpha[0],
+                cos_theta_o * self.cos_2k_alpha[0] - sin_theta_o * self.sin_2k_alpha[0],
+            ),
+            2 => (
+                sin_theta_o * self.cos_2k_alpha[2] + cos_theta_o * self.sin_2k_alpha[2],
+                cos_theta_o * self.cos_2k_alpha[2] - sin_theta_o * self.sin_2k_alpha[2],
+            ),
+            _ => (sin_theta_o, cos_theta_o),
+        };
+
+        (sin_theta_op, cos_theta_op.abs())
+    }
+
+    fn phi(&self, p: usize, gamma_t: f64) -> f64 {
+        let p = p as f64;
+
+        2. * p * gamma_t - 2. * self.gamma_o + p * PI
+    }
+
+    // The azimuthal scattering for path `p`, a logistic lobe about its
+    // deflection through the fibre.
+    fn azimuthal(&self, phi: f64, p: usize, gamma_t: f64) -> f64 {
+        let mut dphi = phi - self.phi(p, gamma_t);
+
+        while dphi > PI {
+            dphi -= 2. * PI;
+        }
+
+        while dphi < -PI {
+            dphi += 2. * PI;
+        }
+
+        trimmed_logistic(dphi, self.s, -PI, PI)
+    }
+}
+
+// Longitudinal and azimuthal angles, where the fibre runs along x.
+fn angles(w: Unit3) -> (f64, f64, f64) {
+    let sin_theta = w.x.clamp(-1., 1.);
+
+    (
+        sin_theta,
+        safe_sqrt(1. - sin_theta * sin_theta),
+        w.z.atan2(w.y),
+    )
+}
+
+fn safe_sqrt(x: f64) -> f64 {
+    x.max(0.).sqrt()
+}
+
+// The longitudinal scattering function, with variance `v`.
+fn longitudinal(
+    cos_theta_i: f64,
+    cos_theta_o: f64,
+    sin_theta_i: f64,
+    sin_theta_o: f64,
+    v: f64,
+) -> f64 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+
+    if v <= 0.1 {
+        (log_i0(a) - b - 1. / v + std::f64::consts::LN_2 + (1. / (2. * v)).ln()).exp()
+    } else {
+        (-b).exp() * i0(a) / ((1. / v).sinh() * 2. * v)
+    }
+}
+
+// The modified Bessel function of the first kind and order zero.
+fn i0(x: f64) -> f64 {
+    let mut value = 0.;
+    let mut x2i = 1.;
+    let mut factorial = 1.;
+    let mut i4 = 1.;
+
+    for i in 0..10 {
+        if i > 1 {
+            factorial *= f64::from(i);
+        }
+
+        value += x2i / (i4 * factorial * factorial);
+        x2i *= x * x;
+        i4 *= 4.;
+    }
+
+    value
+}
+
+fn log_i0(x: f64) -> f64 {
+    if x > 12. {
+        x + 0.5 * (-(2. * PI).ln() + (1. / x).ln() + 1. / (8. * x))
+    } else {
+        i0(x).ln()
+    }
+}
+
+fn logistic(x: f64, s: f64) -> f64 {
+    let x = x.abs();
+
+    (-x / s).exp() / (s * (1. + (-x / s).exp()).powi(2))
+}
+
+fn logistic_cdf(x: f64, s: f64) -> f64 {
+    1. / (1. + (-x / s).exp())
+}
+
+fn trimmed_logistic(x: f64, s: f64, a: f64, b: f64) -> f64 {
+    logistic(x, s) / (logistic_cdf(b, s) - logistic_cdf(a, s))
+}
+
+fn sample_trimmed_logistic(u: f64, s: f64, a: f64, b: f64) -> f64 {
+    let k = logistic_cdf(b, s) - logistic_cdf(a, s);
+    let x = -s * (1. / (u * k + logistic_cdf(a, s)) - 1.).ln();
+
+    x.clamp(a, b)
+}
+
+impl Material for HairMaterial {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        let fibre = self.fibre(hit);
+        let (sin_theta_o, cos_theta_o, phi_o) = angles(wo);
+        let (sin_theta_i, cos_theta_i, phi_i) = angles(wi);
+        let (gamma_t, transmittance) = fibre.transmission(sin_theta_o, cos_theta_o);
+        let attenuation = fibre.attenuation(cos_theta_o, transmittance);
+        let phi = phi_i - phi_o;
+        let f = (0..=P_MAX).fold(Color::new(0., 0., 0.), |sum, p| {
+            let (sin_theta_op, cos_theta_op) = fibre.tilt(p, sin_theta_o, cos_theta_o);
+            let azimuthal = if p < P_MAX {
+                fibre.azimuthal(phi, p, gamma_t)
+            } else {
+                1. / (2. * PI)
+            };
+
+            sum.add(attenuation[p].scale(
+                longitudinal(
+                    cos_theta_i,
+                    cos_theta_op,
+                    sin_theta_i,
+                    sin_theta_op,
+                    fibre.v[p],
+                ) * azimuthal,
+            ))
+        });
+
+        if wi.z == 0. {
+            f
+        } else {
+            f.scale(1. / wi.z.abs())
+        }
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let fibre = self.fibre(hit);
+        let (sin_theta_o, cos_theta_o, phi_o) = angles(wo);
+        let path_pdf = fibre.path_pdf(cos_theta_o);
+
+        // Choose a path, and reuse the remainder of `u[0]` for the azimuth.
+        let mut u0 = u[0];
+        let mut p = 0;
+
+        while p < P_MAX && u0 >= path_pdf[p] {
+            u0 -= path_pdf[p];
+            p += 1;
+        }
+
+        let u0 = (u0 / path_pdf[p]).clamp(0., 1.);
+        let (sin_theta_op, cos_theta_op) = fibre.tilt(p, sin_theta_o, cos_theta_o);
+
+        // Sample the longitudinal lobe about the tilted outgoing direction.
+        let v = fibre.v[p];
+        let u1 = u[1].max(1e-5);
+        let cos_theta = 1. + v * (u1 + (1. - u1) * (-2. / v).exp()).ln();
+        let sin_theta = safe_sqrt(1. - cos_theta * cos_theta);
+        let cos_phi = (2. * PI * u[2]).cos();
+        let sin_theta_i =
+            (-cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op).clamp(-1., 1.);
+        let cos_theta_i = safe_sqrt(1. - sin_theta_i * sin_theta_i);
+
+        // Sample the azimuthal lobe about the deflection along the path.
+        let (gamma_t, _) = fibre.transmission(sin_theta_o, cos_theta_o);
+        let dphi = if p < P_MAX {
+            fibre.phi(p, gamma_t) + sample_trimmed_logistic(u0, fibre.s, -PI, PI)
+        } else {
+            2. * PI * u0
+        };
+        let phi_i = phi_o + dphi;
+        let wi = Unit3::new(
+            sin_theta_i,
+            cos_theta_i * phi_i.cos(),
+            cos_theta_i * phi_i.sin(),
+        );
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            let flags = if same_hemisphere(wo, wi) {
+                BsdfFlags::GLOSSY | BsdfFlags::REFLECTION
+            } else {
+                BsdfFlags::GLOSSY | BsdfFlags::TRANSMISSION
+            };
+
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let fibre = self.fibre(hit);
+        let (sin_theta_o, cos_theta_o, phi_o) = angles(wo);
+        let (sin_theta_i, cos_theta_i, phi_i) = angles(wi);
+        let (gamma_t, _) = fibre.transmission(sin_theta_o, cos_theta_o);
+        let path_pdf = fibre.path_pdf(cos_theta_o);
+        let phi = phi_i - phi_o;
+
+        (0..=P_MAX)
+            .map(|p| {
+                let (sin_theta_op, cos_theta_op) = fibre.tilt(p, sin_theta_o, cos_theta_o);
+                let azimuthal = if p < P_MAX {
+                    fibre.azimuthal(phi, p, gamma_t)
+                } else {
+                    1. / (2. * PI)
+                };
+
+                path_pdf[p]
+                    * longitudinal(
+                        cos_theta_i,
+                        cos_theta_op,
+                        sin_theta_i,
+                        sin_theta_op,
+                        fibre.v[p],
+                    )
+                    * azimuthal
+            })
+            .sum()
+    }
+}
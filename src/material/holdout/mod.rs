@@ -0,0 +1,26 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfSample, Material, Matte};
+
+// Cuts a transparent hole in the image where it is seen by the camera, and
+// absorbs all light elsewhere.
+pub struct HoldoutMaterial;
+
+impl Material for HoldoutMaterial {
+    fn eval(&self, _hit: &Hit, _wo: Unit3, _wi: Unit3) -> Color {
+        Color::new(0., 0., 0.)
+    }
+
+    fn sample(&self, _hit: &Hit, _wo: Unit3, _u: [f64; 3]) -> Option<BsdfSample> {
+        None
+    }
+
+    fn pdf(&self, _hit: &Hit, _wo: Unit3, _wi: Unit3) -> f64 {
+        0.
+    }
+
+    fn matte(&self) -> Option<Matte> {
+        Some(Matte::Holdout)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{same_hemisphere, BsdfFlags, BsdfSample, Material};
+use crate::sampling;
+use crate::texture::Texture;
+
+pub struct LambertianMaterial<T: Texture> {
+    pub texture: T,
+}
+
+impl<T: Texture> LambertianMaterial<T> {
+    fn reflectance(&self, hit: &Hit) -> Color {
+        self.texture
+            .color_at(hit)
+            .scale(std::f64::consts::FRAC_1_PI)
+    }
+}
+
+impl<T: Texture> Material for LambertianMaterial<T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        if same_hemisphere(wo, wi) {
+            self.reflectance(hit)
+        } else {
+            Color::new(0., 0., 0.)
+        }
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let mut wi = sampling::cosine_hemisphere((u[1], u[2]));
+
+        if wo.z < 0. {
+            wi.z = -wi.z;
+        }
+
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.reflectance(hit),
+                pdf,
+                flags: BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        if same_hemisphere(wo, wi) {
+            wi.z.abs() * std::f64::consts::FRAC_1_PI
+        } else {
+            0.
+        }
+    }
+}
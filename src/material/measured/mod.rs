@@ -0,0 +1,232 @@
+use std::f64::consts::{FRAC_1_PI, FRAC_PI_2, PI};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{same_hemisphere, upper, BsdfFlags, BsdfSample, Material};
+use crate::sampling::{self, PiecewiseConstant2D};
+
+const RED_SCALE: f64 = 1. / 1500.;
+const GREEN_SCALE: f64 = 1.15 / 1500.;
+const BLUE_SCALE: f64 = 1.66 / 1500.;
+
+const THETA_O_BINS: usize = 16;
+const THETA_I_BINS: usize = 32;
+const PHI_BINS: usize = 64;
+const COSINE_PROBABILITY: f64 = 0.1;
+// Twice the entries of a MERL table, so that a corrupt header can't ask for
+// more memory than a real file needs.
+const MAX_ENTRIES: usize = 2 * 90 * 90 * 180;
+
+// An isotropic BRDF measured by Matusik et al. 2003, "A Data-Driven
+// Reflectance Model", read from the MERL binary format. Directions are
+// sampled from tables of the BRDF, tabulated over the incident direction for
+// a range of outgoing elevations, mixed with some cosine-weighted sampling
+// for robustness.
+pub struct MeasuredMaterial {
+    theta_half_resolution: usize,
+    theta_diff_resolution: usize,
+    phi_diff_resolution: usize,
+    table: Vec<Color>,
+    distributions: Vec<PiecewiseConstant2D>,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl MeasuredMaterial {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    // The format is a header of three little-endian 32-bit resolutions, for
+    // the half angle, difference angle and difference azimuth, followed by
+    // a table of 64-bit floats for each of the red, green and blue channels.
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut header = [0u8; 12];
+
+        reader.read_exact(&mut header)?;
+
+        let mut resolutions = [0usize; 3];
+
+        for (resolution, bytes) in resolutions.iter_mut().zip(header.chunks_exact(4)) {
+            let value = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+            if value <= 0 {
+                return Err(invalid_data("measured BRDF has an empty table"));
+            }
+
+            *resolution = value as usize;
+        }
+
+        let n = resolutions
+            .iter()
+            .try_fold(1usize, |n, &resolution| n.checked_mul(resolution))
+            .filter(|&n| n <= MAX_ENTRIES)
+            .ok_or_else(|| invalid_data("measured BRDF table is too large"))?;
+        let mut data = vec![0u8; 3 * n * 8];
+
+        reader.read_exact(&mut data)?;
+
+        let values: Vec<f64> = data
+            .chunks_exact(8)
+            .map(|bytes| {
+                let mut value = [0u8; 8];
+
+                value.copy_from_slice(bytes);
+
+                f64::from_le_bytes(value).max(0.)
+            })
+            .collect();
+        let table = (0..n)
+            .map(|i| {
+                Color::new(
+                    values[i] * RED_SCALE,
+                    values[i + n] * GREEN_SCALE,
+                    values[i + 2 * n] * BLUE_SCALE,
+                )
+            })
+            .collect();
+
+        let mut material = Self {
+            theta_half_resolution: resolutions[0],
+            theta_diff_resolution: resolutions[1],
+            phi_diff_resolution: resolutions[2],
+            table,
+            distributions: vec![],
+        };
+
+        material.distributions = (0..THETA_O_BINS)
+            .map(|bin| material.tabulate((bin as f64 + 0.5) / THETA_O_BINS as f64))
+            .collect();
+
+        Ok(material)
+    }
+
+    // The distribution of luminance times cosine over incident directions,
+    // parameterised by cos(theta) and azimuth relative to `wo`, for an
+    // outgoing direction with the given cosine.
+    fn tabulate(&self, cos_theta_o: f64) -> PiecewiseConstant2D {
+        let wo = Unit3::new((1. - cos_theta_o * cos_theta_o).sqrt(), 0., cos_theta_o);
+        let mut func = Vec::with_capacity(THETA_I_BINS * PHI_BINS);
+
+        for j in 0..PHI_BINS {
+            for i in 0..THETA_I_BINS {
+                let wi = Self::direction(
+                    (i as f64 + 0.5) / THETA_I_BINS as f64,
+                    (j as f64 + 0.5) / PHI_BINS as f64,
+                    0.,
+                );
+
+                func.push(self.lookup(wo, wi).luminance() * wi.z);
+            }
+        }
+
+        PiecewiseConstant2D::new(&func, THETA_I_BINS, PHI_BINS)
+    }
+
+    fn direction(cos_theta: f64, phi: f64, phi_o: f64) -> Unit3 {
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * phi + phi_o;
+
+        Unit3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+
+    fn distribution(&self, wo: Unit3) -> &PiecewiseConstant2D {
+        &self.distributions[((wo.z * THETA_O_BINS as f64) as usize).min(THETA_O_BINS - 1)]
+    }
+
+    // Looks up the table in the half and difference angles of Rusinkiewicz
+    // 1998, "A New Change of Variables for Efficient BRDF Representation",
+    // where the half angle is indexed non-linearly to favour the specular
+    // peak.
+    fn lookup(&self, wo: Unit3, wi: Unit3) -> Color {
+        let half = (Vector3::from(wo) + Vector3::from(wi)).normalize();
+        let theta_half = half.z.clamp(-1., 1.).acos();
+        let (sin_phi, cos_phi) = (-half.y.atan2(half.x)).sin_cos();
+        let (sin_theta, cos_theta) = (-theta_half).sin_cos();
+        let x = wi.x * cos_phi - wi.y * sin_phi;
+        let y = wi.x * sin_phi + wi.y * cos_phi;
+        let diff_x = x * cos_theta + wi.z * sin_theta;
+        let diff_z = wi.z * cos_theta - x * sin_theta;
+        let theta_diff = diff_z.clamp(-1., 1.).acos();
+        let mut phi_diff = y.atan2(diff_x);
+
+        if phi_diff < 0. {
+            phi_diff += PI;
+        }
+
+        let index = |fraction: f64, resolution: usize| {
+            ((fraction * resolution as f64).max(0.) as usize).min(resolution - 1)
+        };
+        let theta_half_index = index(
+            (theta_half / FRAC_PI_2).max(0.).sqrt(),
+            self.theta_half_resolution,
+        );
+        let theta_diff_index = index(theta_diff / FRAC_PI_2, self.theta_diff_resolution);
+        let phi_diff_index = index(phi_diff / PI, self.phi_diff_resolution);
+
+        self.table[phi_diff_index
+            + self.phi_diff_resolution
+                * (theta_diff_index + self.theta_diff_resolution * theta_half_index)]
+    }
+}
+
+impl Material for MeasuredMaterial {
+    fn eval(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        if same_hemisphere(wo, wi) {
+            self.lookup(upper(wo), upper(wi))
+        } else {
+            Color::new(0., 0., 0.)
+        }
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let wo_upper = upper(wo);
+        let mut wi = if u[0] < COSINE_PROBABILITY {
+            sampling::cosine_hemisphere((u[1], u[2]))
+        } else {
+            let (cos_theta, phi) = self.distribution(wo_upper).sample((u[1], u[2]));
+
+            Self::direction(cos_theta, phi, wo_upper.y.atan2(wo_upper.x))
+        };
+
+        if wo.z < 0. {
+            wi.z = -wi.z;
+        }
+
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags: BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.;
+        }
+
+        let (wo, wi) = (upper(wo), upper(wi));
+        let mut phi = (wi.y.atan2(wi.x) - wo.y.atan2(wo.x)) / (2. * PI);
+
+        if phi < 0. {
+            phi += 1.;
+        }
+
+        let tabulated = self.distribution(wo).pdf((wi.z, phi)) / (2. * PI);
+
+        COSINE_PROBABILITY * wi.z * FRAC_1_PI + (1. - COSINE_PROBABILITY) * tabulated
+    }
+}
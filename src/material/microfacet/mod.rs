@@ -0,0 +1,101 @@
+use crate::geometry::*;
+use crate::material::{cos2_theta, cos_phi, sin_phi, tan2_theta};
+
+// The Trowbridge-Reitz (GGX) distribution of microfacet normals, sampled
+// from the distribution of normals visible from a given direction.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(roughness: f64) -> Self {
+        Self::anisotropic(roughness, roughness)
+    }
+
+    pub fn anisotropic(roughness_x: f64, roughness_y: f64) -> Self {
+        Self {
+            alpha_x: roughness_x * roughness_x,
+            alpha_y: roughness_y * roughness_y,
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    pub fn d(&self, wm: Unit3) -> f64 {
+        let tan2 = tan2_theta(wm);
+
+        if !tan2.is_finite() {
+            return 0.;
+        }
+
+        let cos4 = cos2_theta(wm).powi(2);
+
+        if cos4 < 1e-16 {
+            return 0.;
+        }
+
+        let e =
+            tan2 * ((cos_phi(wm) / self.alpha_x).powi(2) + (sin_phi(wm) / self.alpha_y).powi(2));
+
+        1. / (std::f64::consts::PI * self.alpha_x * self.alpha_y * cos4 * (1. + e).powi(2))
+    }
+
+    pub fn lambda(&self, w: Unit3) -> f64 {
+        let tan2 = tan2_theta(w);
+
+        if !tan2.is_finite() {
+            return 0.;
+        }
+
+        let alpha2 = (cos_phi(w) * self.alpha_x).powi(2) + (sin_phi(w) * self.alpha_y).powi(2);
+
+        ((1. + alpha2 * tan2).sqrt() - 1.) / 2.
+    }
+
+    pub fn g1(&self, w: Unit3) -> f64 {
+        1. / (1. + self.lambda(w))
+    }
+
+    pub fn g(&self, wo: Unit3, wi: Unit3) -> f64 {
+        1. / (1. + self.lambda(wo) + self.lambda(wi))
+    }
+
+    pub fn visible_d(&self, w: Unit3, wm: Unit3) -> f64 {
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).abs()
+    }
+
+    pub fn pdf(&self, w: Unit3, wm: Unit3) -> f64 {
+        self.visible_d(w, wm)
+    }
+
+    pub fn sample_wm(&self, w: Unit3, u: (f64, f64)) -> Unit3 {
+        let mut wh = Unit3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z);
+
+        if wh.z < 0. {
+            wh = wh.reverse();
+        }
+
+        let t1 = if wh.z < 0.99999 {
+            (Unit3::new(0., 0., 1.) * wh).normalize()
+        } else {
+            Unit3::new(1., 0., 0.)
+        };
+        let t2 = (wh * t1).normalize();
+
+        let r = u.0.sqrt();
+        let phi = 2. * std::f64::consts::PI * u.1;
+        let px = r * phi.cos();
+        let h = (1. - px * px).sqrt();
+        let t = (1. + wh.z) / 2.;
+        let py = (1. - t) * h + t * r * phi.sin();
+        let pz = (1. - px * px - py * py).max(0.).sqrt();
+
+        let nh = t1 * px + t2 * py + wh * pz;
+
+        Unit3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6))
+    }
+}
@@ -0,0 +1,112 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfFlags, BsdfSample, Material, Matte};
+use crate::medium::Medium;
+use crate::texture::Texture;
+
+// Blends two materials, taking `second` where `weight` is one and `first`
+// where it is zero.
+pub struct MixMaterial<A: Material, B: Material, T: Texture> {
+    pub first: A,
+    pub second: B,
+    pub weight: T,
+}
+
+impl<A: Material, B: Material, T: Texture> MixMaterial<A, B, T> {
+    fn weight(&self, hit: &Hit) -> f64 {
+        self.weight.value_at(hit).clamp(0., 1.)
+    }
+}
+
+impl<A: Material, B: Material, T: Texture> Material for MixMaterial<A, B, T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        let weight = self.weight(hit);
+
+        self.first
+            .eval(hit, wo, wi)
+            .scale(1. - weight)
+            .add(self.second.eval(hit, wo, wi).scale(weight))
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let weight = self.weight(hit);
+        let (sample, probability) = if u[0] < weight {
+            let u0 = u[0] / weight;
+
+            (self.second.sample(hit, wo, [u0, u[1], u[2]])?, weight)
+        } else {
+            let u0 = ((u[0] - weight) / (1. - weight)).min(1. - f64::EPSILON);
+
+            (self.first.sample(hit, wo, [u0, u[1], u[2]])?, 1. - weight)
+        };
+
+        if sample.flags.contains(BsdfFlags::SPECULAR) {
+            return Some(BsdfSample {
+                f: sample.f.scale(probability),
+                pdf: sample.pdf * probability,
+                ..sample
+            });
+        }
+
+        let pdf = self.pdf(hit, wo, sample.wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                f: self.eval(hit, wo, sample.wi),
+                pdf,
+                ..sample
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let weight = self.weight(hit);
+
+        (1. - weight) * self.first.pdf(hit, wo, wi) + weight * self.second.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.first.interior().or_else(|| self.second.interior())
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.first
+            .refractive_index()
+            .max(self.second.refractive_index())
+    }
+
+    fn priority(&self) -> u32 {
+        self.first.priority().max(self.second.priority())
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        let weight = self.weight(hit);
+
+        (1. - weight) * self.first.alpha(hit) + weight * self.second.alpha(hit)
+    }
+
+    // Frames can't be blended, so the shading normal is that of whichever
+    // material has the greater weight.
+    fn frame(&self, hit: &Hit) -> Frame {
+        if self.weight(hit) > 0.5 {
+            self.second.frame(hit)
+        } else {
+            self.first.frame(hit)
+        }
+    }
+
+    // A matte only where both materials are the same one, since there is no
+    // hit to weigh them by.
+    fn matte(&self) -> Option<Matte> {
+        let matte = self.first.matte();
+
+        if matte == self.second.matte() {
+            matte
+        } else {
+            None
+        }
+    }
+}
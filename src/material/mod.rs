@@ -1,162 +1,327 @@
-use rand::Rng;
-use rand_xoshiro::Xoshiro256StarStar;
+use std::f64::consts::PI;
+use std::ops::BitOr;
+use std::sync::Arc;
+
+use num_complex::Complex64;
 
 use crate::color::Color;
 use crate::geometry::*;
 use crate::hittable::Hit;
-use crate::ray::Ray;
-use crate::texture::Texture;
+use crate::medium::Medium;
+
+pub mod alpha;
+pub mod brushed_metal;
+pub mod bump;
+pub mod coated;
+pub mod conductor;
+pub mod dielectric;
+pub mod hair;
+pub mod holdout;
+pub mod lambertian;
+pub mod measured;
+pub mod microfacet;
+pub mod mix;
+pub mod oren_nayar;
+pub mod principled;
+pub mod reflective;
+pub mod rough_dielectric;
+pub mod shadow_catcher;
+pub mod sheen;
+pub mod subsurface;
+pub mod thin_film;
+pub mod translucent;
+
+pub use alpha::*;
+pub use brushed_metal::*;
+pub use bump::*;
+pub use coated::*;
+pub use conductor::*;
+pub use dielectric::*;
+pub use hair::*;
+pub use holdout::*;
+pub use lambertian::*;
+pub use measured::*;
+pub use microfacet::*;
+pub use mix::*;
+pub use oren_nayar::*;
+pub use principled::*;
+pub use reflective::*;
+pub use rough_dielectric::*;
+pub use shadow_catcher::*;
+pub use sheen::*;
+pub use subsurface::*;
+pub use thin_film::*;
+pub use translucent::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BsdfFlags(u8);
+
+impl BsdfFlags {
+    pub const REFLECTION: Self = Self(1);
+    pub const TRANSMISSION: Self = Self(1 << 1);
+    pub const DIFFUSE: Self = Self(1 << 2);
+    pub const GLOSSY: Self = Self(1 << 3);
+    pub const SPECULAR: Self = Self(1 << 4);
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
 
-pub trait Material: Send + Sync {
-    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Xoshiro256StarStar) -> Option<(Ray, Color)> {
-        self.scatter_ray(ray, hit, rng)
-            .map(|scattered| (scattered, self.attenuation(hit)))
+impl BitOr for BsdfFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        Self(self.0 | other.0)
     }
+}
 
-    fn scatter_ray(&self, ray: &Ray, hit: &Hit, rng: &mut Xoshiro256StarStar) -> Option<Ray>;
-    fn attenuation(&self, hit: &Hit) -> Color;
+#[derive(Debug, Copy, Clone)]
+pub struct BsdfSample {
+    pub wi: Unit3,
+    pub f: Color,
+    pub pdf: f64,
+    pub flags: BsdfFlags,
 }
 
-pub struct ReflectiveMaterial<T: Texture> {
-    pub texture: T,
+// Surfaces that stand in for parts of a background plate when compositing.
+// Holdouts cut a transparent hole in the image, and shadow catchers record
+// only the shadows and reflections cast onto them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Matte {
+    Holdout,
+    ShadowCatcher,
 }
 
-impl<T: Texture> Material for ReflectiveMaterial<T> {
-    fn scatter_ray(&self, ray: &Ray, hit: &Hit, _rng: &mut Xoshiro256StarStar) -> Option<Ray> {
-        let reflection_direction = ray.direction.reflect(hit.normal);
+// All directions are in the local shading frame, where the surface normal is
+// +z, and both `wo` and `wi` point away from the surface.
+pub trait Material: Send + Sync {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color;
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample>;
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64;
 
-        Some(Ray::new(hit.p, reflection_direction))
+    fn interior(&self) -> Option<Medium> {
+        None
     }
 
-    fn attenuation(&self, hit: &Hit) -> Color {
-        self.texture.color(hit.u, hit.v, &hit.p)
+    // The index of refraction of the interior, relative to vacuum.
+    fn refractive_index(&self) -> f64 {
+        1.
+    }
+
+    // Where the interiors of objects overlap, the one with the highest
+    // priority takes precedence and the surfaces of the others are ignored.
+    fn priority(&self) -> u32 {
+        0
+    }
+
+    fn frame(&self, hit: &Hit) -> Frame {
+        hit.frame()
+    }
+
+    fn alpha(&self, _hit: &Hit) -> f64 {
+        1.
     }
-}
 
-pub struct FuzzyReflectiveMaterial<T: Texture> {
-    pub texture: T,
-    pub fuzz: f64,
+    fn matte(&self) -> Option<Matte> {
+        None
+    }
 }
 
-impl<T: Texture> Material for FuzzyReflectiveMaterial<T> {
-    fn scatter_ray(&self, ray: &Ray, hit: &Hit, rng: &mut Xoshiro256StarStar) -> Option<Ray> {
-        let reflection_direction = ray.direction.reflect(hit.normal);
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        (**self).eval(hit, wo, wi)
+    }
 
-        let coords: [f64; 3] = rng.gen();
-        let fuzz_vector = Vector3::from(coords) * self.fuzz;
-        let scattered: Unit3 = (Vector3::from(reflection_direction) + fuzz_vector).into();
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        (**self).sample(hit, wo, u)
+    }
 
-        if scattered.dot(hit.normal) > 0.0 {
-            Some(Ray::new(hit.p, scattered))
-        } else {
-            None
-        }
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        (**self).pdf(hit, wo, wi)
     }
 
-    fn attenuation(&self, hit: &Hit) -> Color {
-        self.texture.color(hit.u, hit.v, &hit.p)
+    fn interior(&self) -> Option<Medium> {
+        (**self).interior()
     }
-}
 
-pub struct LambertianMaterial<T: Texture> {
-    pub texture: T,
-}
+    fn refractive_index(&self) -> f64 {
+        (**self).refractive_index()
+    }
 
-impl<T: Texture> LambertianMaterial<T> {
-    fn random_in_unit_sphere(rng: &mut Xoshiro256StarStar) -> Vector3 {
-        let mut vec;
+    fn priority(&self) -> u32 {
+        (**self).priority()
+    }
 
-        loop {
-            let coords: [f64; 3] = rng.gen();
+    fn frame(&self, hit: &Hit) -> Frame {
+        (**self).frame(hit)
+    }
 
-            vec = Vector3::from(coords);
+    fn alpha(&self, hit: &Hit) -> f64 {
+        (**self).alpha(hit)
+    }
 
-            if vec.length() <= 1.0 {
-                break vec;
-            }
-        }
+    fn matte(&self) -> Option<Matte> {
+        (**self).matte()
     }
 }
 
-impl<T: Texture> Material for LambertianMaterial<T> {
-    fn scatter_ray(&self, _ray: &Ray, hit: &Hit, rng: &mut Xoshiro256StarStar) -> Option<Ray> {
-        let direction = (Self::random_in_unit_sphere(rng) + Vector3::from(hit.normal)).into();
+pub fn same_hemisphere(a: Unit3, b: Unit3) -> bool {
+    a.z * b.z > 0.
+}
 
-        Some(Ray::new(hit.p, direction))
+pub fn reflect(wo: Unit3) -> Unit3 {
+    Unit3 {
+        x: -wo.x,
+        y: -wo.y,
+        z: wo.z,
     }
+}
 
-    fn attenuation(&self, hit: &Hit) -> Color {
-        self.texture.color(hit.u, hit.v, &hit.p)
-    }
+// Mirrors `w` into the upper hemisphere.
+pub fn upper(w: Unit3) -> Unit3 {
+    Unit3 { z: w.z.abs(), ..w }
 }
 
-pub struct DielectricMaterial<T: Texture> {
-    pub texture: T,
-    pub refractive_index: f64,
+pub fn reflect_about(wo: Unit3, n: Unit3) -> Unit3 {
+    (n * (2. * wo.dot(n)) - Vector3::from(wo)).into()
 }
 
-impl<T: Texture> DielectricMaterial<T> {
-    fn refract(direction: &Unit3, normal: &Unit3, ni_over_nt: f64) -> Option<Unit3> {
-        let uv: Vector3 = (*direction).into();
-        let un: Vector3 = (*normal).into();
-        let dt = uv.dot(un);
-        let discriminant = 1.0 - ni_over_nt.powi(2) * (1.0 - dt.powi(2));
+pub fn cos2_theta(w: Unit3) -> f64 {
+    w.z * w.z
+}
 
-        if discriminant > 0.0 {
-            let refracted = (uv - *normal * dt) * ni_over_nt - *normal * discriminant.sqrt();
+pub fn sin2_theta(w: Unit3) -> f64 {
+    (1. - cos2_theta(w)).max(0.)
+}
 
-            Some(refracted.into())
-        } else {
-            None
-        }
-    }
+pub fn tan2_theta(w: Unit3) -> f64 {
+    sin2_theta(w) / cos2_theta(w)
+}
 
-    fn reflect(ray: &Ray, hit: &Hit) -> Option<Ray> {
-        let direction = ray.direction.reflect(hit.normal);
+pub fn cos_phi(w: Unit3) -> f64 {
+    let sin_theta = sin2_theta(w).sqrt();
 
-        Some(Ray::new(hit.p, direction))
+    if sin_theta == 0. {
+        1.
+    } else {
+        (w.x / sin_theta).clamp(-1., 1.)
     }
+}
 
-    fn schlick(&self, cosine: f64) -> f64 {
-        let r0 = ((1.0 - self.refractive_index) / (1.0 + self.refractive_index)).powi(2);
+pub fn sin_phi(w: Unit3) -> f64 {
+    let sin_theta = sin2_theta(w).sqrt();
 
-        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    if sin_theta == 0. {
+        0.
+    } else {
+        (w.y / sin_theta).clamp(-1., 1.)
     }
 }
 
-impl<T: Texture> Material for DielectricMaterial<T> {
-    fn scatter_ray(&self, ray: &Ray, hit: &Hit, rng: &mut Xoshiro256StarStar) -> Option<Ray> {
-        let outward_normal;
-        let ni_over_nt;
-        let cosine;
-        let rdotn = ray.direction.dot(hit.normal);
+// Refracts `wo` through the surface, where `eta` is the ratio of the index
+// below the surface to the index above it. Returns the refracted direction
+// and the relative index actually crossed, or `None` on total internal
+// reflection.
+pub fn refract(wo: Unit3, eta: f64) -> Option<(Unit3, f64)> {
+    refract_about(wo, Unit3::new(0., 0., 1.), eta)
+}
 
-        if rdotn > 0.0 {
-            outward_normal = hit.normal.reverse();
-            ni_over_nt = self.refractive_index;
-            cosine = self.refractive_index * rdotn;
-        } else {
-            outward_normal = hit.normal;
-            ni_over_nt = 1.0 / self.refractive_index;
-            cosine = -rdotn;
-        }
+pub fn refract_about(wo: Unit3, n: Unit3, eta: f64) -> Option<(Unit3, f64)> {
+    let cos_i = wo.dot(n);
+    let (eta, cos_i, n) = if cos_i < 0. {
+        (1. / eta, -cos_i, n.reverse())
+    } else {
+        (eta, cos_i, n)
+    };
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
+
+    if sin2_t >= 1. {
+        return None;
+    }
 
-        let reflect_prob = self.schlick(cosine);
+    let cos_t = (1. - sin2_t).sqrt();
+    let wi = (n * (cos_i / eta - cos_t) - wo / eta).into();
 
-        if let Some(refracted) = Self::refract(&ray.direction, &outward_normal, ni_over_nt) {
-            let val: f64 = rng.gen();
+    Some((wi, eta))
+}
 
-            if val < reflect_prob {
-                Self::reflect(ray, hit)
-            } else {
-                Some(Ray::new(hit.p, refracted))
-            }
-        } else {
-            Self::reflect(ray, hit)
-        }
-    }
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (eta, cos_i) = if cos_i < 0. {
+        (1. / eta, -cos_i.max(-1.))
+    } else {
+        (eta, cos_i.min(1.))
+    };
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
 
-    fn attenuation(&self, hit: &Hit) -> Color {
-        self.texture.color(hit.u, hit.v, &hit.p)
+    if sin2_t >= 1. {
+        return 1.;
     }
+
+    let cos_t = (1. - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (r_parallel.powi(2) + r_perpendicular.powi(2)) / 2.
+}
+
+pub fn fresnel_complex(cos_i: f64, eta: Complex64) -> f64 {
+    let cos_i = Complex64::from(cos_i.clamp(0., 1.));
+    let sin2_i = Complex64::from(1.) - cos_i * cos_i;
+    let sin2_t = sin2_i / (eta * eta);
+    let cos_t = (Complex64::from(1.) - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (r_parallel.norm_sqr() + r_perpendicular.norm_sqr()) / 2.
 }
+
+// Representative wavelengths of the red, green and blue channels, in
+// nanometres, for wave-optical effects.
+const WAVELENGTHS: [f64; 3] = [630., 532., 465.];
+
+// The reflectance of an interface of relative index `eta` under a film of
+// relative index `film_eta` and `thickness` nanometres, from the Airy sum
+// of the waves reflected between either side of the film.
+pub fn fresnel_thin_film(cos_i: f64, eta: f64, film_eta: f64, thickness: f64) -> Color {
+    let (outer_eta, inner_eta, cos_i) = if cos_i < 0. {
+        (eta, 1., -cos_i.max(-1.))
+    } else {
+        (1., eta, cos_i.min(1.))
+    };
+    let sin2_i = 1. - cos_i * cos_i;
+    let cos_in = |eta: f64| (Complex64::from(1. - sin2_i * (outer_eta / eta).powi(2))).sqrt();
+    let cos_i = Complex64::from(cos_i);
+    let cos_film = cos_in(film_eta);
+    let cos_t = cos_in(inner_eta);
+    let perpendicular = |eta_a: f64, cos_a: Complex64, eta_b: f64, cos_b: Complex64| {
+        (cos_a * eta_a - cos_b * eta_b) / (cos_a * eta_a + cos_b * eta_b)
+    };
+    let parallel = |eta_a: f64, cos_a: Complex64, eta_b: f64, cos_b: Complex64| {
+        (cos_a * eta_b - cos_b * eta_a) / (cos_a * eta_b + cos_b * eta_a)
+    };
+    let reflectance = |wavelength: f64| {
+        let shift =
+            (Complex64::i() * cos_film * (4. * PI * film_eta * thickness / wavelength)).exp();
+        let airy = |outer: Complex64, inner: Complex64| {
+            ((outer + inner * shift) / (Complex64::from(1.) + outer * inner * shift)).norm_sqr()
+        };
+
+        (airy(
+            perpendicular(outer_eta, cos_i, film_eta, cos_film),
+            perpendicular(film_eta, cos_film, inner_eta, cos_t),
+        ) + airy(
+            parallel(outer_eta, cos_i, film_eta, cos_film),
+            parallel(film_eta, cos_film, inner_eta, cos_t),
+        )) / 2.
+    };
+
+    Color::new(
+        reflectance(WAVELENGTHS[0]),
+        reflectance(WAVELENGTHS[1]),
+        reflectance(WAVELENGTHS[2]),
+    )
+}
+
+#[cfg(test)]
+mod test;
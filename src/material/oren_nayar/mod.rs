@@ -0,0 +1,84 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{
+    cos_phi, same_hemisphere, sin2_theta, sin_phi, BsdfFlags, BsdfSample, Material,
+};
+use crate::sampling;
+use crate::texture::Texture;
+
+// Rough diffuse reflection after Oren and Nayar 1994, "Generalization of
+// Lambert's Reflectance Model", where `sigma` is the standard deviation of
+// the microfacet slope angle in radians.
+pub struct OrenNayarMaterial<T: Texture> {
+    pub texture: T,
+    pub sigma: f64,
+}
+
+impl<T: Texture> OrenNayarMaterial<T> {
+    fn coefficients(&self) -> (f64, f64) {
+        let sigma2 = self.sigma * self.sigma;
+
+        (
+            1. - sigma2 / (2. * (sigma2 + 0.33)),
+            0.45 * sigma2 / (sigma2 + 0.09),
+        )
+    }
+}
+
+impl<T: Texture> Material for OrenNayarMaterial<T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new(0., 0., 0.);
+        }
+
+        let (a, b) = self.coefficients();
+        let sin_theta_i = sin2_theta(wi).sqrt();
+        let sin_theta_o = sin2_theta(wo).sqrt();
+
+        let max_cos = if sin_theta_i > 1e-4 && sin_theta_o > 1e-4 {
+            (cos_phi(wi) * cos_phi(wo) + sin_phi(wi) * sin_phi(wo)).max(0.)
+        } else {
+            0.
+        };
+
+        let (sin_alpha, tan_beta) = if wi.z.abs() > wo.z.abs() {
+            (sin_theta_o, sin_theta_i / wi.z.abs())
+        } else {
+            (sin_theta_i, sin_theta_o / wo.z.abs())
+        };
+
+        self.texture
+            .color_at(hit)
+            .scale(std::f64::consts::FRAC_1_PI * (a + b * max_cos * sin_alpha * tan_beta))
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let mut wi = sampling::cosine_hemisphere((u[1], u[2]));
+
+        if wo.z < 0. {
+            wi.z = -wi.z;
+        }
+
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags: BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        if same_hemisphere(wo, wi) {
+            wi.z.abs() * std::f64::consts::FRAC_1_PI
+        } else {
+            0.
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{
+    reflect_about, same_hemisphere, BsdfFlags, BsdfSample, Material, MicrofacetDielectric,
+    TrowbridgeReitz,
+};
+use crate::medium::Medium;
+use crate::sampling;
+use crate::texture::{ConstantTexture, Texture};
+
+// A single artist-facing material after Burley 2012, "Physically Based Shading
+// at Disney", with a rough dielectric transmission lobe. Every parameter is a
+// texture; scalar parameters use the texture's `value`. Transmissive objects
+// are filled with `interior`, and nest with other dielectrics by `priority`.
+pub struct PrincipledMaterial {
+    pub base_color: Box<dyn Texture>,
+    pub metallic: Box<dyn Texture>,
+    pub roughness: Box<dyn Texture>,
+    pub specular: Box<dyn Texture>,
+    pub specular_tint: Box<dyn Texture>,
+    pub sheen: Box<dyn Texture>,
+    pub clearcoat: Box<dyn Texture>,
+    pub clearcoat_gloss: Box<dyn Texture>,
+    pub transmission: Box<dyn Texture>,
+    pub refractive_index: Box<dyn Texture>,
+    pub interior: Option<Medium>,
+    pub priority: u32,
+}
+
+struct Parameters {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    specular_tint: f64,
+    sheen: f64,
+    clearcoat: f64,
+    clearcoat_gloss: f64,
+    transmission: f64,
+    // Relative to the exterior of the surface.
+    refractive_index: f64,
+}
+
+#[derive(Copy, Clone)]
+enum Lobe {
+    Diffuse,
+    Specular,
+    Clearcoat,
+    Transmission,
+}
+
+impl PrincipledMaterial {
+    pub fn new<T: Texture + 'static>(base_color: T) -> Self {
+        Self {
+            base_color: Box::new(base_color),
+            metallic: Box::new(ConstantTexture::scalar(0.)),
+            roughness: Box::new(ConstantTexture::scalar(0.5)),
+            specular: Box::new(ConstantTexture::scalar(0.5)),
+            specular_tint: Box::new(ConstantTexture::scalar(0.)),
+            sheen: Box::new(ConstantTexture::scalar(0.)),
+            clearcoat: Box::new(ConstantTexture::scalar(0.)),
+            clearcoat_gloss: Box::new(ConstantTexture::scalar(1.)),
+            transmission: Box::new(ConstantTexture::scalar(0.)),
+            refractive_index: Box::new(ConstantTexture::scalar(1.5)),
+            interior: None,
+            priority: 0,
+        }
+    }
+
+    fn parameters(&self, hit: &Hit) -> Parameters {
+        Parameters {
+            base_color: self.base_color.color_at(hit),
+            metallic: self.metallic.value_at(hit).clamp(0., 1.),
+            roughness: self.roughness.value_at(hit).clamp(0.05, 1.),
+            specular: self.specular.value_at(hit).max(0.),
+            specular_tint: self.specular_tint.value_at(hit).clamp(0., 1.),
+            sheen: self.sheen.value_at(hit).max(0.),
+            clearcoat: self.clearcoat.value_at(hit).max(0.),
+            clearcoat_gloss: self.clearcoat_gloss.value_at(hit).clamp(0., 1.),
+            transmission: self.transmission.value_at(hit).clamp(0., 1.),
+            refractive_index: self.refractive_index.value_at(hit).max(1.)
+                / hit.exterior_refractive_index,
+        }
+    }
+}
+
+fn schlick_weight(cos: f64) -> f64 {
+    (1. - cos).clamp(0., 1.).powi(5)
+}
+
+fn mix(a: Color, b: Color, t: f64) -> Color {
+    a.scale(1. - t).add(b.scale(t))
+}
+
+fn half_vector(wo: Unit3, wi: Unit3) -> Option<Unit3> {
+    let half = Vector3::from(wo) + Vector3::from(wi);
+
+    if half.length_squared() == 0. {
+        return None;
+    }
+
+    let wh = half.normalize();
+
+    Some(if wh.z < 0. { wh.reverse() } else { wh })
+}
+
+// The GTR1 distribution used for the clearcoat lobe.
+fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+    let alpha2 = alpha * alpha;
+
+    (alpha2 - 1.) / (std::f64::consts::PI * alpha2.ln() * (1. + (alpha2 - 1.) * cos_h * cos_h))
+}
+
+fn sample_gtr1(u: (f64, f64), alpha: f64) -> Unit3 {
+    let alpha2 = alpha * alpha;
+    let cos_h = ((1. - alpha2.powf(1. - u.0)) / (1. - alpha2))
+        .max(0.)
+        .sqrt();
+    let sin_h = (1. - cos_h * cos_h).max(0.).sqrt();
+    let phi = 2. * std::f64::consts::PI * u.1;
+
+    Unit3::new(sin_h * phi.cos(), sin_h * phi.sin(), cos_h)
+}
+
+impl Parameters {
+    fn distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::new(self.roughness)
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        0.1 * (1. - self.clearcoat_gloss) + 0.001 * self.clearcoat_gloss
+    }
+
+    fn transmission_bsdf(&self) -> MicrofacetDielectric {
+        MicrofacetDielectric {
+            distribution: self.distribution(),
+            refractive_index: self.refractive_index,
+        }
+    }
+
+    fn diffuse_weight(&self) -> f64 {
+        (1. - self.metallic) * (1. - self.transmission)
+    }
+
+    fn transmission_weight(&self) -> f64 {
+        (1. - self.metallic) * self.transmission
+    }
+
+    fn specular_color(&self) -> Color {
+        let luminance = self.base_color.luminance();
+        let tint = if luminance > 0. {
+            self.base_color.scale(1. / luminance)
+        } else {
+            Color::new(1., 1., 1.)
+        };
+        let dielectric =
+            mix(Color::new(1., 1., 1.), tint, self.specular_tint).scale(0.08 * self.specular);
+
+        mix(dielectric, self.base_color, self.metallic)
+    }
+
+    fn lobe_probabilities(&self) -> [(Lobe, f64); 4] {
+        let diffuse = self.diffuse_weight();
+        let specular = 1. - self.transmission_weight();
+        let clearcoat = 0.25 * self.clearcoat;
+        let transmission = self.transmission_weight();
+        let total = diffuse + specular + clearcoat + transmission;
+
+        [
+            (Lobe::Diffuse, diffuse / total),
+            (Lobe::Specular, specular / total),
+            (Lobe::Clearcoat, clearcoat / total),
+            (Lobe::Transmission, transmission / total),
+        ]
+    }
+
+    fn eval(&self, wo: Unit3, wi: Unit3) -> Color {
+        if wo.z == 0. || wi.z == 0. {
+            return Color::new(0., 0., 0.);
+        }
+
+        let transmission = self.transmission_bsdf().eval(wo, wi) * self.transmission_weight();
+
+        if !same_hemisphere(wo, wi) {
+            return self.base_color.scale(transmission);
+        }
+
+        let wh = match half_vector(wo, wi) {
+            Some(wh) => wh,
+            None => return Color::new(0., 0., 0.),
+        };
+        let cos_o = wo.z.abs();
+        let cos_i = wi.z.abs();
+        let cos_d = wi.dot(wh).abs();
+
+        let fd90 = 0.5 + 2. * self.roughness * cos_d * cos_d;
+        let retro_reflection =
+            (1. + (fd90 - 1.) * schlick_weight(cos_i)) * (1. + (fd90 - 1.) * schlick_weight(cos_o));
+        let diffuse = self
+            .base_color
+            .scale(retro_reflection * std::f64::consts::FRAC_1_PI)
+            .add(Color::new(1., 1., 1.).scale(self.sheen * schlick_weight(cos_d)))
+            .scale(self.diffuse_weight());
+
+        let distribution = self.distribution();
+        let specular = mix(
+            self.specular_color(),
+            Color::new(1., 1., 1.),
+            schlick_weight(cos_d),
+        )
+        .scale(
+            distribution.d(wh) * distribution.g(wo, wi) / (4. * cos_i * cos_o)
+                * (1. - self.transmission_weight()),
+        );
+
+        let coat = TrowbridgeReitz {
+            alpha_x: 0.25,
+            alpha_y: 0.25,
+        };
+        let clearcoat = 0.25
+            * self.clearcoat
+            * (0.04 + 0.96 * schlick_weight(cos_d))
+            * gtr1(wh.z, self.clearcoat_alpha())
+            * coat.g1(wo)
+            * coat.g1(wi)
+            / (4. * cos_i * cos_o);
+
+        diffuse
+            .add(specular)
+            .add(Color::new(1., 1., 1.).scale(clearcoat + transmission))
+    }
+
+    fn pdf(&self, wo: Unit3, wi: Unit3) -> f64 {
+        if wo.z == 0. || wi.z == 0. {
+            return 0.;
+        }
+
+        let [(_, diffuse), (_, specular), (_, clearcoat), (_, transmission)] =
+            self.lobe_probabilities();
+        let transmission = transmission * self.transmission_bsdf().pdf(wo, wi);
+
+        if !same_hemisphere(wo, wi) {
+            return transmission;
+        }
+
+        let wh = match half_vector(wo, wi) {
+            Some(wh) => wh,
+            None => return 0.,
+        };
+        let jacobian = 1. / (4. * wo.dot(wh).abs());
+
+        diffuse * wi.z.abs() * std::f64::consts::FRAC_1_PI
+            + specular * self.distribution().pdf(wo, wh) * jacobian
+            + clearcoat * gtr1(wh.z, self.clearcoat_alpha()) * wh.z * jacobian
+            + transmission
+    }
+
+    fn sample_lobe(&self, lobe: Lobe, wo: Unit3, u: [f64; 3]) -> Option<Unit3> {
+        match lobe {
+            Lobe::Diffuse => {
+                let mut wi = sampling::cosine_hemisphere((u[1], u[2]));
+
+                if wo.z < 0. {
+                    wi.z = -wi.z;
+                }
+
+                Some(wi)
+            }
+            Lobe::Specular => {
+                let wm = self.distribution().sample_wm(wo, (u[1], u[2]));
+
+                Some(reflect_about(wo, wm))
+            }
+            Lobe::Clearcoat => {
+                let wh = sample_gtr1((u[1], u[2]), self.clearcoat_alpha());
+
+                Some(reflect_about(wo, wh))
+            }
+            Lobe::Transmission => self.transmission_bsdf().sample(wo, u).map(|(wi, _)| wi),
+        }
+    }
+}
+
+impl Material for PrincipledMaterial {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.parameters(hit).eval(wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        if wo.z == 0. {
+            return None;
+        }
+
+        let parameters = self.parameters(hit);
+        let lobes = parameters.lobe_probabilities();
+        let mut remaining = u[0];
+        let mut chosen = lobes.len() - 1;
+
+        for (i, (_, probability)) in lobes.iter().enumerate() {
+            if remaining < *probability {
+                chosen = i;
+                break;
+            }
+
+            remaining -= probability;
+        }
+
+        let (lobe, probability) = lobes[chosen];
+
+        if probability == 0. {
+            return None;
+        }
+
+        let u0 = (remaining / probability).min(1. - f64::EPSILON);
+        let wi = parameters.sample_lobe(lobe, wo, [u0, u[1], u[2]])?;
+        let pdf = parameters.pdf(wo, wi);
+
+        if pdf == 0. {
+            return None;
+        }
+
+        let flags = match (lobe, same_hemisphere(wo, wi)) {
+            (Lobe::Diffuse, _) => BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION,
+            (_, true) => BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+            (_, false) => BsdfFlags::GLOSSY | BsdfFlags::TRANSMISSION,
+        };
+
+        Some(BsdfSample {
+            wi,
+            f: parameters.eval(wo, wi),
+            pdf,
+            flags,
+        })
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.parameters(hit).pdf(wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.interior
+    }
+
+    // Paths inside don't know where they entered, so the interior takes the
+    // index at the origin of texture space. Textures that vary the index
+    // still vary the reflections off the surface.
+    fn refractive_index(&self) -> f64 {
+        self.refractive_index
+            .value(0., 0., &Point3::new(0., 0., 0.))
+            .max(1.)
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+}
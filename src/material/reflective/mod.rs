@@ -0,0 +1,95 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{reflect, same_hemisphere, BsdfFlags, BsdfSample, Material};
+use crate::sampling;
+use crate::texture::Texture;
+
+pub struct ReflectiveMaterial<T: Texture> {
+    pub texture: T,
+}
+
+impl<T: Texture> Material for ReflectiveMaterial<T> {
+    fn eval(&self, _hit: &Hit, _wo: Unit3, _wi: Unit3) -> Color {
+        Color::new(0., 0., 0.)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, _u: [f64; 3]) -> Option<BsdfSample> {
+        let wi = reflect(wo);
+
+        if wi.z == 0. {
+            return None;
+        }
+
+        Some(BsdfSample {
+            wi,
+            f: self.texture.color_at(hit).scale(1. / wi.z.abs()),
+            pdf: 1.,
+            flags: BsdfFlags::SPECULAR | BsdfFlags::REFLECTION,
+        })
+    }
+
+    fn pdf(&self, _hit: &Hit, _wo: Unit3, _wi: Unit3) -> f64 {
+        0.
+    }
+}
+
+// Perturbs the mirror direction by a point drawn uniformly from a ball of
+// radius `fuzz`. Perturbations that end up below the surface are absorbed.
+pub struct FuzzyReflectiveMaterial<T: Texture> {
+    pub texture: T,
+    pub fuzz: f64,
+}
+
+impl<T: Texture> FuzzyReflectiveMaterial<T> {
+    fn ball_pdf(&self, wo: Unit3, wi: Unit3) -> f64 {
+        let d = wi.dot(reflect(wo));
+        let discriminant = d * d - 1. + self.fuzz * self.fuzz;
+
+        if discriminant <= 0. {
+            return 0.;
+        }
+
+        let t_near = (d - discriminant.sqrt()).max(0.);
+        let t_far = (d + discriminant.sqrt()).max(0.);
+
+        (t_far.powi(3) - t_near.powi(3)) / (4. * std::f64::consts::PI * self.fuzz.powi(3))
+    }
+}
+
+impl<T: Texture> Material for FuzzyReflectiveMaterial<T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            self.texture.color_at(hit).scale(pdf / wi.z.abs())
+        } else {
+            Color::new(0., 0., 0.)
+        }
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let fuzz_vector = sampling::uniform_ball(u) * self.fuzz;
+        let wi: Unit3 = (Vector3::from(reflect(wo)) + fuzz_vector).into();
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags: BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        if same_hemisphere(wo, wi) {
+            self.ball_pdf(wo, wi)
+        } else {
+            0.
+        }
+    }
+}
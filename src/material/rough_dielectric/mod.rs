@@ -0,0 +1,322 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{
+    dielectric_reflectance, fresnel_dielectric, reflect_about, refract_about, same_hemisphere,
+    sample_smooth_dielectric, upper, BsdfFlags, BsdfSample, Material, ThinFilm, TrowbridgeReitz,
+};
+use crate::medium::Medium;
+use crate::texture::Texture;
+
+// Microfacet reflection and transmission after Walter et al. 2007, "Microfacet
+// Models for Refraction through Rough Surfaces".
+#[derive(Debug, Copy, Clone)]
+pub struct MicrofacetDielectric {
+    pub distribution: TrowbridgeReitz,
+    pub refractive_index: f64,
+}
+
+impl MicrofacetDielectric {
+    // The generalized half vector for a pair of directions, facing the
+    // outside of the surface, and the relative index crossed between them.
+    // Returns `None` for degenerate configurations and for microfacets that
+    // face away from either direction.
+    fn half_vector(&self, wo: Unit3, wi: Unit3) -> Option<(Unit3, f64)> {
+        let reflection = wo.z * wi.z > 0.;
+        let eta = if reflection {
+            1.
+        } else if wo.z > 0. {
+            self.refractive_index
+        } else {
+            1. / self.refractive_index
+        };
+        let half = wi * eta + Vector3::from(wo);
+
+        if wo.z == 0. || wi.z == 0. || half.length_squared() == 0. {
+            return None;
+        }
+
+        let mut wm = half.normalize();
+
+        if wm.z < 0. {
+            wm = wm.reverse();
+        }
+
+        if wm.dot(wi) * wi.z < 0. || wm.dot(wo) * wo.z < 0. {
+            return None;
+        }
+
+        Some((wm, eta))
+    }
+
+    fn fresnel(&self, cos_i: f64) -> Color {
+        let fresnel = fresnel_dielectric(cos_i, self.refractive_index);
+
+        Color::new(fresnel, fresnel, fresnel)
+    }
+
+    pub fn eval(&self, wo: Unit3, wi: Unit3) -> f64 {
+        self.eval_with(wo, wi, &|cos_i| self.fresnel(cos_i)).r
+    }
+
+    pub fn sample(&self, wo: Unit3, u: [f64; 3]) -> Option<(Unit3, BsdfFlags)> {
+        self.sample_with(wo, u, &|cos_i| self.fresnel(cos_i))
+    }
+
+    pub fn pdf(&self, wo: Unit3, wi: Unit3) -> f64 {
+        self.pdf_with(wo, wi, &|cos_i| self.fresnel(cos_i))
+    }
+
+    // The `_with` variants take the reflectance of a microfacet in place of
+    // the dielectric Fresnel term, choosing between reflection and
+    // transmission by its luminance.
+    pub fn eval_with(&self, wo: Unit3, wi: Unit3, reflectance: &dyn Fn(f64) -> Color) -> Color {
+        let (wm, eta) = match self.half_vector(wo, wi) {
+            Some(half_vector) => half_vector,
+            None => return Color::new(0., 0., 0.),
+        };
+        let fresnel = reflectance(wo.dot(wm));
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+
+        if same_hemisphere(wo, wi) {
+            fresnel.scale(d * g / (4. * wi.z * wo.z).abs())
+        } else {
+            let denominator = (wi.dot(wm) + wo.dot(wm) / eta).powi(2) * wi.z * wo.z;
+
+            Color::new(1., 1., 1.)
+                .add(fresnel.scale(-1.))
+                .scale(d * g * (wi.dot(wm) * wo.dot(wm) / denominator).abs())
+        }
+    }
+
+    pub fn sample_with(
+        &self,
+        wo: Unit3,
+        u: [f64; 3],
+        reflectance: &dyn Fn(f64) -> Color,
+    ) -> Option<(Unit3, BsdfFlags)> {
+        if wo.z == 0. {
+            return None;
+        }
+
+        let wm = self.distribution.sample_wm(wo, (u[1], u[2]));
+
+        let (wi, flags) = if u[0] < reflectance(wo.dot(wm)).luminance() {
+            (
+                reflect_about(wo, wm),
+                BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+            )
+        } else {
+            let (wi, _) = refract_about(wo, wm, self.refractive_index)?;
+
+            (wi, BsdfFlags::GLOSSY | BsdfFlags::TRANSMISSION)
+        };
+
+        if flags.contains(BsdfFlags::REFLECTION) == same_hemisphere(wo, wi) {
+            Some((wi, flags))
+        } else {
+            None
+        }
+    }
+
+    pub fn pdf_with(&self, wo: Unit3, wi: Unit3, reflectance: &dyn Fn(f64) -> Color) -> f64 {
+        let (wm, eta) = match self.half_vector(wo, wi) {
+            Some(half_vector) => half_vector,
+            None => return 0.,
+        };
+        let probability = reflectance(wo.dot(wm)).luminance();
+
+        if same_hemisphere(wo, wi) {
+            self.distribution.pdf(wo, wm) / (4. * wo.dot(wm).abs()) * probability
+        } else {
+            let denominator = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+            let dwm_dwi = wi.dot(wm).abs() / denominator;
+
+            self.distribution.pdf(wo, wm) * dwm_dwi * (1. - probability)
+        }
+    }
+
+    // Thin-walled slabs reflect through the microfacet distribution, and
+    // transmit through the same lobe mirrored through the surface, so that
+    // transmission is blurred but not refracted.
+    pub fn eval_thin(&self, wo: Unit3, wi: Unit3, reflectance: &dyn Fn(f64) -> Color) -> Color {
+        let (wo_upper, wi_upper) = (upper(wo), upper(wi));
+
+        if wo.z == 0. || wi.z == 0. {
+            return Color::new(0., 0., 0.);
+        }
+
+        let wm = (Vector3::from(wo_upper) + Vector3::from(wi_upper)).normalize();
+        let fresnel = reflectance(wo_upper.dot(wm));
+        let lobe = self.distribution.d(wm) * self.distribution.g(wo_upper, wi_upper)
+            / (4. * wo_upper.z * wi_upper.z);
+
+        if same_hemisphere(wo, wi) {
+            fresnel.scale(lobe)
+        } else {
+            Color::new(1., 1., 1.).add(fresnel.scale(-1.)).scale(lobe)
+        }
+    }
+
+    pub fn sample_thin(
+        &self,
+        wo: Unit3,
+        u: [f64; 3],
+        reflectance: &dyn Fn(f64) -> Color,
+    ) -> Option<(Unit3, BsdfFlags)> {
+        if wo.z == 0. {
+            return None;
+        }
+
+        let wo_upper = upper(wo);
+        let wm = self.distribution.sample_wm(wo_upper, (u[1], u[2]));
+        let reflected = reflect_about(wo_upper, wm);
+
+        if reflected.z <= 0. {
+            return None;
+        }
+
+        if u[0] < reflectance(wo_upper.dot(wm)).luminance() {
+            Some((
+                Unit3 {
+                    z: reflected.z * wo.z.signum(),
+                    ..reflected
+                },
+                BsdfFlags::GLOSSY | BsdfFlags::REFLECTION,
+            ))
+        } else {
+            Some((
+                Unit3 {
+                    z: -reflected.z * wo.z.signum(),
+                    ..reflected
+                },
+                BsdfFlags::GLOSSY | BsdfFlags::TRANSMISSION,
+            ))
+        }
+    }
+
+    pub fn pdf_thin(&self, wo: Unit3, wi: Unit3, reflectance: &dyn Fn(f64) -> Color) -> f64 {
+        let (wo_upper, wi_upper) = (upper(wo), upper(wi));
+
+        if wo.z == 0. || wi.z == 0. {
+            return 0.;
+        }
+
+        let wm = (Vector3::from(wo_upper) + Vector3::from(wi_upper)).normalize();
+        let probability = reflectance(wo_upper.dot(wm)).luminance();
+        let pdf = self.distribution.pdf(wo_upper, wm) / (4. * wo_upper.dot(wm));
+
+        if same_hemisphere(wo, wi) {
+            pdf * probability
+        } else {
+            pdf * (1. - probability)
+        }
+    }
+}
+
+pub struct RoughDielectricMaterial<T: Texture> {
+    pub roughness: T,
+    pub refractive_index: f64,
+    pub interior: Option<Medium>,
+    pub priority: u32,
+    pub thin_film: Option<ThinFilm>,
+    pub thin_walled: bool,
+}
+
+impl<T: Texture> RoughDielectricMaterial<T> {
+    fn bsdf(&self, hit: &Hit) -> MicrofacetDielectric {
+        MicrofacetDielectric {
+            distribution: TrowbridgeReitz::new(self.roughness.value_at(hit)),
+            refractive_index: self.refractive_index / hit.exterior_refractive_index,
+        }
+    }
+
+    // Smooth surfaces, and interfaces between matching indices where there
+    // is no microfacet half vector for straight-through transmission, are
+    // handled as perfectly specular.
+    fn is_specular(bsdf: &MicrofacetDielectric) -> bool {
+        bsdf.distribution.is_smooth() || bsdf.refractive_index == 1.
+    }
+
+    fn reflectance<'a>(&'a self, hit: &'a Hit, eta: f64) -> impl Fn(f64) -> Color + 'a {
+        move |cos_i| dielectric_reflectance(hit, cos_i, eta, &self.thin_film, self.thin_walled)
+    }
+}
+
+impl<T: Texture> Material for RoughDielectricMaterial<T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        let bsdf = self.bsdf(hit);
+        let reflectance = self.reflectance(hit, bsdf.refractive_index);
+
+        if Self::is_specular(&bsdf) {
+            Color::new(0., 0., 0.)
+        } else if self.thin_walled {
+            bsdf.eval_thin(wo, wi, &reflectance)
+        } else {
+            bsdf.eval_with(wo, wi, &reflectance)
+        }
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let bsdf = self.bsdf(hit);
+        let reflectance = self.reflectance(hit, bsdf.refractive_index);
+
+        if Self::is_specular(&bsdf) {
+            return sample_smooth_dielectric(
+                wo,
+                bsdf.refractive_index,
+                reflectance(wo.z),
+                self.thin_walled,
+                u[0],
+            );
+        }
+
+        let (wi, flags) = if self.thin_walled {
+            bsdf.sample_thin(wo, u, &reflectance)?
+        } else {
+            bsdf.sample_with(wo, u, &reflectance)?
+        };
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let bsdf = self.bsdf(hit);
+        let reflectance = self.reflectance(hit, bsdf.refractive_index);
+
+        if Self::is_specular(&bsdf) {
+            0.
+        } else if self.thin_walled {
+            bsdf.pdf_thin(wo, wi, &reflectance)
+        } else {
+            bsdf.pdf_with(wo, wi, &reflectance)
+        }
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.interior
+    }
+
+    fn refractive_index(&self) -> f64 {
+        if self.thin_walled {
+            1.
+        } else {
+            self.refractive_index
+        }
+    }
+
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+}
@@ -0,0 +1,50 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfSample, Material, Matte};
+use crate::medium::Medium;
+
+// Seen by the camera, records only how much the rest of the scene shadows
+// or reflects onto `material`, compared with the background alone. Light
+// bounces off it elsewhere as it would off `material`.
+pub struct ShadowCatcherMaterial<M: Material> {
+    pub material: M,
+}
+
+impl<M: Material> Material for ShadowCatcherMaterial<M> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.material.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.material.sample(hit, wo, u)
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.material.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.material.interior()
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.material.refractive_index()
+    }
+
+    fn priority(&self) -> u32 {
+        self.material.priority()
+    }
+
+    fn matte(&self) -> Option<Matte> {
+        Some(Matte::ShadowCatcher)
+    }
+
+    fn frame(&self, hit: &Hit) -> Frame {
+        self.material.frame(hit)
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        self.material.alpha(hit)
+    }
+}
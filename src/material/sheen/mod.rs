@@ -0,0 +1,78 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{same_hemisphere, sin2_theta, BsdfFlags, BsdfSample, Material};
+use crate::sampling;
+use crate::texture::Texture;
+
+// Diffuse reflection under a layer of fibres that scatter light at grazing
+// angles, for velvet and other fabrics. The sheen is the "Charlie"
+// distribution of Estevez and Kulla 2017, "Production Friendly Microfacet
+// Sheen BRDF", with the visibility term of Neubelt and Pettineo 2013,
+// "Crafting a Next-Gen Material Pipeline for The Order: 1886".
+pub struct SheenMaterial<T: Texture, R: Texture> {
+    pub diffuse: T,
+    pub sheen: T,
+    pub roughness: R,
+}
+
+impl<T: Texture, R: Texture> SheenMaterial<T, R> {
+    fn distribution(&self, hit: &Hit, wm: Unit3) -> f64 {
+        let roughness = self.roughness.value_at(hit);
+        let inverse_alpha = 1. / (roughness * roughness).max(1e-3);
+
+        (2. + inverse_alpha) * sin2_theta(wm).powf(inverse_alpha / 2.) / (2. * std::f64::consts::PI)
+    }
+}
+
+impl<T: Texture, R: Texture> Material for SheenMaterial<T, R> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new(0., 0., 0.);
+        }
+
+        let diffuse = self
+            .diffuse
+            .color_at(hit)
+            .scale(std::f64::consts::FRAC_1_PI);
+        let (cos_o, cos_i) = (wo.z.abs(), wi.z.abs());
+        let wm = (Vector3::from(wo) + Vector3::from(wi)).normalize();
+        let visibility = 1. / (4. * (cos_i + cos_o - cos_i * cos_o));
+        let sheen = self
+            .sheen
+            .color_at(hit)
+            .scale(self.distribution(hit, wm) * visibility);
+
+        diffuse.add(sheen)
+    }
+
+    // The sheen is broad enough to be sampled along with the diffuse term.
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let mut wi = sampling::cosine_hemisphere((u[1], u[2]));
+
+        if wo.z < 0. {
+            wi.z = -wi.z;
+        }
+
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags: BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, _hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        if same_hemisphere(wo, wi) {
+            wi.z.abs() * std::f64::consts::FRAC_1_PI
+        } else {
+            0.
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{BsdfSample, Material, RoughDielectricMaterial};
+use crate::medium::Medium;
+use crate::texture::Texture;
+
+// A dielectric boundary around a scattering interior, rendered by random
+// walks through the interior of closed objects. `albedo` is the overall
+// colour of the material and `mean_free_path` is the average distance light
+// travels between scattering events, per colour channel.
+pub struct SubsurfaceMaterial<T: Texture> {
+    surface: RoughDielectricMaterial<T>,
+}
+
+impl<T: Texture> SubsurfaceMaterial<T> {
+    pub fn new(albedo: Color, mean_free_path: Color, roughness: T, refractive_index: f64) -> Self {
+        Self {
+            surface: RoughDielectricMaterial {
+                roughness,
+                refractive_index,
+                interior: Some(Medium::from_albedo(albedo, mean_free_path)),
+                priority: 0,
+                thin_film: None,
+                thin_walled: false,
+            },
+        }
+    }
+}
+
+impl<T: Texture> Material for SubsurfaceMaterial<T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        self.surface.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        self.surface.sample(hit, wo, u)
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        self.surface.pdf(hit, wo, wi)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.surface.interior()
+    }
+
+    fn refractive_index(&self) -> f64 {
+        self.surface.refractive_index()
+    }
+
+    fn priority(&self) -> u32 {
+        self.surface.priority()
+    }
+}
@@ -0,0 +1,1570 @@
+use rand::Rng;
+use rand_xoshiro::rand_core::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
+
+use num_complex::Complex64;
+
+use super::*;
+
+use crate::hittable::NO_ATTRIBUTES;
+use crate::medium::{Medium, MediumStack};
+use crate::sampling;
+use crate::texture::ConstantTexture;
+
+const SAMPLES: usize = 100_000;
+
+fn grey() -> ConstantTexture {
+    ConstantTexture {
+        color: Color::new(0.5, 0.5, 0.5),
+    }
+}
+
+fn hit(material: &dyn Material) -> Hit<'_> {
+    Hit {
+        t: 1.,
+        p: Point3::new(0., 0., 0.),
+        u: 0.5,
+        v: 0.5,
+        normal: Unit3::new(0., 0., 1.),
+        dpdu: Vector3::new(1., 0., 0.),
+        dpdv: Vector3::new(0., 1., 0.),
+        dudx: 0.,
+        dudy: 0.,
+        dvdx: 0.,
+        dvdy: 0.,
+        exterior_refractive_index: 1.,
+        material,
+        attributes: &NO_ATTRIBUTES,
+    }
+}
+
+fn wo() -> Unit3 {
+    Unit3::new(0.3, -0.2, 0.8)
+}
+
+fn assert_close(a: f64, b: f64, tolerance: f64) {
+    assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+}
+
+fn assert_sample_matches_eval_and_pdf(material: &dyn Material, wo: Unit3) {
+    let hit = hit(material);
+    let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+
+    for _ in 0..1000 {
+        if let Some(sample) = material.sample(&hit, wo, rng.gen()) {
+            let f = material.eval(&hit, wo, sample.wi);
+
+            assert_close(sample.pdf, material.pdf(&hit, wo, sample.wi), 1e-9);
+            assert_close(sample.f.r, f.r, 1e-9);
+            assert_close(sample.f.g, f.g, 1e-9);
+            assert_close(sample.f.b, f.b, 1e-9);
+        }
+    }
+}
+
+fn pdf_integral(material: &dyn Material, wo: Unit3) -> f64 {
+    let hit = hit(material);
+    let strata = 1000;
+    let sphere_area = 4. * std::f64::consts::PI;
+    let mut total = 0.;
+
+    for i in 0..strata {
+        for j in 0..strata {
+            let u = (
+                (f64::from(i) + 0.5) / f64::from(strata),
+                (f64::from(j) + 0.5) / f64::from(strata),
+            );
+
+            total += material.pdf(&hit, wo, sampling::uniform_sphere(u));
+        }
+    }
+
+    total * sphere_area / f64::from(strata * strata)
+}
+
+fn integrated_albedo(material: &dyn Material, wo: Unit3) -> Color {
+    let hit = hit(material);
+    let strata = 1000;
+    let sphere_area = 4. * std::f64::consts::PI;
+    let mut total = Color::new(0., 0., 0.);
+
+    for i in 0..strata {
+        for j in 0..strata {
+            let u = (
+                (f64::from(i) + 0.5) / f64::from(strata),
+                (f64::from(j) + 0.5) / f64::from(strata),
+            );
+            let wi = sampling::uniform_sphere(u);
+
+            total = total.add(material.eval(&hit, wo, wi).scale(wi.z.abs()));
+        }
+    }
+
+    total.scale(sphere_area / f64::from(strata * strata))
+}
+
+fn albedo(material: &dyn Material, wo: Unit3) -> Color {
+    let hit = hit(material);
+    let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+
+    (0..SAMPLES)
+        .filter_map(|_| material.sample(&hit, wo, rng.gen()))
+        .fold(Color::new(0., 0., 0.), |acc, sample| {
+            acc.add(sample.f.scale(sample.wi.z.abs() / sample.pdf))
+        })
+        .scale(1. / SAMPLES as f64)
+}
+
+mod lambertian {
+    use super::*;
+
+    fn subject() -> LambertianMaterial<ConstantTexture> {
+        LambertianMaterial { texture: grey() }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(), wo());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        assert_close(pdf_integral(&subject(), wo()), 1., 0.01);
+    }
+
+    #[test]
+    fn it_reflects_the_texture_color() {
+        assert_close(albedo(&subject(), wo()).r, 0.5, 1e-9);
+    }
+
+    #[test]
+    fn it_does_not_transmit() {
+        let subject = subject();
+
+        assert!(subject
+            .eval(&hit(&subject), wo(), wo().reverse())
+            .is_black());
+    }
+
+    #[test]
+    fn it_reflects_on_the_side_of_the_outgoing_direction() {
+        let subject = subject();
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let sample = subject
+            .sample(&hit(&subject), wo().reverse(), rng.gen())
+            .unwrap();
+
+        assert!(sample.wi.z < 0.);
+        assert!(sample
+            .flags
+            .contains(BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION));
+    }
+}
+
+mod fuzzy_reflective {
+    use super::*;
+
+    fn subject() -> FuzzyReflectiveMaterial<ConstantTexture> {
+        FuzzyReflectiveMaterial {
+            texture: grey(),
+            fuzz: 0.3,
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(), wo());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one_away_from_the_horizon() {
+        assert_close(pdf_integral(&subject(), wo()), 1., 0.01);
+    }
+
+    #[test]
+    fn it_reflects_the_texture_color() {
+        assert_close(albedo(&subject(), wo()).r, 0.5, 1e-9);
+    }
+}
+
+mod reflective {
+    use super::*;
+
+    #[test]
+    fn it_samples_the_mirror_direction() {
+        let subject = ReflectiveMaterial { texture: grey() };
+        let sample = subject.sample(&hit(&subject), wo(), [0.; 3]).unwrap();
+
+        assert_eq!(sample.wi, reflect(wo()));
+        assert!(sample.flags.contains(BsdfFlags::SPECULAR));
+        assert_close(sample.f.r * sample.wi.z / sample.pdf, 0.5, 1e-9);
+    }
+
+    #[test]
+    fn it_has_no_density_for_arbitrary_directions() {
+        let subject = ReflectiveMaterial { texture: grey() };
+        let hit = hit(&subject);
+
+        assert_eq!(subject.pdf(&hit, wo(), reflect(wo())), 0.);
+        assert!(subject.eval(&hit, wo(), reflect(wo())).is_black());
+    }
+}
+
+mod dielectric {
+    use super::*;
+
+    fn subject() -> DielectricMaterial<ConstantTexture> {
+        DielectricMaterial {
+            texture: ConstantTexture {
+                color: Color::new(1., 1., 1.),
+            },
+            refractive_index: 1.5,
+            priority: 0,
+            thin_film: None,
+            thin_walled: false,
+        }
+    }
+
+    #[test]
+    fn it_conserves_energy() {
+        assert_close(albedo(&subject(), wo()).r, 1., 1e-9);
+    }
+
+    #[test]
+    fn it_reflects_in_proportion_to_the_fresnel_term() {
+        let subject = subject();
+        let hit = hit(&subject);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let reflected = (0..SAMPLES)
+            .filter_map(|_| subject.sample(&hit, wo(), rng.gen()))
+            .filter(|sample| sample.flags.contains(BsdfFlags::REFLECTION))
+            .count();
+
+        assert_close(
+            reflected as f64 / SAMPLES as f64,
+            fresnel_dielectric(wo().z, 1.5),
+            0.005,
+        );
+    }
+
+    #[test]
+    fn it_refracts_towards_the_normal_on_entry() {
+        let subject = subject();
+        let sample = subject.sample(&hit(&subject), wo(), [1.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::TRANSMISSION));
+        assert!(sample.wi.z < -wo().z);
+    }
+
+    #[test]
+    fn it_totally_internally_reflects_at_grazing_exit() {
+        let subject = subject();
+        let grazing = Unit3::new(0.9, 0., -0.1);
+        let sample = subject.sample(&hit(&subject), grazing, [1.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::REFLECTION));
+        assert_eq!(sample.pdf, 1.);
+    }
+
+    #[test]
+    fn it_is_invisible_inside_a_matching_index() {
+        let subject = subject();
+        let hit = Hit {
+            exterior_refractive_index: 1.5,
+            ..hit(&subject)
+        };
+        let sample = subject.sample(&hit, wo(), [0.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::TRANSMISSION));
+        assert_close(sample.wi.dot(wo()), -1., 1e-9);
+        assert_close(sample.pdf, 1., 1e-9);
+    }
+
+    #[test]
+    fn it_uses_the_index_relative_to_its_exterior() {
+        let bubble = DielectricMaterial {
+            refractive_index: 1.,
+            ..subject()
+        };
+        let hit = Hit {
+            exterior_refractive_index: 1.5,
+            ..hit(&bubble)
+        };
+        let grazing = Unit3::new(0.9, 0., 0.1);
+        let sample = bubble.sample(&hit, grazing, [1.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::REFLECTION));
+        assert_eq!(sample.pdf, 1.);
+    }
+
+    #[test]
+    fn it_transmits_straight_through_when_thin_walled() {
+        let subject = DielectricMaterial {
+            thin_walled: true,
+            ..subject()
+        };
+        let sample = subject.sample(&hit(&subject), wo(), [1.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::TRANSMISSION));
+        assert_close(sample.wi.dot(wo()), -1., 1e-9);
+        assert_close(albedo(&subject, wo()).r, 1., 1e-9);
+    }
+
+    #[test]
+    fn it_reflects_more_when_thin_walled() {
+        let subject = DielectricMaterial {
+            thin_walled: true,
+            ..subject()
+        };
+        let sample = subject.sample(&hit(&subject), wo(), [0.; 3]).unwrap();
+        let fresnel = fresnel_dielectric(wo().z, 1.5);
+
+        assert_close(sample.pdf, 2. * fresnel / (1. + fresnel), 1e-9);
+    }
+
+    #[test]
+    fn it_colours_reflections_under_a_thin_film() {
+        let subject = DielectricMaterial {
+            thin_film: Some(ThinFilm {
+                thickness: Box::new(ConstantTexture::scalar(400.)),
+                refractive_index: 1.33,
+            }),
+            ..subject()
+        };
+        let sample = subject.sample(&hit(&subject), wo(), [0.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::REFLECTION));
+        assert!((sample.f.r - sample.f.b).abs() > 0.01);
+        assert_close(albedo(&subject, wo()).g, 1., 0.01);
+    }
+
+    #[test]
+    fn it_reflects_everything_under_a_thin_film_at_grazing_exit() {
+        let subject = DielectricMaterial {
+            thin_film: Some(ThinFilm {
+                thickness: Box::new(ConstantTexture::scalar(400.)),
+                refractive_index: 1.33,
+            }),
+            ..subject()
+        };
+        let hit = hit(&subject);
+        let grazing = Unit3::new(0.9, 0., -0.1);
+        let reflectance = dielectric_reflectance(&hit, grazing.z, 1.5, &subject.thin_film, false);
+        let sample = subject.sample(&hit, grazing, [0.; 3]).unwrap();
+        let weight = sample.f.scale(sample.wi.z.abs() / sample.pdf);
+
+        assert!(sample.flags.contains(BsdfFlags::REFLECTION));
+        assert_eq!(sample.pdf, 1.);
+        assert_close(weight.r, reflectance.r, 1e-9);
+        assert_close(weight.g, reflectance.g, 1e-9);
+        assert_close(weight.b, reflectance.b, 1e-9);
+    }
+
+    #[test]
+    fn it_weights_total_internal_reflection_by_the_reflectance() {
+        let reflectance = Color::new(0.9, 0.6, 0.3);
+        let grazing = Unit3::new(0.9, 0., -0.1);
+        let sample = sample_smooth_dielectric(grazing, 1.5, reflectance, false, 0.99).unwrap();
+        let weight = sample.f.scale(sample.wi.z.abs() / sample.pdf);
+
+        assert!(sample.flags.contains(BsdfFlags::REFLECTION));
+        assert_close(weight.r, reflectance.r, 1e-9);
+        assert_close(weight.g, reflectance.g, 1e-9);
+        assert_close(weight.b, reflectance.b, 1e-9);
+    }
+}
+
+mod thin_film {
+    use super::*;
+
+    #[test]
+    fn it_is_plain_fresnel_without_thickness() {
+        for &cos_i in &[0.9, 0.5, 0.1, -0.5, -0.9] {
+            let reflectance = fresnel_thin_film(cos_i, 1.5, 1.33, 0.);
+
+            assert_close(reflectance.r, fresnel_dielectric(cos_i, 1.5), 1e-9);
+            assert_close(reflectance.b, fresnel_dielectric(cos_i, 1.5), 1e-9);
+        }
+    }
+
+    #[test]
+    fn it_is_plain_fresnel_when_matching_the_exterior() {
+        let reflectance = fresnel_thin_film(0.7, 1.5, 1., 500.);
+
+        assert_close(reflectance.g, fresnel_dielectric(0.7, 1.5), 1e-9);
+    }
+
+    #[test]
+    fn a_quarter_wave_coating_cancels_reflection() {
+        let film_eta = 1.5_f64.sqrt();
+        let reflectance = fresnel_thin_film(1., 1.5, film_eta, 532. / (4. * film_eta));
+
+        assert_close(reflectance.g, 0., 1e-9);
+        assert!(reflectance.r > 0.);
+    }
+
+    #[test]
+    fn it_totally_internally_reflects() {
+        let reflectance = fresnel_thin_film(-0.1, 1.5, 1.33, 300.);
+
+        assert_close(reflectance.r, 1., 1e-9);
+        assert_close(reflectance.g, 1., 1e-9);
+        assert_close(reflectance.b, 1., 1e-9);
+    }
+}
+
+mod conductor {
+    use super::*;
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&ConductorMaterial::gold(0.5), wo());
+        assert_sample_matches_eval_and_pdf(
+            &ConductorMaterial::anisotropic(
+                Color::new(0.2, 0.9, 1.1),
+                Color::new(3.9, 2.5, 2.1),
+                0.2,
+                0.6,
+            ),
+            wo(),
+        );
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        assert_close(
+            pdf_integral(&ConductorMaterial::silver(0.3), wo()),
+            1.,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = ConductorMaterial::aluminium(0.6);
+
+        assert_close(
+            albedo(&subject, wo()).g,
+            integrated_albedo(&subject, wo()).g,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn it_is_a_tinted_mirror_when_smooth() {
+        let subject = ConductorMaterial::gold(0.);
+        let sample = subject.sample(&hit(&subject), wo(), [0.; 3]).unwrap();
+
+        assert_eq!(sample.wi, reflect(wo()));
+        assert!(sample.flags.contains(BsdfFlags::SPECULAR));
+        assert!(sample.f.r > sample.f.b);
+    }
+
+    #[test]
+    fn its_fresnel_term_matches_a_dielectric_without_absorption() {
+        for &cos in &[0.1, 0.5, 0.9] {
+            assert_close(
+                fresnel_complex(cos, Complex64::new(1.5, 0.)),
+                fresnel_dielectric(cos, 1.5),
+                1e-9,
+            );
+        }
+    }
+}
+
+mod microfacet {
+    use super::*;
+
+    #[test]
+    fn its_projected_normal_distribution_integrates_to_one() {
+        let distribution = TrowbridgeReitz::anisotropic(0.4, 0.7);
+        let strata = 1000;
+        let mut total = 0.;
+
+        for i in 0..strata {
+            for j in 0..strata {
+                let u = (
+                    (f64::from(i) + 0.5) / f64::from(strata),
+                    (f64::from(j) + 0.5) / f64::from(strata),
+                );
+                let wm = sampling::uniform_sphere(u);
+
+                if wm.z > 0. {
+                    total += distribution.d(wm) * wm.z;
+                }
+            }
+        }
+
+        assert_close(
+            total * 4. * std::f64::consts::PI / f64::from(strata * strata),
+            1.,
+            0.01,
+        );
+    }
+}
+
+mod rough_dielectric {
+    use super::*;
+
+    fn subject(roughness: f64) -> RoughDielectricMaterial<ConstantTexture> {
+        RoughDielectricMaterial {
+            roughness: ConstantTexture {
+                color: Color::new(roughness, roughness, roughness),
+            },
+            refractive_index: 1.5,
+            interior: None,
+            priority: 0,
+            thin_film: None,
+            thin_walled: false,
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf_from_either_side() {
+        assert_sample_matches_eval_and_pdf(&subject(0.5), wo());
+        assert_sample_matches_eval_and_pdf(&subject(0.5), wo().reverse());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        assert_close(pdf_integral(&subject(0.3), wo()), 1., 0.01);
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = subject(0.6);
+
+        assert_close(
+            albedo(&subject, wo()).r,
+            integrated_albedo(&subject, wo()).r,
+            0.01,
+        );
+        assert_close(
+            albedo(&subject, wo().reverse()).r,
+            integrated_albedo(&subject, wo().reverse()).r,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn it_does_not_create_energy() {
+        assert!(albedo(&subject(0.3), wo()).r <= 1.);
+    }
+
+    #[test]
+    fn it_totally_internally_reflects_at_grazing_exit() {
+        let subject = subject(0.05);
+        let hit = hit(&subject);
+        let grazing = Unit3::new(0.95, 0., -0.05);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            if let Some(sample) = subject.sample(&hit, grazing, rng.gen()) {
+                assert!(sample.flags.contains(BsdfFlags::REFLECTION));
+            }
+        }
+    }
+
+    #[test]
+    fn it_is_smooth_glass_without_roughness() {
+        let subject = subject(0.);
+        let sample = subject.sample(&hit(&subject), wo(), [1.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::SPECULAR));
+        assert!(sample.flags.contains(BsdfFlags::TRANSMISSION));
+    }
+
+    #[test]
+    fn it_is_invisible_inside_a_matching_index() {
+        let subject = subject(0.3);
+        let hit = Hit {
+            exterior_refractive_index: 1.5,
+            ..hit(&subject)
+        };
+        let sample = subject.sample(&hit, wo(), [0.5; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::TRANSMISSION));
+        assert_close(sample.wi.dot(wo()), -1., 1e-9);
+    }
+
+    mod thin_walled {
+        use super::*;
+
+        fn subject(roughness: f64) -> RoughDielectricMaterial<ConstantTexture> {
+            RoughDielectricMaterial {
+                thin_walled: true,
+                ..super::subject(roughness)
+            }
+        }
+
+        #[test]
+        fn its_samples_agree_with_eval_and_pdf_from_either_side() {
+            assert_sample_matches_eval_and_pdf(&subject(0.5), wo());
+            assert_sample_matches_eval_and_pdf(&subject(0.5), wo().reverse());
+        }
+
+        #[test]
+        fn its_pdf_integrates_to_one() {
+            assert_close(pdf_integral(&subject(0.3), wo()), 1., 0.01);
+        }
+
+        #[test]
+        fn its_sampling_matches_its_distribution() {
+            let subject = subject(0.6);
+
+            assert_close(
+                albedo(&subject, wo()).r,
+                integrated_albedo(&subject, wo()).r,
+                0.01,
+            );
+        }
+
+        #[test]
+        fn it_transmits_around_the_straight_through_direction() {
+            let subject = subject(0.1);
+            let hit = hit(&subject);
+            let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+            let transmitted: Vec<Unit3> = (0..1000)
+                .filter_map(|_| subject.sample(&hit, wo(), rng.gen()))
+                .filter(|sample| sample.flags.contains(BsdfFlags::TRANSMISSION))
+                .map(|sample| sample.wi)
+                .collect();
+            let straight = transmitted.iter().filter(|wi| wi.dot(wo()) < -0.99).count();
+
+            assert!(!transmitted.is_empty());
+            assert!(straight as f64 > 0.95 * transmitted.len() as f64);
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf_under_a_thin_film() {
+        let subject = RoughDielectricMaterial {
+            thin_film: Some(ThinFilm {
+                thickness: Box::new(ConstantTexture::scalar(400.)),
+                refractive_index: 1.33,
+            }),
+            ..subject(0.5)
+        };
+
+        assert_sample_matches_eval_and_pdf(&subject, wo());
+        assert_sample_matches_eval_and_pdf(&subject, wo().reverse());
+    }
+}
+
+mod principled {
+    use super::*;
+
+    fn subject() -> PrincipledMaterial {
+        PrincipledMaterial {
+            metallic: Box::new(ConstantTexture::scalar(0.3)),
+            roughness: Box::new(ConstantTexture::scalar(0.4)),
+            sheen: Box::new(ConstantTexture::scalar(0.5)),
+            clearcoat: Box::new(ConstantTexture::scalar(1.)),
+            clearcoat_gloss: Box::new(ConstantTexture::scalar(0.5)),
+            transmission: Box::new(ConstantTexture::scalar(0.5)),
+            ..PrincipledMaterial::new(ConstantTexture {
+                color: Color::new(0.8, 0.4, 0.2),
+            })
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf_from_either_side() {
+        assert_sample_matches_eval_and_pdf(&subject(), wo());
+        assert_sample_matches_eval_and_pdf(&subject(), wo().reverse());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_at_most_one() {
+        let integral = pdf_integral(&subject(), wo());
+
+        assert!(integral <= 1.01);
+        assert!(integral >= 0.9);
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = subject();
+
+        assert_close(
+            albedo(&subject, wo()).g,
+            integrated_albedo(&subject, wo()).g,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn it_reflects_its_base_color_when_metallic() {
+        let subject = PrincipledMaterial {
+            metallic: Box::new(ConstantTexture::scalar(1.)),
+            ..PrincipledMaterial::new(ConstantTexture {
+                color: Color::new(1., 0.5, 0.),
+            })
+        };
+        let albedo = albedo(&subject, wo());
+
+        assert!(albedo.r <= 1.);
+        assert!(albedo.r > albedo.g);
+        assert!(albedo.g > albedo.b);
+    }
+
+    #[test]
+    fn it_only_transmits_with_transmission() {
+        let subject = PrincipledMaterial::new(ConstantTexture::scalar(0.5));
+        let hit = hit(&subject);
+
+        assert!(subject.eval(&hit, wo(), wo().reverse()).is_black());
+        assert_eq!(subject.pdf(&hit, wo(), wo().reverse()), 0.);
+    }
+
+    #[test]
+    fn it_uses_the_index_relative_to_its_exterior() {
+        let bubble = PrincipledMaterial {
+            roughness: Box::new(ConstantTexture::scalar(0.)),
+            transmission: Box::new(ConstantTexture::scalar(1.)),
+            refractive_index: Box::new(ConstantTexture::scalar(1.)),
+            ..PrincipledMaterial::new(ConstantTexture::scalar(1.))
+        };
+        let hit = Hit {
+            exterior_refractive_index: 1.5,
+            ..hit(&bubble)
+        };
+        let grazing = Unit3::new(0.9, 0., 0.1);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            if let Some(sample) = bubble.sample(&hit, grazing, rng.gen()) {
+                assert!(!sample.flags.contains(BsdfFlags::TRANSMISSION));
+            }
+        }
+    }
+
+    #[test]
+    fn it_fills_nested_media_by_priority() {
+        let water = PrincipledMaterial {
+            transmission: Box::new(ConstantTexture::scalar(1.)),
+            refractive_index: Box::new(ConstantTexture::scalar(1.33)),
+            interior: Some(Medium::from_transmittance(Color::new(0.8, 0.9, 1.), 1.)),
+            priority: 1,
+            ..PrincipledMaterial::new(ConstantTexture::scalar(1.))
+        };
+        let glass = PrincipledMaterial {
+            transmission: Box::new(ConstantTexture::scalar(1.)),
+            priority: 2,
+            ..PrincipledMaterial::new(ConstantTexture::scalar(1.))
+        };
+        let stack = MediumStack::default().entered(&glass).entered(&water);
+
+        assert_close(stack.refractive_index(), 1.5, 1e-9);
+        assert!(stack.medium().is_none());
+
+        let stack = stack.exited(&glass);
+
+        assert_close(stack.refractive_index(), 1.33, 1e-9);
+        assert!(stack.medium().is_some());
+    }
+}
+
+mod measured {
+    use super::*;
+
+    fn table(resolutions: [i32; 3], value: impl Fn(usize) -> f64) -> Vec<u8> {
+        let n = resolutions.iter().product::<i32>() as usize;
+        let mut bytes: Vec<u8> = resolutions
+            .iter()
+            .flat_map(|resolution| resolution.to_le_bytes().to_vec())
+            .collect();
+
+        for _ in 0..3 {
+            for i in 0..n {
+                bytes.extend_from_slice(&value(i).to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    fn glossy() -> MeasuredMaterial {
+        let bytes = table([8, 4, 8], |i| if i < 4 * 8 { 150_000. } else { 150. });
+
+        MeasuredMaterial::read(&mut &bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn it_loads_a_synthetic_table_file() {
+        let path = std::env::temp_dir().join(format!("raygrass-{}.binary", std::process::id()));
+
+        std::fs::write(&path, table([2, 2, 4], |_| 1500.)).unwrap();
+
+        let subject = MeasuredMaterial::open(&path).unwrap();
+        let f = subject.eval(&hit(&subject), wo(), Unit3::new(-0.5, 0.1, 0.6));
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_close(f.r, 1., 1e-9);
+        assert_close(f.g, 1.15, 1e-9);
+        assert_close(f.b, 1.66, 1e-9);
+    }
+
+    #[test]
+    fn it_rejects_a_truncated_table() {
+        let bytes = table([2, 2, 4], |_| 1500.);
+
+        assert!(MeasuredMaterial::read(&mut &bytes[..bytes.len() - 8]).is_err());
+        assert!(MeasuredMaterial::read(&mut &table([0, 2, 4], |_| 1.)[..]).is_err());
+    }
+
+    #[test]
+    fn it_rejects_an_oversized_header_before_reading_the_table() {
+        let mut bytes: Vec<u8> = vec![];
+
+        for _ in 0..3 {
+            bytes.extend_from_slice(&i32::MAX.to_le_bytes());
+        }
+
+        let error = MeasuredMaterial::read(&mut &bytes[..]).err().unwrap();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_indexes_the_half_angle_non_linearly() {
+        let bytes = table([4, 1, 1], |i| 1500. * i as f64);
+        let subject = MeasuredMaterial::read(&mut &bytes[..]).unwrap();
+        let hit = hit(&subject);
+        let normal = Unit3::new(0., 0., 1.);
+        let tilted = Unit3::new(1., 0., 1.);
+
+        assert_close(subject.eval(&hit, normal, normal).r, 0., 1e-9);
+        assert_close(subject.eval(&hit, tilted, tilted).r, 2., 1e-9);
+    }
+
+    #[test]
+    fn it_does_not_transmit() {
+        let subject = glossy();
+
+        assert_eq!(
+            subject.eval(&hit(&subject), wo(), wo().reverse()),
+            Color::new(0., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf_from_either_side() {
+        assert_sample_matches_eval_and_pdf(&glossy(), wo());
+        assert_sample_matches_eval_and_pdf(&glossy(), wo().reverse());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        assert_close(pdf_integral(&glossy(), wo()), 1., 0.02);
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = glossy();
+
+        assert_close(
+            albedo(&subject, wo()).g,
+            integrated_albedo(&subject, wo()).g,
+            0.02,
+        );
+    }
+
+    #[test]
+    fn its_sampling_follows_the_specular_peak() {
+        let subject = glossy();
+        let hit = hit(&subject);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let near_mirror = (0..1000)
+            .filter_map(|_| subject.sample(&hit, wo(), rng.gen()))
+            .filter(|sample| sample.wi.dot(reflect(wo())) > 0.9)
+            .count();
+
+        assert!(near_mirror > 500);
+    }
+}
+
+mod matte {
+    use super::*;
+
+    #[test]
+    fn a_holdout_absorbs_all_light() {
+        let subject = HoldoutMaterial;
+
+        assert!(subject.sample(&hit(&subject), wo(), [0.5; 3]).is_none());
+        assert_eq!(subject.matte(), Some(Matte::Holdout));
+    }
+
+    #[test]
+    fn a_shadow_catcher_scatters_like_its_material() {
+        let subject = ShadowCatcherMaterial {
+            material: LambertianMaterial { texture: grey() },
+        };
+
+        assert_sample_matches_eval_and_pdf(&subject, wo());
+        assert_close(albedo(&subject, wo()).r, 0.5, 0.01);
+        assert_eq!(subject.matte(), Some(Matte::ShadowCatcher));
+        assert_eq!(subject.material.matte(), None);
+    }
+
+    #[test]
+    fn a_shadow_catcher_keeps_the_alpha_and_frame_of_its_material() {
+        let subject = ShadowCatcherMaterial {
+            material: AlphaMaterial {
+                material: BumpMaterial {
+                    material: LambertianMaterial { texture: grey() },
+                    bump: crate::texture::UVTexture {},
+                    scale: 1.,
+                },
+                alpha: ConstantTexture::scalar(0.25),
+            },
+        };
+        let hit = hit(&subject);
+
+        assert_close(subject.alpha(&hit), 0.25, 1e-9);
+        assert_eq!(subject.frame(&hit).n, subject.material.frame(&hit).n);
+        assert_ne!(subject.frame(&hit).n, Unit3::new(0., 0., 1.));
+        assert_eq!(subject.matte(), Some(Matte::ShadowCatcher));
+    }
+}
+
+mod mix {
+    use super::*;
+
+    fn subject(
+        weight: f64,
+    ) -> MixMaterial<LambertianMaterial<ConstantTexture>, ConductorMaterial, ConstantTexture> {
+        MixMaterial {
+            first: LambertianMaterial { texture: grey() },
+            second: ConductorMaterial::gold(0.4),
+            weight: ConstantTexture::scalar(weight),
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(0.3), wo());
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = subject(0.3);
+
+        assert_close(
+            albedo(&subject, wo()).r,
+            integrated_albedo(&subject, wo()).r,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn it_is_a_matte_only_where_both_materials_are() {
+        let holdouts = MixMaterial {
+            first: HoldoutMaterial,
+            second: HoldoutMaterial,
+            weight: ConstantTexture::scalar(0.5),
+        };
+        let half_held_out = MixMaterial {
+            first: HoldoutMaterial,
+            second: LambertianMaterial { texture: grey() },
+            weight: ConstantTexture::scalar(0.5),
+        };
+
+        assert_eq!(holdouts.matte(), Some(Matte::Holdout));
+        assert_eq!(half_held_out.matte(), None);
+    }
+
+    #[test]
+    fn it_is_the_first_material_at_zero_weight() {
+        let subject = subject(0.);
+        let hit = hit(&subject);
+        let wi = Unit3::new(-0.5, 0.1, 0.4);
+
+        assert_eq!(
+            subject.eval(&hit, wo(), wi),
+            subject.first.eval(&hit, wo(), wi)
+        );
+        assert_eq!(
+            subject.pdf(&hit, wo(), wi),
+            subject.first.pdf(&hit, wo(), wi)
+        );
+    }
+
+    #[test]
+    fn it_keeps_specular_samples_unbiased() {
+        let subject = MixMaterial {
+            first: LambertianMaterial { texture: grey() },
+            second: ReflectiveMaterial { texture: grey() },
+            weight: ConstantTexture::scalar(0.5),
+        };
+
+        assert_close(albedo(&subject, wo()).r, 0.5, 0.01);
+    }
+}
+
+mod coated {
+    use super::*;
+
+    fn subject(
+        roughness: f64,
+        absorption: Option<Medium>,
+    ) -> CoatedMaterial<LambertianMaterial<ConstantTexture>, ConstantTexture> {
+        CoatedMaterial {
+            base: LambertianMaterial {
+                texture: ConstantTexture::scalar(1.),
+            },
+            roughness: ConstantTexture::scalar(roughness),
+            refractive_index: 1.5,
+            thickness: 0.1,
+            absorption,
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(0.3, None), wo());
+        assert_sample_matches_eval_and_pdf(&subject(0.3, None), wo().reverse());
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = subject(0.3, None);
+
+        assert_close(
+            albedo(&subject, wo()).r,
+            integrated_albedo(&subject, wo()).r,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn it_does_not_create_energy() {
+        assert!(albedo(&subject(0., None), wo()).r <= 1.);
+        assert!(albedo(&subject(0.3, None), wo()).r <= 1.);
+    }
+
+    #[test]
+    fn it_darkens_with_an_absorbing_coating() {
+        let clear = albedo(&subject(0., None), wo());
+        let tinted = albedo(
+            &subject(
+                0.,
+                Some(Medium::from_transmittance(Color::new(0.5, 0.5, 1.), 0.1)),
+            ),
+            wo(),
+        );
+
+        assert!(tinted.r < clear.r);
+        assert_close(tinted.b, clear.b, 1e-9);
+    }
+}
+
+mod bump {
+    use super::*;
+
+    use crate::texture::{Texture, UVTexture};
+
+    #[test]
+    fn it_keeps_the_geometric_frame_for_a_flat_bump() {
+        let subject = BumpMaterial {
+            material: LambertianMaterial { texture: grey() },
+            bump: ConstantTexture::scalar(0.3),
+            scale: 1.,
+        };
+
+        assert_eq!(subject.frame(&hit(&subject)).n, Unit3::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn it_tilts_the_normal_against_the_bump_gradient() {
+        let subject = BumpMaterial {
+            material: LambertianMaterial { texture: grey() },
+            bump: UVTexture {},
+            scale: 1.,
+        };
+        let normal = subject.frame(&hit(&subject)).n;
+        let expected = Unit3::new(-0.2126, -0.7152, 1.);
+
+        assert_close(normal.dot(expected), 1., 1e-6);
+    }
+
+    fn bumped() -> BumpMaterial<LambertianMaterial<ConstantTexture>, UVTexture> {
+        BumpMaterial {
+            material: LambertianMaterial { texture: grey() },
+            bump: UVTexture {},
+            scale: 1.,
+        }
+    }
+
+    // A ramp along u that can only be looked up at a hit.
+    struct HitOnlyTexture;
+
+    impl Texture for HitOnlyTexture {
+        fn color(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+            Color::new(0., 0., 0.)
+        }
+
+        fn color_at(&self, hit: &Hit) -> Color {
+            Color::new(hit.u, hit.u, hit.u)
+        }
+    }
+
+    #[test]
+    fn it_looks_the_bump_up_at_the_hit() {
+        let subject = BumpMaterial {
+            material: LambertianMaterial { texture: grey() },
+            bump: HitOnlyTexture,
+            scale: 1.,
+        };
+        let normal = subject.frame(&hit(&subject)).n;
+
+        assert_close(normal.dot(Unit3::new(-1., 0., 1.)), 1., 1e-6);
+    }
+
+    #[test]
+    fn it_is_kept_under_a_coating() {
+        let subject = CoatedMaterial {
+            base: bumped(),
+            roughness: ConstantTexture::scalar(0.),
+            refractive_index: 1.5,
+            thickness: 0.,
+            absorption: None,
+        };
+        let hit = hit(&subject);
+
+        assert_eq!(subject.frame(&hit).n, bumped().frame(&hit).n);
+    }
+
+    #[test]
+    fn it_is_kept_where_it_outweighs_the_other_side_of_a_mix() {
+        let mix = |weight| MixMaterial {
+            first: LambertianMaterial { texture: grey() },
+            second: bumped(),
+            weight: ConstantTexture::scalar(weight),
+        };
+        let bumped_mix = mix(0.8);
+        let flat_mix = mix(0.2);
+
+        assert_eq!(
+            bumped_mix.frame(&hit(&bumped_mix)).n,
+            bumped().frame(&hit(&bumped_mix)).n
+        );
+        assert_eq!(flat_mix.frame(&hit(&flat_mix)).n, Unit3::new(0., 0., 1.));
+    }
+}
+
+mod normal_map {
+    use super::*;
+
+    use crate::mipmap::Filter;
+    use image::{DynamicImage, ImageBuffer, Rgb};
+
+    // A one texel normal map with the 16-bit colour `rgb`.
+    fn subject(rgb: [u16; 3]) -> NormalMapMaterial<LambertianMaterial<ConstantTexture>> {
+        let image = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(1, 1, Rgb(rgb)));
+
+        NormalMapMaterial::new(
+            LambertianMaterial { texture: grey() },
+            &image,
+            Filter::Nearest,
+        )
+    }
+
+    #[test]
+    fn it_keeps_the_normal_for_a_flat_map() {
+        let subject = subject([32768, 32768, 65535]);
+        let normal = subject.frame(&hit(&subject)).n;
+
+        // Half is a hair above 32767.5 in sixteen bits.
+        assert_close(normal.dot(Unit3::new(0., 0., 1.)), 1., 1e-9);
+    }
+
+    #[test]
+    fn it_tilts_the_normal_by_the_map() {
+        let subject = subject([65535, 32768, 65535]);
+        let normal = subject.frame(&hit(&subject)).n;
+
+        assert_close(normal.dot(Unit3::new(1., 0., 1.)), 1., 1e-9);
+    }
+
+    #[test]
+    fn it_keeps_the_rest_of_the_material() {
+        let subject = subject([65535, 32768, 65535]);
+        let hit = hit(&subject);
+
+        assert_eq!(
+            subject.eval(&hit, wo(), Unit3::new(0., 0., 1.)),
+            LambertianMaterial { texture: grey() }.eval(&hit, wo(), Unit3::new(0., 0., 1.))
+        );
+    }
+}
+
+mod oren_nayar {
+    use super::*;
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        let subject = OrenNayarMaterial {
+            texture: grey(),
+            sigma: 0.5,
+        };
+
+        assert_sample_matches_eval_and_pdf(&subject, wo());
+        assert_sample_matches_eval_and_pdf(&subject, wo().reverse());
+    }
+
+    #[test]
+    fn it_is_lambertian_when_smooth() {
+        let subject = OrenNayarMaterial {
+            texture: grey(),
+            sigma: 0.,
+        };
+        let lambertian = LambertianMaterial { texture: grey() };
+        let wi = Unit3::new(-0.5, 0.1, 0.4);
+
+        assert_eq!(
+            subject.eval(&hit(&subject), wo(), wi),
+            lambertian.eval(&hit(&lambertian), wo(), wi)
+        );
+    }
+
+    #[test]
+    fn it_scatters_more_towards_the_light_than_lambertian_at_grazing_angles() {
+        let subject = OrenNayarMaterial {
+            texture: grey(),
+            sigma: 0.5,
+        };
+        let grazing = Unit3::new(0.95, 0., 0.1);
+        let back = Unit3::new(0.9, 0.1, 0.2);
+
+        assert!(subject.eval(&hit(&subject), grazing, back).r > 0.5 / std::f64::consts::PI);
+    }
+}
+
+mod translucent {
+    use super::*;
+
+    fn subject() -> TranslucentMaterial<ConstantTexture, ConstantTexture> {
+        TranslucentMaterial {
+            reflectance: ConstantTexture {
+                color: Color::new(0.6, 0.3, 0.2),
+            },
+            transmittance: ConstantTexture {
+                color: Color::new(0.2, 0.5, 0.1),
+            },
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(), wo());
+        assert_sample_matches_eval_and_pdf(&subject(), wo().reverse());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        assert_close(pdf_integral(&subject(), wo()), 1., 0.01);
+    }
+
+    #[test]
+    fn it_transmits_its_transmittance() {
+        let subject = subject();
+        let hit = hit(&subject);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let transmitted = (0..SAMPLES)
+            .filter_map(|_| subject.sample(&hit, wo(), rng.gen()))
+            .filter(|sample| sample.flags.contains(BsdfFlags::TRANSMISSION))
+            .fold(Color::new(0., 0., 0.), |acc, sample| {
+                acc.add(sample.f.scale(sample.wi.z.abs() / sample.pdf))
+            })
+            .scale(1. / SAMPLES as f64);
+
+        assert_close(transmitted.g, 0.5, 0.01);
+    }
+}
+
+mod white_furnace {
+    use super::*;
+
+    fn white() -> ConstantTexture {
+        ConstantTexture::scalar(1.)
+    }
+
+    fn directions() -> Vec<Unit3> {
+        vec![
+            Unit3::new(0., 0., 1.),
+            wo(),
+            Unit3::new(0.7, 0.1, 0.3),
+            Unit3::new(-0.2, 0.9, 0.05),
+        ]
+    }
+
+    #[test]
+    fn lambertian_conserves_energy() {
+        let subject = LambertianMaterial { texture: white() };
+
+        for wo in directions() {
+            assert_close(albedo(&subject, wo).r, 1., 1e-9);
+            assert_close(integrated_albedo(&subject, wo).r, 1., 0.01);
+        }
+    }
+
+    #[test]
+    fn translucent_conserves_energy() {
+        let subject = TranslucentMaterial {
+            reflectance: ConstantTexture::scalar(0.4),
+            transmittance: ConstantTexture::scalar(0.6),
+        };
+
+        for wo in directions() {
+            assert_close(albedo(&subject, wo).r, 1., 1e-9);
+            assert_close(integrated_albedo(&subject, wo).r, 1., 0.01);
+        }
+    }
+
+    #[test]
+    fn oren_nayar_does_not_create_energy() {
+        let subject = OrenNayarMaterial {
+            texture: white(),
+            sigma: 0.8,
+        };
+
+        for wo in directions() {
+            let albedo = albedo(&subject, wo).r;
+
+            assert!(albedo <= 1.);
+            assert!(albedo >= 0.5);
+            assert_close(albedo, integrated_albedo(&subject, wo).r, 0.01);
+        }
+    }
+}
+
+mod subsurface {
+    use super::*;
+
+    #[test]
+    fn its_interior_scatters_at_the_mean_free_path() {
+        let subject = SubsurfaceMaterial::new(
+            Color::new(0.8, 0.8, 0.8),
+            Color::new(0.5, 0.25, 0.1),
+            ConstantTexture::scalar(0.),
+            1.3,
+        );
+        let extinction = subject.interior().unwrap().extinction();
+
+        assert_close(extinction.r, 2., 1e-9);
+        assert_close(extinction.g, 4., 1e-9);
+        assert_close(extinction.b, 10., 1e-9);
+    }
+
+    #[test]
+    fn it_transmits_into_its_interior() {
+        let subject = SubsurfaceMaterial::new(
+            Color::new(0.8, 0.8, 0.8),
+            Color::new(0.5, 0.5, 0.5),
+            ConstantTexture::scalar(0.),
+            1.3,
+        );
+        let sample = subject.sample(&hit(&subject), wo(), [1.; 3]).unwrap();
+
+        assert!(sample.flags.contains(BsdfFlags::TRANSMISSION));
+        assert!(sample.wi.z < 0.);
+    }
+}
+
+mod brushed_metal {
+    use super::*;
+
+    fn subject() -> BrushedMetalMaterial {
+        BrushedMetalMaterial::new(ConductorMaterial::silver(0.), 0.1, 0.5)
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(), wo());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        let subject = BrushedMetalMaterial::new(ConductorMaterial::silver(0.), 0.2, 0.3);
+
+        assert_close(pdf_integral(&subject, wo()), 1., 0.01);
+    }
+
+    #[test]
+    fn it_stretches_highlights_across_the_brushing_direction() {
+        let subject = subject();
+        let hit = hit(&subject);
+        let wo = Unit3::new(0., 0., 1.);
+        let along = subject.eval(&hit, wo, Unit3::new(0.3, 0., 0.95));
+        let across = subject.eval(&hit, wo, Unit3::new(0., 0.3, 0.95));
+
+        assert!(across.g > 2. * along.g);
+    }
+
+    #[test]
+    fn it_follows_the_tangent_without_a_flow_map() {
+        let subject = subject();
+        let hit = hit(&subject);
+
+        assert_eq!(subject.frame(&hit), hit.frame());
+    }
+
+    #[test]
+    fn it_follows_its_flow_map() {
+        let subject = BrushedMetalMaterial {
+            flow: Some(Box::new(ConstantTexture {
+                color: Color::new(0.5, 1., 0.5),
+            })),
+            ..subject()
+        };
+        let frame = subject.frame(&hit(&subject));
+
+        assert_close(frame.s.y, 1., 1e-9);
+        assert_close(frame.n.z, 1., 1e-9);
+    }
+}
+
+mod sheen {
+    use super::*;
+
+    fn subject(diffuse: f64, roughness: f64) -> SheenMaterial<ConstantTexture, ConstantTexture> {
+        SheenMaterial {
+            diffuse: ConstantTexture::scalar(diffuse),
+            sheen: ConstantTexture::scalar(1.),
+            roughness: ConstantTexture::scalar(roughness),
+        }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        assert_sample_matches_eval_and_pdf(&subject(0.5, 0.5), wo());
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        assert_close(pdf_integral(&subject(0.5, 0.5), wo()), 1., 0.01);
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = subject(0.5, 0.3);
+
+        assert_close(
+            albedo(&subject, wo()).g,
+            integrated_albedo(&subject, wo()).g,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn its_sheen_does_not_create_energy() {
+        for &roughness in &[0.1, 0.5, 1.] {
+            for &wo in &[wo(), Unit3::new(0.95, 0., 0.1)] {
+                assert!(integrated_albedo(&subject(0., roughness), wo).g <= 1.);
+            }
+        }
+    }
+
+    #[test]
+    fn its_sheen_is_brightest_at_grazing_angles() {
+        let subject = subject(0., 0.5);
+        let grazing = integrated_albedo(&subject, Unit3::new(0.95, 0., 0.1));
+        let normal = integrated_albedo(&subject, Unit3::new(0., 0., 1.));
+
+        assert!(grazing.g > normal.g);
+    }
+}
+
+mod hair {
+    use super::*;
+
+    fn subject(sigma_a: f64) -> HairMaterial {
+        HairMaterial::new(Color::new(sigma_a, sigma_a, sigma_a), 0.3, 0.3)
+    }
+
+    fn directions() -> Vec<Unit3> {
+        vec![wo(), Unit3::new(0.8, 0.3, 0.2), Unit3::new(-0.2, 0.9, -0.3)]
+    }
+
+    fn across(material: &dyn Material, v: f64) -> Hit<'_> {
+        Hit { v, ..hit(material) }
+    }
+
+    #[test]
+    fn its_samples_agree_with_eval_and_pdf() {
+        let subject = subject(0.5);
+        let hit = across(&subject, 0.8);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+
+        for wo in directions() {
+            for _ in 0..1000 {
+                if let Some(sample) = subject.sample(&hit, wo, rng.gen()) {
+                    let f = subject.eval(&hit, wo, sample.wi);
+
+                    assert_close(sample.pdf, subject.pdf(&hit, wo, sample.wi), 1e-9);
+                    assert_close(sample.f.g, f.g, 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn its_pdf_integrates_to_one() {
+        for wo in directions() {
+            assert_close(pdf_integral(&subject(0.5), wo), 1., 0.02);
+        }
+    }
+
+    #[test]
+    fn it_does_not_absorb_without_pigment() {
+        for wo in directions() {
+            assert_close(integrated_albedo(&subject(0.), wo).g, 1., 0.05);
+        }
+    }
+
+    #[test]
+    fn its_sampling_matches_its_distribution() {
+        let subject = subject(0.5);
+
+        assert_close(
+            albedo(&subject, wo()).g,
+            integrated_albedo(&subject, wo()).g,
+            0.01,
+        );
+    }
+
+    #[test]
+    fn it_is_darker_with_more_eumelanin() {
+        let blonde = HairMaterial::from_melanin(0.3, 0., 0.3, 0.3);
+        let black = HairMaterial::from_melanin(8., 0., 0.3, 0.3);
+
+        assert!(integrated_albedo(&black, wo()).g < integrated_albedo(&blonde, wo()).g);
+    }
+
+    #[test]
+    fn it_takes_the_hue_of_a_color() {
+        let subject = HairMaterial::from_color(Color::new(0.45, 0.15, 0.05), 0.3, 0.3);
+
+        assert!(subject.sigma_a.r < subject.sigma_a.g);
+        assert!(subject.sigma_a.g < subject.sigma_a.b);
+    }
+
+    #[test]
+    fn it_reflects_and_transmits_through_the_fibre() {
+        let subject = subject(0.1);
+        let hit = hit(&subject);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let samples: Vec<BsdfSample> = (0..1000)
+            .filter_map(|_| subject.sample(&hit, wo(), rng.gen()))
+            .collect();
+
+        assert!(samples
+            .iter()
+            .any(|sample| sample.flags.contains(BsdfFlags::REFLECTION)));
+        assert!(samples
+            .iter()
+            .any(|sample| sample.flags.contains(BsdfFlags::TRANSMISSION)));
+    }
+}
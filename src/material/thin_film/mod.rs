@@ -0,0 +1,24 @@
+use crate::color::Color;
+use crate::hittable::Hit;
+use crate::material::fresnel_thin_film;
+use crate::texture::Texture;
+
+// A thin film over the surface of a dielectric, such as an anti-reflective
+// coating, which colours its reflections by interference. The `thickness`
+// texture gives the thickness of the film in nanometres. A soap bubble is
+// a film over a dielectric with the index of its surroundings.
+pub struct ThinFilm {
+    pub thickness: Box<dyn Texture>,
+    pub refractive_index: f64,
+}
+
+impl ThinFilm {
+    pub fn reflectance(&self, hit: &Hit, cos_i: f64, eta: f64) -> Color {
+        fresnel_thin_film(
+            cos_i,
+            eta,
+            self.refractive_index / hit.exterior_refractive_index,
+            self.thickness.value_at(hit),
+        )
+    }
+}
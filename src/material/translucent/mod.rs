@@ -0,0 +1,83 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::material::{same_hemisphere, BsdfFlags, BsdfSample, Material};
+use crate::sampling;
+use crate::texture::Texture;
+
+// Diffuse reflection and diffuse transmission through a thin surface, such as
+// paper or a leaf.
+pub struct TranslucentMaterial<R: Texture, T: Texture> {
+    pub reflectance: R,
+    pub transmittance: T,
+}
+
+impl<R: Texture, T: Texture> TranslucentMaterial<R, T> {
+    fn colors(&self, hit: &Hit) -> (Color, Color) {
+        (
+            self.reflectance.color_at(hit),
+            self.transmittance.color_at(hit),
+        )
+    }
+
+    fn reflection_probability(reflectance: Color, transmittance: Color) -> f64 {
+        let total = reflectance.luminance() + transmittance.luminance();
+
+        if total > 0. {
+            reflectance.luminance() / total
+        } else {
+            0.5
+        }
+    }
+}
+
+impl<R: Texture, T: Texture> Material for TranslucentMaterial<R, T> {
+    fn eval(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> Color {
+        let (reflectance, transmittance) = self.colors(hit);
+
+        if same_hemisphere(wo, wi) {
+            reflectance.scale(std::f64::consts::FRAC_1_PI)
+        } else {
+            transmittance.scale(std::f64::consts::FRAC_1_PI)
+        }
+    }
+
+    fn sample(&self, hit: &Hit, wo: Unit3, u: [f64; 3]) -> Option<BsdfSample> {
+        let (reflectance, transmittance) = self.colors(hit);
+        let reflection = u[0] < Self::reflection_probability(reflectance, transmittance);
+        let mut wi = sampling::cosine_hemisphere((u[1], u[2]));
+
+        if (wo.z < 0.) == reflection {
+            wi.z = -wi.z;
+        }
+
+        let pdf = self.pdf(hit, wo, wi);
+
+        if pdf > 0. {
+            Some(BsdfSample {
+                wi,
+                f: self.eval(hit, wo, wi),
+                pdf,
+                flags: if reflection {
+                    BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION
+                } else {
+                    BsdfFlags::DIFFUSE | BsdfFlags::TRANSMISSION
+                },
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, hit: &Hit, wo: Unit3, wi: Unit3) -> f64 {
+        let (reflectance, transmittance) = self.colors(hit);
+        let reflection_probability = Self::reflection_probability(reflectance, transmittance);
+        let probability = if same_hemisphere(wo, wi) {
+            reflection_probability
+        } else {
+            1. - reflection_probability
+        };
+
+        probability * wi.z.abs() * std::f64::consts::FRAC_1_PI
+    }
+}
@@ -0,0 +1,357 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::material::Material;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Medium {
+    pub absorption: Color,
+    pub scattering: Color,
+    pub anisotropy: f64,
+}
+
+impl Medium {
+    // The purely absorbing medium that leaves `color` after travelling
+    // `distance` through it. Black channels are taken as nearly black, so
+    // that the absorption stays finite.
+    pub fn from_transmittance(color: Color, distance: f64) -> Self {
+        debug_assert!(distance > 0.);
+
+        let absorption = |channel: f64| -channel.clamp(1e-6, 1.).ln() / distance;
+
+        Self {
+            absorption: Color::new(
+                absorption(color.r),
+                absorption(color.g),
+                absorption(color.b),
+            ),
+            scattering: Color::new(0., 0., 0.),
+            anisotropy: 0.,
+        }
+    }
+
+    // The isotropic scattering medium whose multiple-scattering albedo is
+    // roughly `albedo`, using the inversion from Chiang, Kutz and Burley
+    // 2016, "Practical and Controllable Subsurface Scattering for Production
+    // Path Tracing".
+    pub fn from_albedo(albedo: Color, mean_free_path: Color) -> Self {
+        let single_scattering = |albedo: f64| {
+            let albedo = albedo.clamp(0., 1.);
+
+            1. - (4.09712 + 4.20863 * albedo
+                - (9.59217 + 41.6808 * albedo + 17.7126 * albedo * albedo).sqrt())
+            .powi(2)
+        };
+        let extinction = Color::new(
+            1. / mean_free_path.r,
+            1. / mean_free_path.g,
+            1. / mean_free_path.b,
+        );
+        let scattering = extinction.mul(Color::new(
+            single_scattering(albedo.r),
+            single_scattering(albedo.g),
+            single_scattering(albedo.b),
+        ));
+
+        Self {
+            absorption: extinction.add(scattering.scale(-1.)),
+            scattering,
+            anisotropy: 0.,
+        }
+    }
+
+    pub fn extinction(&self) -> Color {
+        self.absorption.add(self.scattering)
+    }
+
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let channel = |extinction: f64| {
+            if extinction == 0. {
+                1.
+            } else {
+                (-extinction * distance).exp()
+            }
+        };
+        let extinction = self.extinction();
+
+        Color::new(
+            channel(extinction.r),
+            channel(extinction.g),
+            channel(extinction.b),
+        )
+    }
+
+    // Samples the distance to the next scattering event along a ray that
+    // leaves the medium at `t_max`, choosing a colour channel uniformly to
+    // drive the sampling. Returns the distance, if the ray scatters before
+    // leaving, along with the weight to apply to the path.
+    pub fn sample_distance(&self, t_max: f64, u: (f64, f64)) -> (Option<f64>, Color) {
+        if self.scattering.is_black() {
+            return (None, self.transmittance(t_max));
+        }
+
+        let extinction = self.extinction();
+        let sigma = match (u.0 * 3.) as u32 {
+            0 => extinction.r,
+            1 => extinction.g,
+            _ => extinction.b,
+        };
+        let t = if sigma > 0. {
+            -(1. - u.1).ln() / sigma
+        } else {
+            f64::INFINITY
+        };
+
+        if t < t_max {
+            let transmittance = self.transmittance(t);
+            let pdf = (extinction.r * transmittance.r
+                + extinction.g * transmittance.g
+                + extinction.b * transmittance.b)
+                / 3.;
+
+            (Some(t), self.scattering.mul(transmittance).scale(1. / pdf))
+        } else {
+            let transmittance = self.transmittance(t_max);
+            let probability = (transmittance.r + transmittance.g + transmittance.b) / 3.;
+
+            if probability > 0. {
+                (None, transmittance.scale(1. / probability))
+            } else {
+                (None, Color::new(0., 0., 0.))
+            }
+        }
+    }
+
+    // Samples a new direction of travel from the Henyey-Greenstein phase
+    // function. The phase function is sampled exactly, so the path weight is
+    // unchanged.
+    pub fn sample_phase(&self, direction: Unit3, u: (f64, f64)) -> Unit3 {
+        let g = self.anisotropy;
+        let cos_theta = if g.abs() < 1e-3 {
+            1. - 2. * u.0
+        } else {
+            let square = (1. - g * g) / (1. - g + 2. * g * u.0);
+
+            ((1. + g * g - square * square) / (2. * g)).clamp(-1., 1.)
+        };
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * std::f64::consts::PI * u.1;
+
+        Frame::from_normal(direction).to_world(Unit3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
+
+// The materials whose interiors a path is currently inside, in the order they
+// were entered. Overlapping interiors are resolved by material priority, with
+// the most recently entered winning ties, after Schmidt and Budge 2002,
+// "Simple Nested Dielectrics in Ray Traced Images".
+#[derive(Clone, Default)]
+pub struct MediumStack<'a> {
+    materials: Vec<&'a dyn Material>,
+}
+
+impl<'a> MediumStack<'a> {
+    fn current(&self) -> Option<&'a dyn Material> {
+        self.materials
+            .iter()
+            .copied()
+            .max_by_key(|material| material.priority())
+    }
+
+    pub fn medium(&self) -> Option<Medium> {
+        self.current().and_then(|material| material.interior())
+    }
+
+    pub fn refractive_index(&self) -> f64 {
+        self.current()
+            .map_or(1., |material| material.refractive_index())
+    }
+
+    // Whether the surfaces of `material` are hidden by a higher priority
+    // interior that the path is already inside.
+    pub fn overrides(&self, material: &dyn Material) -> bool {
+        self.current()
+            .is_some_and(|current| current.priority() > material.priority())
+    }
+
+    pub fn entered(&self, material: &'a dyn Material) -> Self {
+        let mut materials = self.materials.clone();
+
+        materials.push(material);
+
+        Self { materials }
+    }
+
+    pub fn exited(&self, material: &dyn Material) -> Self {
+        let mut materials = self.materials.clone();
+
+        if let Some(index) = materials
+            .iter()
+            .rposition(|entered| std::ptr::addr_eq(*entered, material))
+        {
+            materials.remove(index);
+        }
+
+        Self { materials }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use rand_xoshiro::rand_core::SeedableRng;
+    use rand_xoshiro::Xoshiro256StarStar;
+
+    use super::*;
+    use crate::material::DielectricMaterial;
+    use crate::texture::ConstantTexture;
+
+    fn subject() -> Medium {
+        Medium {
+            absorption: Color::new(0.5, 1., 2.),
+            scattering: Color::new(2., 1., 0.5),
+            anisotropy: 0.,
+        }
+    }
+
+    #[test]
+    fn it_darkens_with_thickness() {
+        let medium = Medium::from_transmittance(Color::new(0.5, 0.8, 1.), 1.);
+
+        assert!((medium.transmittance(1.).r - 0.5).abs() < 1e-9);
+        assert!((medium.transmittance(2.).r - 0.25).abs() < 1e-9);
+        assert!((medium.transmittance(2.).b - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn it_keeps_absorption_finite_for_black() {
+        let medium = Medium::from_transmittance(Color::new(0., 1.5, 1.), 1.);
+
+        assert!(medium.absorption.r.is_finite());
+        assert!(medium.transmittance(1.).r < 1e-5);
+        assert_eq!(medium.absorption.g, 0.);
+    }
+
+    #[test]
+    fn its_distance_sampling_estimates_transmittance_without_bias() {
+        let medium = subject();
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let samples = 100_000;
+        let mut total = Color::new(0., 0., 0.);
+
+        for _ in 0..samples {
+            if let (None, weight) = medium.sample_distance(0.5, rng.gen()) {
+                total = total.add(weight);
+            }
+        }
+
+        let estimate = total.scale(1. / f64::from(samples));
+        let expected = medium.transmittance(0.5);
+
+        assert!((estimate.r - expected.r).abs() < 0.01);
+        assert!((estimate.g - expected.g).abs() < 0.01);
+        assert!((estimate.b - expected.b).abs() < 0.01);
+    }
+
+    #[test]
+    fn its_scattering_weight_is_the_single_scattering_albedo_for_grey_media() {
+        let medium = Medium {
+            absorption: Color::new(1., 1., 1.),
+            scattering: Color::new(3., 3., 3.),
+            anisotropy: 0.,
+        };
+
+        if let (Some(_), weight) = medium.sample_distance(f64::INFINITY, (0.5, 0.5)) {
+            assert!((weight.r - 0.75).abs() < 1e-9);
+        } else {
+            panic!("expected the ray to scatter");
+        }
+    }
+
+    #[test]
+    fn its_albedo_inversion_spans_the_unit_interval() {
+        let black = Medium::from_albedo(Color::new(0., 0., 0.), Color::new(1., 1., 1.));
+        let white = Medium::from_albedo(Color::new(1., 1., 1.), Color::new(1., 1., 1.));
+
+        assert!(black.scattering.r.abs() < 1e-4);
+        assert!(white.scattering.r > 0.99);
+        assert!((white.extinction().r - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn its_phase_function_scatters_forwards_when_anisotropic() {
+        let medium = Medium {
+            anisotropy: 0.8,
+            ..subject()
+        };
+        let direction = Unit3::new(0., 0., 1.);
+        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+        let mean_cosine = (0..10_000)
+            .map(|_| medium.sample_phase(direction, rng.gen()).dot(direction))
+            .sum::<f64>()
+            / 10_000.;
+
+        assert!((mean_cosine - 0.8).abs() < 0.02);
+    }
+
+    mod medium_stack {
+        use super::*;
+
+        fn dielectric(refractive_index: f64, priority: u32) -> DielectricMaterial<ConstantTexture> {
+            DielectricMaterial {
+                texture: ConstantTexture {
+                    color: Color::new(1., 1., 1.),
+                },
+                refractive_index,
+                priority,
+                thin_film: None,
+                thin_walled: false,
+            }
+        }
+
+        #[test]
+        fn it_is_air_when_empty() {
+            let stack = MediumStack::default();
+
+            assert_eq!(stack.refractive_index(), 1.);
+            assert!(stack.medium().is_none());
+        }
+
+        #[test]
+        fn it_uses_the_most_recently_entered_of_equal_priorities() {
+            let glass = dielectric(1.5, 0);
+            let water = dielectric(1.33, 0);
+            let stack = MediumStack::default().entered(&glass).entered(&water);
+
+            assert_eq!(stack.refractive_index(), 1.33);
+            assert_eq!(stack.exited(&water).refractive_index(), 1.5);
+        }
+
+        #[test]
+        fn it_uses_the_highest_priority() {
+            let glass = dielectric(1.5, 1);
+            let water = dielectric(1.33, 0);
+            let stack = MediumStack::default().entered(&glass).entered(&water);
+
+            assert_eq!(stack.refractive_index(), 1.5);
+            assert!(stack.overrides(&water));
+            assert!(!stack.exited(&glass).overrides(&water));
+        }
+
+        #[test]
+        fn it_exits_materials_out_of_order() {
+            let glass = dielectric(1.5, 0);
+            let water = dielectric(1.33, 0);
+            let stack = MediumStack::default()
+                .entered(&glass)
+                .entered(&water)
+                .exited(&glass);
+
+            assert_eq!(stack.refractive_index(), 1.33);
+            assert_eq!(stack.exited(&water).refractive_index(), 1.);
+        }
+    }
+}
@@ -0,0 +1,619 @@
+use std::marker::PhantomData;
+
+use crate::color::Color;
+
+// The widest ratio of the major to the minor axis of an elliptical footprint.
+// Narrower footprints are widened, which blurs them slightly but bounds the
+// number of texels visited.
+const MAX_ANISOTROPY: f64 = 8.;
+
+// The falloff of the Gaussian weights of the elliptical filter.
+const EWA_ALPHA: f64 = 2.;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    Bicubic,
+    // Bilinear lookups in the two levels nearest the size of the footprint.
+    Trilinear,
+    // Elliptically weighted averages over the footprint, after Heckbert 1989,
+    // "Fundamentals of Texture Mapping and Image Warping".
+    Ewa,
+}
+
+// How lookups outside the unit square find a texel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+    Mirror,
+    // Outside the image, the texture is the given colour.
+    Border(Color),
+}
+
+// The values stored in a mipmap, such as colours or scalars.
+pub trait Texel: Copy + Send + Sync {
+    fn zero() -> Self;
+    fn plus(self, other: Self) -> Self;
+    fn times(self, scalar: f64) -> Self;
+    fn non_negative(self) -> Self;
+    fn from_color(color: Color) -> Self;
+}
+
+impl Texel for Color {
+    fn zero() -> Self {
+        Color::new(0., 0., 0.)
+    }
+
+    fn plus(self, other: Self) -> Self {
+        self.add(other)
+    }
+
+    fn times(self, scalar: f64) -> Self {
+        self.scale(scalar)
+    }
+
+    fn non_negative(self) -> Self {
+        Color::new(self.r.max(0.), self.g.max(0.), self.b.max(0.))
+    }
+
+    fn from_color(color: Color) -> Self {
+        color
+    }
+}
+
+impl Texel for f64 {
+    fn zero() -> Self {
+        0.
+    }
+
+    fn plus(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn times(self, scalar: f64) -> Self {
+        self * scalar
+    }
+
+    fn non_negative(self) -> Self {
+        self.max(0.)
+    }
+
+    fn from_color(color: Color) -> Self {
+        color.luminance()
+    }
+}
+
+// Where the texels of a level are kept. `x` and `y` are always inside it.
+pub trait Source<T: Texel>: Send + Sync {
+    fn texel(&self, x: usize, y: usize) -> T;
+}
+
+// Texels held in memory, row by row.
+pub struct Dense<T: Texel> {
+    width: usize,
+    texels: Vec<T>,
+}
+
+impl<T: Texel> Source<T> for Dense<T> {
+    fn texel(&self, x: usize, y: usize) -> T {
+        self.texels[y * self.width + x]
+    }
+}
+
+pub struct Level<T: Texel, S: Source<T>> {
+    width: usize,
+    height: usize,
+    source: S,
+    wrap: Wrap,
+    texel: PhantomData<T>,
+}
+
+impl<T: Texel, S: Source<T>> Level<T, S> {
+    pub fn new(width: usize, height: usize, source: S, wrap: Wrap) -> Self {
+        Self {
+            width,
+            height,
+            source,
+            wrap,
+            texel: PhantomData,
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> T {
+        let wrap = |i: i64, size: usize| {
+            let size = size as i64;
+
+            match self.wrap {
+                Wrap::Repeat => Some(i.rem_euclid(size)),
+                Wrap::Clamp => Some(i.clamp(0, size - 1)),
+                Wrap::Mirror => {
+                    let i = i.rem_euclid(2 * size);
+
+                    Some(if i < size { i } else { 2 * size - 1 - i })
+                }
+                Wrap::Border(_) if (0..size).contains(&i) => Some(i),
+                Wrap::Border(_) => None,
+            }
+        };
+
+        match (wrap(x, self.width), wrap(y, self.height), self.wrap) {
+            (Some(x), Some(y), _) => self.source.texel(x as usize, y as usize),
+            (_, _, Wrap::Border(color)) => T::from_color(color),
+            _ => unreachable!(),
+        }
+    }
+
+    // Continuous texel coordinates, where texel centres fall on integers.
+    fn position(&self, u: f64, v: f64) -> (f64, f64) {
+        (u * self.width as f64 - 0.5, v * self.height as f64 - 0.5)
+    }
+
+    fn nearest(&self, u: f64, v: f64) -> T {
+        self.texel(
+            (u * self.width as f64).floor() as i64,
+            (v * self.height as f64).floor() as i64,
+        )
+    }
+
+    fn bilinear(&self, u: f64, v: f64) -> T {
+        let (s, t) = self.position(u, v);
+        let (x, y) = (s.floor(), t.floor());
+        let (ds, dt) = (s - x, t - y);
+        let (x, y) = (x as i64, y as i64);
+
+        self.texel(x, y)
+            .times((1. - ds) * (1. - dt))
+            .plus(self.texel(x + 1, y).times(ds * (1. - dt)))
+            .plus(self.texel(x, y + 1).times((1. - ds) * dt))
+            .plus(self.texel(x + 1, y + 1).times(ds * dt))
+    }
+
+    // Catmull-Rom interpolation over the surrounding four by four texels.
+    fn bicubic(&self, u: f64, v: f64) -> T {
+        let weights = |t: f64| {
+            let (t2, t3) = (t * t, t * t * t);
+
+            [
+                (-t3 + 2. * t2 - t) / 2.,
+                (3. * t3 - 5. * t2 + 2.) / 2.,
+                (-3. * t3 + 4. * t2 + t) / 2.,
+                (t3 - t2) / 2.,
+            ]
+        };
+        let (s, t) = self.position(u, v);
+        let (x, y) = (s.floor(), t.floor());
+        let (ws, wt) = (weights(s - x), weights(t - y));
+        let (x, y) = (x as i64, y as i64);
+        let mut sum = T::zero();
+
+        for (j, wt) in wt.iter().enumerate() {
+            for (i, ws) in ws.iter().enumerate() {
+                sum = sum.plus(
+                    self.texel(x + i as i64 - 1, y + j as i64 - 1)
+                        .times(ws * wt),
+                );
+            }
+        }
+
+        // The negative lobes of the filter can overshoot below zero at sharp
+        // edges.
+        sum.non_negative()
+    }
+
+    // A Gaussian weighted average over the ellipse with axes `major` and
+    // `minor`, given as offsets in texture coordinates.
+    fn ewa(&self, u: f64, v: f64, major: (f64, f64), minor: (f64, f64)) -> T {
+        let (s, t) = self.position(u, v);
+        let (width, height) = (self.width as f64, self.height as f64);
+        let (ds0, dt0) = (major.0 * width, major.1 * height);
+        let (ds1, dt1) = (minor.0 * width, minor.1 * height);
+
+        // The implicit equation of the ellipse, widened by a texel so that it
+        // always covers at least one.
+        let mut a = dt0 * dt0 + dt1 * dt1 + 1.;
+        let mut b = -2. * (ds0 * dt0 + ds1 * dt1);
+        let mut c = ds0 * ds0 + ds1 * ds1 + 1.;
+        let f = 1. / (a * c - b * b / 4.);
+
+        a *= f;
+        b *= f;
+        c *= f;
+
+        // The bounds of the ellipse, kept within a repeat of the level so
+        // that the loops below stay short whatever the footprint.
+        let determinant = 4. * a * c - b * b;
+        let s_extent = (2. * (determinant * c).sqrt() / determinant).min(width);
+        let t_extent = (2. * (determinant * a).sqrt() / determinant).min(height);
+        let mut sum = T::zero();
+        let mut total = 0.;
+
+        for y in (t - t_extent).ceil() as i64..=(t + t_extent).floor() as i64 {
+            let dt = y as f64 - t;
+
+            for x in (s - s_extent).ceil() as i64..=(s + s_extent).floor() as i64 {
+                let ds = x as f64 - s;
+                let r2 = a * ds * ds + b * ds * dt + c * dt * dt;
+
+                if r2 < 1. {
+                    let weight = (-EWA_ALPHA * r2).exp() - (-EWA_ALPHA).exp();
+
+                    sum = sum.plus(self.texel(x, y).times(weight));
+                    total += weight;
+                }
+            }
+        }
+
+        if total > 0. {
+            sum.times(1. / total)
+        } else {
+            self.bilinear(u, v)
+        }
+    }
+}
+
+impl<T: Texel> Level<T, Dense<T>> {
+    // The next level of the pyramid.
+    fn downsample(&self) -> Self {
+        let (width, height, texels) =
+            downsample(self.width, self.height, |x, y| self.source.texel(x, y));
+
+        Self::new(width, height, Dense { width, texels }, self.wrap)
+    }
+}
+
+// The size of the level below one of `width` by `height` texels.
+pub fn halved(width: usize, height: usize) -> (usize, usize) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+// The next level of a pyramid from one of `width` by `height` texels read
+// with `texel`, averaging each two by two block. Blocks at the end of an odd
+// row or column take in the texel that would otherwise be dropped. Reads
+// stay inside the level whatever its wrap mode, so that a border colour
+// doesn't bleed into coarser levels.
+pub fn downsample<T: Texel>(
+    width: usize,
+    height: usize,
+    texel: impl Fn(usize, usize) -> T,
+) -> (usize, usize, Vec<T>) {
+    let (next_width, next_height) = halved(width, height);
+    let block = |i: usize, size: usize, next_size: usize| {
+        let end = if i + 1 == next_size { size } else { 2 * i + 2 };
+
+        2 * i..end
+    };
+    let mut texels = Vec::with_capacity(next_width * next_height);
+
+    for y in 0..next_height {
+        let rows = block(y, height, next_height);
+
+        for x in 0..next_width {
+            let columns = block(x, width, next_width);
+            let mut sum = T::zero();
+            let mut count = 0;
+
+            for j in rows.clone() {
+                for i in columns.clone() {
+                    sum = sum.plus(texel(i, j));
+                    count += 1;
+                }
+            }
+
+            texels.push(sum.times(1. / count as f64));
+        }
+    }
+
+    (next_width, next_height, texels)
+}
+
+// A pyramid of successively halved copies of an image, for filtering lookups
+// over footprints of any size.
+pub struct MipMap<T: Texel, S: Source<T> = Dense<T>> {
+    levels: Vec<Level<T, S>>,
+    filter: Filter,
+}
+
+impl<T: Texel> MipMap<T> {
+    pub fn new(width: usize, height: usize, texels: Vec<T>, filter: Filter, wrap: Wrap) -> Self {
+        let mut levels = vec![Level::new(width, height, Dense { width, texels }, wrap)];
+
+        while let Some(level) = levels.last().filter(|level| level.width * level.height > 1) {
+            let next = level.downsample();
+
+            levels.push(next);
+        }
+
+        Self { levels, filter }
+    }
+}
+
+impl<T: Texel, S: Source<T>> MipMap<T, S> {
+    // A pyramid of levels made elsewhere, each half the size of the last.
+    pub fn from_levels(levels: Vec<Level<T, S>>, filter: Filter) -> Self {
+        Self { levels, filter }
+    }
+
+    // Looks up the image at `(u, v)`, over a footprint spanned by the offsets
+    // in texture coordinates `dx` and `dy`. Zero offsets give a point lookup.
+    pub fn lookup(&self, u: f64, v: f64, dx: (f64, f64), dy: (f64, f64)) -> T {
+        let base = &self.levels[0];
+
+        match self.filter {
+            Filter::Nearest => base.nearest(u, v),
+            Filter::Bilinear => base.bilinear(u, v),
+            Filter::Bicubic => base.bicubic(u, v),
+            Filter::Trilinear => {
+                let width = self.texel_length(dx).max(self.texel_length(dy));
+
+                self.between_levels(width.log2(), |level| level.bilinear(u, v))
+            }
+            Filter::Ewa => {
+                let (major, mut minor) = if self.texel_length(dx) >= self.texel_length(dy) {
+                    (dx, dy)
+                } else {
+                    (dy, dx)
+                };
+                let major_length = self.texel_length(major);
+                let mut minor_length = self.texel_length(minor);
+
+                if minor_length * MAX_ANISOTROPY < major_length && minor_length > 0. {
+                    let scale = major_length / (minor_length * MAX_ANISOTROPY);
+
+                    minor = (minor.0 * scale, minor.1 * scale);
+                    minor_length *= scale;
+                }
+
+                if minor_length == 0. {
+                    return base.bilinear(u, v);
+                }
+
+                // Footprints as wide as the image average all of it, which
+                // the coarsest level already holds.
+                let level = minor_length.log2();
+                let coarsest = self.levels.len() - 1;
+
+                if level >= coarsest as f64 {
+                    return self.levels[coarsest].texel(0, 0);
+                }
+
+                self.between_levels(level, |level| level.ewa(u, v, major, minor))
+            }
+        }
+    }
+
+    // The length of an offset in texture coordinates, in texels of the finest
+    // level.
+    fn texel_length(&self, offset: (f64, f64)) -> f64 {
+        let base = &self.levels[0];
+
+        (offset.0 * base.width as f64).hypot(offset.1 * base.height as f64)
+    }
+
+    // Blends `lookup` between the two levels either side of `level`, which
+    // may be fractional.
+    fn between_levels(&self, level: f64, lookup: impl Fn(&Level<T, S>) -> T) -> T {
+        let coarsest = self.levels.len() - 1;
+
+        if level.is_nan() || level <= 0. {
+            return lookup(&self.levels[0]);
+        } else if level >= coarsest as f64 {
+            return lookup(&self.levels[coarsest]);
+        }
+
+        let finer = level.floor() as usize;
+        let delta = level - level.floor();
+
+        lookup(&self.levels[finer])
+            .times(1. - delta)
+            .plus(lookup(&self.levels[finer + 1]).times(delta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64, tolerance: f64) {
+        assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+    }
+
+    // Vertical stripes, `stripe` texels wide, alternately white and black.
+    fn stripes(size: usize, stripe: usize, filter: Filter) -> MipMap<Color> {
+        let texels = (0..size * size)
+            .map(|i| {
+                let value = if (i % size / stripe).is_multiple_of(2) {
+                    1.
+                } else {
+                    0.
+                };
+
+                Color::new(value, value, value)
+            })
+            .collect();
+
+        MipMap::new(size, size, texels, filter, Wrap::Repeat)
+    }
+
+    fn ramp(filter: Filter) -> MipMap<Color> {
+        ramp_wrapped(filter, Wrap::Repeat)
+    }
+
+    // Texels increasing from 0 to 7 along x.
+    fn ramp_wrapped(filter: Filter, wrap: Wrap) -> MipMap<Color> {
+        let texels = (0..64)
+            .map(|i| Color::new(f64::from(i % 8), 0., 0.))
+            .collect();
+
+        MipMap::new(8, 8, texels, filter, wrap)
+    }
+
+    #[test]
+    fn it_halves_each_level_down_to_a_single_texel() {
+        let subject = MipMap::new(
+            8,
+            4,
+            vec![Color::new(0.5, 0.5, 0.5); 32],
+            Filter::Trilinear,
+            Wrap::Repeat,
+        );
+        let sizes: Vec<(usize, usize)> = subject
+            .levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+
+        assert_eq!(sizes, vec![(8, 4), (4, 2), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn its_coarsest_level_is_the_average() {
+        let subject = stripes(16, 2, Filter::Trilinear);
+
+        assert_close(subject.levels.last().unwrap().source.texels[0].g, 0.5, 1e-9);
+    }
+
+    #[test]
+    fn it_keeps_border_colours_out_of_coarser_levels() {
+        let subject = MipMap::new(
+            4,
+            1,
+            vec![1.; 4],
+            Filter::Trilinear,
+            Wrap::Border(Color::new(0., 0., 0.)),
+        );
+
+        for level in &subject.levels {
+            assert!(level.source.texels.iter().all(|&texel| texel == 1.));
+        }
+    }
+
+    #[test]
+    fn it_takes_in_the_last_row_and_column_of_odd_sizes() {
+        let subject = MipMap::new(
+            3,
+            3,
+            (0..9).map(f64::from).collect(),
+            Filter::Trilinear,
+            Wrap::Repeat,
+        );
+        let coarsest = subject.levels.last().unwrap();
+
+        assert_eq!((coarsest.width, coarsest.height), (1, 1));
+        assert_close(coarsest.source.texels[0], 4., 1e-9);
+    }
+
+    #[test]
+    fn it_looks_up_the_nearest_texel() {
+        let subject = ramp(Filter::Nearest);
+
+        assert_close(subject.lookup(0.3, 0.5, (0., 0.), (0., 0.)).r, 2., 1e-9);
+    }
+
+    #[test]
+    fn it_interpolates_between_texel_centres() {
+        let subject = ramp(Filter::Bilinear);
+
+        assert_close(subject.lookup(0.3, 0.5, (0., 0.), (0., 0.)).r, 1.9, 1e-9);
+    }
+
+    #[test]
+    fn it_interpolates_smoothly_with_bicubic_filtering() {
+        let subject = ramp(Filter::Bicubic);
+
+        // Catmull-Rom interpolation reproduces a linear ramp away from where
+        // it wraps.
+        assert_close(subject.lookup(0.3, 0.5, (0., 0.), (0., 0.)).r, 1.9, 1e-9);
+    }
+
+    #[test]
+    fn it_repeats_outside_the_image() {
+        let subject = ramp(Filter::Bilinear);
+
+        assert_close(subject.lookup(1.3, -0.5, (0., 0.), (0., 0.)).r, 1.9, 1e-9);
+    }
+
+    #[test]
+    fn it_clamps_to_the_edge() {
+        let subject = ramp_wrapped(Filter::Nearest, Wrap::Clamp);
+
+        assert_eq!(subject.lookup(1.3, 0.5, (0., 0.), (0., 0.)).r, 7.);
+        assert_eq!(subject.lookup(-0.3, 0.5, (0., 0.), (0., 0.)).r, 0.);
+    }
+
+    #[test]
+    fn it_mirrors_alternate_repeats() {
+        let subject = ramp_wrapped(Filter::Nearest, Wrap::Mirror);
+
+        assert_eq!(subject.lookup(1.3, 0.5, (0., 0.), (0., 0.)).r, 5.);
+        assert_eq!(subject.lookup(-0.3, 0.5, (0., 0.), (0., 0.)).r, 2.);
+        assert_eq!(subject.lookup(2.3, 0.5, (0., 0.), (0., 0.)).r, 2.);
+    }
+
+    #[test]
+    fn it_uses_the_border_colour_outside_the_image() {
+        let border = Color::new(0.1, 0.2, 0.3);
+        let subject = ramp_wrapped(Filter::Nearest, Wrap::Border(border));
+
+        assert_eq!(subject.lookup(1.3, 0.5, (0., 0.), (0., 0.)), border);
+        assert_eq!(subject.lookup(0.5, -0.1, (0., 0.), (0., 0.)), border);
+        assert_eq!(subject.lookup(0.3, 0.5, (0., 0.), (0., 0.)).r, 2.);
+    }
+
+    #[test]
+    fn it_filters_scalars() {
+        let texels = (0..64).map(|i| f64::from(i % 8)).collect();
+        let subject = MipMap::new(8, 8, texels, Filter::Bicubic, Wrap::Repeat);
+
+        assert_close(subject.lookup(0.3, 0.5, (0., 0.), (0., 0.)), 1.9, 1e-9);
+    }
+
+    #[test]
+    fn it_averages_over_large_footprints() {
+        for &filter in &[Filter::Trilinear, Filter::Ewa] {
+            let subject = stripes(64, 1, filter);
+            let color = subject.lookup(0.3, 0.3, (2., 0.), (0., 2.));
+
+            assert_close(color.g, 0.5, 1e-6);
+        }
+    }
+
+    #[test]
+    fn it_takes_the_coarsest_level_for_footprints_wider_than_the_image() {
+        let subject = stripes(8, 1, Filter::Ewa);
+        let color = subject.lookup(0.3, 0.3, (3000., 0.), (0., 3000.));
+
+        assert_close(color.g, 0.5, 1e-9);
+    }
+
+    #[test]
+    fn it_keeps_elliptical_lookups_within_the_level() {
+        let subject = stripes(8, 1, Filter::Ewa);
+
+        // Thousands of repeats of the level across, were it not cut short.
+        let color = subject.levels[0].ewa(0.3, 0.3, (3000., 0.), (0., 3000.));
+
+        assert_close(color.g, 0.5, 0.1);
+    }
+
+    #[test]
+    fn it_is_bilinear_without_a_footprint() {
+        let bilinear = ramp(Filter::Bilinear).lookup(0.3, 0.5, (0., 0.), (0., 0.));
+
+        for &filter in &[Filter::Trilinear, Filter::Ewa] {
+            assert_eq!(ramp(filter).lookup(0.3, 0.5, (0., 0.), (0., 0.)), bilinear);
+        }
+    }
+
+    #[test]
+    fn its_elliptical_filter_only_blurs_along_the_footprint() {
+        // A footprint 32 texels long along the stripes, but two across them.
+        let (dx, dy) = ((2. / 64., 0.), (0., 32. / 64.));
+        let ewa = stripes(64, 16, Filter::Ewa).lookup(8. / 64., 0.5, dx, dy);
+        let trilinear = stripes(64, 16, Filter::Trilinear).lookup(8. / 64., 0.5, dx, dy);
+
+        assert!(ewa.g > 0.95, "{}", ewa.g);
+        assert_close(trilinear.g, 0.5, 1e-9);
+    }
+}
@@ -0,0 +1,528 @@
+use crate::geometry::*;
+
+// Noise in [-1, 1] that varies over distances of about one.
+pub trait Noise: Send + Sync {
+    fn noise(&self, point: &Point3) -> f64;
+}
+
+// Noise over space and a fourth dimension such as time, which changes shape
+// smoothly as `w` moves.
+pub trait Noise4: Noise {
+    fn noise4(&self, point: &Point3, w: f64) -> f64;
+}
+
+// Hashes the lattice cell `cell` into 64 random bits. Any integer cell can be
+// hashed, so patterns neither repeat nor break down away from the origin.
+pub fn hash(seed: u64, cell: &[i64]) -> u64 {
+    cell.iter()
+        .fold(mix(seed), |hash, &coordinate| mix(hash ^ coordinate as u64))
+}
+
+// The finaliser of SplitMix64.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// A hash as a float in [0, 1).
+pub fn unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3. - 2. * t)
+}
+
+// Random values at the lattice points, interpolated smoothly between them.
+pub struct ValueNoise {
+    seed: u64,
+}
+
+impl ValueNoise {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn lattice(&self, point: &[f64]) -> f64 {
+        let cell: Vec<i64> = point.iter().map(|x| x.floor() as i64).collect();
+        let weights: Vec<f64> = point.iter().map(|x| smoothstep(x - x.floor())).collect();
+        let mut corner = vec![0; point.len()];
+        let mut accum = 0.;
+
+        for bits in 0..1 << point.len() {
+            let mut weight = 1.;
+
+            for (axis, offset) in corner.iter_mut().enumerate() {
+                let high = bits >> axis & 1 == 1;
+
+                *offset = cell[axis] + high as i64;
+                weight *= if high {
+                    weights[axis]
+                } else {
+                    1. - weights[axis]
+                };
+            }
+
+            accum += weight * (2. * unit(hash(self.seed, &corner)) - 1.);
+        }
+
+        accum
+    }
+}
+
+impl Noise for ValueNoise {
+    fn noise(&self, point: &Point3) -> f64 {
+        self.lattice(&[point.x, point.y, point.z])
+    }
+}
+
+impl Noise4 for ValueNoise {
+    fn noise4(&self, point: &Point3, w: f64) -> f64 {
+        self.lattice(&[point.x, point.y, point.z, w])
+    }
+}
+
+// Gradient noise on a simplicial lattice, after Gustavson 2005, "Simplex
+// noise demystified". It has fewer directional artefacts than Perlin noise,
+// and stays cheap in four dimensions.
+pub struct Simplex {
+    seed: u64,
+}
+
+const GRADIENTS_3: [[f64; 3]; 12] = [
+    [1., 1., 0.],
+    [-1., 1., 0.],
+    [1., -1., 0.],
+    [-1., -1., 0.],
+    [1., 0., 1.],
+    [-1., 0., 1.],
+    [1., 0., -1.],
+    [-1., 0., -1.],
+    [0., 1., 1.],
+    [0., -1., 1.],
+    [0., 1., -1.],
+    [0., -1., -1.],
+];
+
+impl Simplex {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    // One of the 32 directions to the edges of a tesseract.
+    fn gradient4(hash: u64, d: &[f64; 4]) -> f64 {
+        let zero = (hash % 4) as usize;
+        let signs = hash / 4;
+
+        (0..4)
+            .filter(|&axis| axis != zero)
+            .enumerate()
+            .map(|(bit, axis)| {
+                if signs >> bit & 1 == 1 {
+                    -d[axis]
+                } else {
+                    d[axis]
+                }
+            })
+            .sum()
+    }
+}
+
+impl Noise for Simplex {
+    fn noise(&self, point: &Point3) -> f64 {
+        let skew = 1. / 3.;
+        let unskew = 1. / 6.;
+        let point = [point.x, point.y, point.z];
+
+        let s = point.iter().sum::<f64>() * skew;
+        let cell: Vec<i64> = point.iter().map(|x| (x + s).floor() as i64).collect();
+        let t = cell.iter().sum::<i64>() as f64 * unskew;
+        let mut d = [0.; 3];
+
+        for axis in 0..3 {
+            d[axis] = point[axis] - (cell[axis] as f64 - t);
+        }
+
+        let mut rank = [0; 3];
+
+        for a in 0..3 {
+            for b in a + 1..3 {
+                if d[a] > d[b] {
+                    rank[a] += 1;
+                } else {
+                    rank[b] += 1;
+                }
+            }
+        }
+
+        let mut accum = 0.;
+
+        for corner in 0..4 {
+            let mut offset = [0; 3];
+            let mut corner_d = [0.; 3];
+
+            for axis in 0..3 {
+                offset[axis] = i64::from(rank[axis] + corner >= 3);
+                corner_d[axis] = d[axis] - offset[axis] as f64 + corner as f64 * unskew;
+            }
+
+            let falloff = 0.6 - corner_d.iter().map(|x| x * x).sum::<f64>();
+
+            if falloff > 0. {
+                let lattice: Vec<i64> = (0..3).map(|axis| cell[axis] + offset[axis]).collect();
+                let gradient = GRADIENTS_3[(hash(self.seed, &lattice) % 12) as usize];
+                let dot: f64 = gradient.iter().zip(&corner_d).map(|(g, d)| g * d).sum();
+
+                accum += falloff.powi(4) * dot;
+            }
+        }
+
+        32. * accum
+    }
+}
+
+impl Noise4 for Simplex {
+    fn noise4(&self, point: &Point3, w: f64) -> f64 {
+        let skew = (5f64.sqrt() - 1.) / 4.;
+        let unskew = (5. - 5f64.sqrt()) / 20.;
+        let point = [point.x, point.y, point.z, w];
+
+        let s = point.iter().sum::<f64>() * skew;
+        let cell: Vec<i64> = point.iter().map(|x| (x + s).floor() as i64).collect();
+        let t = cell.iter().sum::<i64>() as f64 * unskew;
+        let mut d = [0.; 4];
+
+        for axis in 0..4 {
+            d[axis] = point[axis] - (cell[axis] as f64 - t);
+        }
+
+        // The simplex containing the point is found by ranking its offsets
+        // from the cell origin, and its corners step along the axes in order.
+        let mut rank = [0; 4];
+
+        for a in 0..4 {
+            for b in a + 1..4 {
+                if d[a] > d[b] {
+                    rank[a] += 1;
+                } else {
+                    rank[b] += 1;
+                }
+            }
+        }
+
+        let mut accum = 0.;
+
+        for corner in 0..5 {
+            let mut offset = [0; 4];
+            let mut corner_d = [0.; 4];
+
+            for axis in 0..4 {
+                offset[axis] = i64::from(rank[axis] + corner >= 4);
+                corner_d[axis] = d[axis] - offset[axis] as f64 + corner as f64 * unskew;
+            }
+
+            let falloff = 0.6 - corner_d.iter().map(|x| x * x).sum::<f64>();
+
+            if falloff > 0. {
+                let lattice: Vec<i64> = (0..4).map(|axis| cell[axis] + offset[axis]).collect();
+
+                accum += falloff.powi(4) * Self::gradient4(hash(self.seed, &lattice), &corner_d);
+            }
+        }
+
+        27. * accum
+    }
+}
+
+// Cellular noise after Worley 1996, "A Cellular Texture Basis Function", with
+// one feature point scattered in each lattice cell.
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    // The distances to the nearest and second nearest feature points.
+    pub fn distances(&self, point: &Point3) -> (f64, f64) {
+        let cell = [
+            point.x.floor() as i64,
+            point.y.floor() as i64,
+            point.z.floor() as i64,
+        ];
+        let mut nearest = (f64::INFINITY, f64::INFINITY);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    let hash = hash(self.seed, &neighbour);
+                    let feature = Point3::new(
+                        neighbour[0] as f64 + unit(mix(hash)),
+                        neighbour[1] as f64 + unit(mix(hash ^ 1)),
+                        neighbour[2] as f64 + unit(mix(hash ^ 2)),
+                    );
+                    let distance = (feature - *point).length();
+
+                    if distance < nearest.0 {
+                        nearest = (distance, nearest.0);
+                    } else if distance < nearest.1 {
+                        nearest.1 = distance;
+                    }
+                }
+            }
+        }
+
+        nearest
+    }
+}
+
+// Octaves of noise summed at rising frequencies and falling amplitudes: each
+// octave is `lacunarity` times the frequency and `gain` times the amplitude of
+// the last.
+#[derive(Clone, Copy, Debug)]
+pub struct Fractal {
+    pub octaves: u32,
+    pub lacunarity: f64,
+    pub gain: f64,
+}
+
+impl Fractal {
+    // Fractional Brownian motion, normalised to stay in [-1, 1].
+    pub fn fbm(&self, noise: impl Fn(&Point3) -> f64, point: &Point3) -> f64 {
+        let mut accum = 0.;
+        let mut total = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = 1.;
+
+        for _ in 0..self.octaves {
+            accum += amplitude * noise(&(*point * frequency));
+            total += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+
+        if total > 0. {
+            accum / total
+        } else {
+            0.
+        }
+    }
+
+    // The ridged multifractal of Musgrave, "Texturing and Modeling: A
+    // Procedural Approach", in [0, 1]. Ridges form along the zeros of the
+    // noise, and each octave is weighted by the last so that detail gathers
+    // on them.
+    pub fn ridged(&self, noise: impl Fn(&Point3) -> f64, point: &Point3) -> f64 {
+        let mut accum = 0.;
+        let mut total = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = 1.;
+        let mut weight = 1.;
+
+        for _ in 0..self.octaves {
+            let ridge = 1. - noise(&(*point * frequency)).abs().min(1.);
+            let signal = ridge * ridge * weight;
+
+            weight = (2. * signal).clamp(0., 1.);
+            accum += amplitude * signal;
+            total += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+
+        if total > 0. {
+            accum / total
+        } else {
+            0.
+        }
+    }
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Self {
+            octaves: 6,
+            lacunarity: 2.,
+            gain: 0.5,
+        }
+    }
+}
+
+// Moves `point` by up to `strength` along a field of noise, so that patterns
+// looked up at the result swirl and fold.
+pub fn warp(noise: impl Fn(&Point3) -> f64, point: &Point3, strength: f64) -> Point3 {
+    // Offsets that decorrelate the three components.
+    let offsets = [
+        Vector3::new(0., 0., 0.),
+        Vector3::new(5.2, 1.3, 2.8),
+        Vector3::new(1.7, 9.2, 4.5),
+    ];
+    let displacement = Vector3::new(
+        noise(&(*point + offsets[0])),
+        noise(&(*point + offsets[1])),
+        noise(&(*point + offsets[2])),
+    );
+
+    *point + displacement * strength
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Points spread over a few lattice cells either side of the origin.
+    fn points() -> impl Iterator<Item = Point3> {
+        (0..2000).map(|i| {
+            let i = f64::from(i);
+
+            Point3::new(
+                (i * 0.618_034).fract() * 8. - 4.,
+                (i * 0.414_214).fract() * 8. - 4.,
+                (i * 0.732_051).fract() * 8. - 4.,
+            )
+        })
+    }
+
+    fn range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+        values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), x| {
+            (low.min(x), high.max(x))
+        })
+    }
+
+    fn assert_in_range(values: impl Iterator<Item = f64>, low: f64, high: f64) {
+        let (min, max) = range(values);
+
+        assert!(min >= low && max <= high, "[{}, {}]", min, max);
+        // The noise should use much of its range, and not be flat.
+        assert!(max - min > 0.5 * (high - low), "[{}, {}]", min, max);
+    }
+
+    fn assert_continuous(noise: impl Fn(&Point3) -> f64) {
+        for point in points() {
+            let step = noise(&(point + Vector3::new(1e-6, -1e-6, 1e-6))) - noise(&point);
+
+            assert!(step.abs() < 1e-4, "{} at {:?}", step, point);
+        }
+    }
+
+    #[test]
+    fn it_hashes_negative_cells_apart() {
+        assert_ne!(hash(0, &[-1, 0, 0]), hash(0, &[0, 0, 0]));
+        assert_ne!(hash(0, &[-1, 0, 0]), hash(0, &[-2, 0, 0]));
+        assert_ne!(hash(0, &[0, 0, 0]), hash(1, &[0, 0, 0]));
+        assert_eq!(hash(3, &[-7, 2, 9]), hash(3, &[-7, 2, 9]));
+    }
+
+    #[test]
+    fn value_noise_is_continuous_and_in_range() {
+        let subject = ValueNoise::new(0);
+
+        assert_in_range(points().map(|p| subject.noise(&p)), -1., 1.);
+        assert_continuous(|p| subject.noise(p));
+        assert_continuous(|p| subject.noise4(p, -2.5));
+    }
+
+    #[test]
+    fn value_noise_is_seeded() {
+        let p = Point3::new(0.3, -1.7, 2.2);
+
+        assert_eq!(ValueNoise::new(4).noise(&p), ValueNoise::new(4).noise(&p));
+        assert_ne!(ValueNoise::new(4).noise(&p), ValueNoise::new(5).noise(&p));
+    }
+
+    #[test]
+    fn simplex_noise_is_continuous_and_in_range() {
+        let subject = Simplex::new(0);
+
+        assert_in_range(points().map(|p| subject.noise(&p)), -1., 1.);
+        assert_in_range(points().map(|p| subject.noise4(&p, p.x - p.z)), -1., 1.);
+        assert_continuous(|p| subject.noise(p));
+        assert_continuous(|p| subject.noise4(p, 0.7));
+    }
+
+    #[test]
+    fn simplex_noise_changes_with_time() {
+        let subject = Simplex::new(0);
+        let p = Point3::new(0.3, 0.6, -0.2);
+
+        assert_ne!(subject.noise4(&p, 0.), subject.noise4(&p, 0.5));
+        assert_ne!(Simplex::new(1).noise(&p), subject.noise(&p));
+    }
+
+    #[test]
+    fn worley_distances_are_ordered() {
+        let subject = Worley::new(0);
+
+        for point in points() {
+            let (f1, f2) = subject.distances(&point);
+
+            assert!(f1 <= f2);
+            assert!(f1 < 3f64.sqrt());
+        }
+    }
+
+    #[test]
+    fn worley_distances_vanish_at_feature_points() {
+        let subject = Worley::new(0);
+        let hash = hash(0, &[-2, 1, 0]);
+        let feature = Point3::new(
+            -2. + unit(mix(hash)),
+            1. + unit(mix(hash ^ 1)),
+            unit(mix(hash ^ 2)),
+        );
+
+        assert!(subject.distances(&feature).0 < 1e-12);
+    }
+
+    #[test]
+    fn fbm_stays_in_range() {
+        let noise = Simplex::new(0);
+        let fractal = Fractal::default();
+
+        assert_in_range(
+            points().map(|p| fractal.fbm(|q| noise.noise(q), &p)),
+            -1.,
+            1.,
+        );
+    }
+
+    #[test]
+    fn fbm_adds_octaves_by_gain() {
+        let fractal = Fractal {
+            octaves: 3,
+            lacunarity: 2.,
+            gain: 0.5,
+        };
+        let p = Point3::new(1., 0., 0.);
+
+        // Noise equal to x sums 1 + 0.5 * 2 + 0.25 * 4 over a total of 1.75.
+        assert!((fractal.fbm(|q| q.x, &p) - 3. / 1.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ridged_noise_stays_in_range() {
+        let noise = Simplex::new(0);
+        let fractal = Fractal::default();
+
+        assert_in_range(
+            points().map(|p| fractal.ridged(|q| noise.noise(q), &p)),
+            0.,
+            1.,
+        );
+    }
+
+    #[test]
+    fn warping_moves_points_by_up_to_the_strength() {
+        let noise = Simplex::new(0);
+        let p = Point3::new(0.5, 0.25, -0.75);
+
+        assert_eq!(warp(|q| noise.noise(q), &p, 0.), p);
+        assert!((warp(|q| noise.noise(q), &p, 0.1) - p).length() <= 0.1 * 3f64.sqrt());
+    }
+}
@@ -29,10 +29,31 @@ impl<M: Material> Plane<M> {
 
         (pv.dot(self.u.into()), pv.dot(self.v.into()))
     }
+
+    fn hit_at(&self, t: f64, p: Point3) -> Hit<'_> {
+        let (u, v) = self.uv(p);
+
+        Hit {
+            t,
+            p,
+            u,
+            v,
+            normal: self.normal,
+            dpdu: self.u.into(),
+            dpdv: self.v.into(),
+            dudx: 0.,
+            dudy: 0.,
+            dvdx: 0.,
+            dvdy: 0.,
+            exterior_refractive_index: 1.,
+            material: &self.material,
+            attributes: &NO_ATTRIBUTES,
+        }
+    }
 }
 
 impl<M: Material> Hittable for Plane<M> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
         let ndotl = self.normal.dot(ray.direction);
 
         if ndotl.abs() < 1e-10 {
@@ -43,18 +64,20 @@ impl<M: Material> Hittable for Plane<M> {
             if t < t_min || t > t_max {
                 None
             } else {
-                let p = ray.at(t);
-                let uv = self.uv(p);
-
-                Some(Hit {
-                    t,
-                    p,
-                    u: uv.0,
-                    v: uv.1,
-                    normal: self.normal,
-                    material: &self.material,
-                })
+                let hit = self.hit_at(t, ray.at(t));
+
+                if hit.is_opaque(ray) {
+                    Some(hit)
+                } else {
+                    None
+                }
             }
         }
     }
 }
+
+impl<M: Material> Surface for Plane<M> {
+    fn at(&self, u: f64, v: f64) -> Option<Hit<'_>> {
+        Some(self.hit_at(0., self.point + self.u * u + self.v * v))
+    }
+}
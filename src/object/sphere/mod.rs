@@ -7,6 +7,7 @@ pub struct Sphere<M: Material> {
     center: Point3,
     radius: f64,
     material: M,
+    attributes: Attributes,
 }
 
 impl<M: Material> Sphere<M> {
@@ -15,9 +16,15 @@ impl<M: Material> Sphere<M> {
             center,
             radius,
             material,
+            attributes: Attributes::default(),
         }
     }
 
+    // The object with `attributes` for textures to read.
+    pub fn with_attributes(self, attributes: Attributes) -> Self {
+        Self { attributes, ..self }
+    }
+
     fn surface_normal(&self, point: Point3) -> Unit3 {
         (point - self.center).normalize()
     }
@@ -30,10 +37,62 @@ impl<M: Material> Sphere<M> {
 
         (1. - (phi + pi) / (2. * pi), (theta + pi / 2.) / pi)
     }
+
+    // The point with texture coordinates `u` and `v`, inverting `uv`.
+    fn point(&self, u: f64, v: f64) -> Point3 {
+        let pi = std::f64::consts::PI;
+        let phi = pi - 2. * pi * u;
+        let theta = pi * v - pi / 2.;
+
+        self.center
+            + Vector3::new(
+                theta.cos() * phi.sin(),
+                theta.sin(),
+                theta.cos() * phi.cos(),
+            ) * self.radius
+    }
+
+    fn hit_at(&self, t: f64, p: Point3) -> Hit<'_> {
+        let (u, v) = self.uv(p);
+        let (dpdu, dpdv) = self.tangents(p);
+
+        Hit {
+            t,
+            p,
+            u,
+            v,
+            normal: self.surface_normal(p),
+            dpdu,
+            dpdv,
+            dudx: 0.,
+            dudy: 0.,
+            dvdx: 0.,
+            dvdy: 0.,
+            exterior_refractive_index: 1.,
+            material: &self.material,
+            attributes: &self.attributes,
+        }
+    }
+
+    fn tangents(&self, p: Point3) -> (Vector3, Vector3) {
+        let pi = std::f64::consts::PI;
+        let local = p - self.center;
+        let cos_theta = (local.x.powi(2) + local.z.powi(2)).sqrt() / self.radius;
+        let tan_theta = local.y / (local.x.powi(2) + local.z.powi(2)).sqrt().max(1e-12);
+
+        (
+            Vector3::new(-local.z, 0., local.x) * (2. * pi),
+            Vector3::new(
+                -local.x * tan_theta,
+                self.radius * cos_theta,
+                -local.z * tan_theta,
+            ) * pi,
+        )
+    }
 }
 
 impl<M: Material> Hittable for Sphere<M> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
         let oc = ray.origin - self.center;
         let dot = ray.direction.dot(oc.normalize()) * oc.length();
 
@@ -48,18 +107,11 @@ impl<M: Material> Hittable for Sphere<M> {
 
         for t in &[-dot - sqrt, -dot + sqrt] {
             if (t_min..t_max).contains(t) {
-                let p = ray.at(*t);
-                let normal = self.surface_normal(p);
-                let uv = self.uv(p);
-
-                return Some(Hit {
-                    t: *t,
-                    p,
-                    u: uv.0,
-                    v: uv.1,
-                    normal,
-                    material: &self.material,
-                });
+                let hit = self.hit_at(*t, ray.at(*t));
+
+                if hit.is_opaque(ray) {
+                    return Some(hit);
+                }
             }
         }
 
@@ -67,6 +119,12 @@ impl<M: Material> Hittable for Sphere<M> {
     }
 }
 
+impl<M: Material> Surface for Sphere<M> {
+    fn at(&self, u: f64, v: f64) -> Option<Hit<'_>> {
+        Some(self.hit_at(0., self.point(u, v)))
+    }
+}
+
 impl<M: Material> Bounded for Sphere<M> {
     fn bounding_box(&self) -> BoundingBox {
         let offset = Vector3::new(self.radius, self.radius, self.radius);
@@ -26,9 +26,10 @@ mod hit {
         let ray = Ray {
             origin: Point3::new(-2.0, 0.0, 0.0),
             direction: Unit3::new(1.0, 0.0, 0.0),
+            differentials: None,
         };
 
-        assert_eq!(subject().hit(&ray, 0.0, std::f64::INFINITY).unwrap().t, 1.0);
+        assert_eq!(subject().hit(&ray, 0.0, f64::INFINITY).unwrap().t, 1.0);
     }
 
     #[test]
@@ -36,9 +37,10 @@ mod hit {
         let ray = Ray {
             origin: Point3::new(2.0, 0.0, 0.0),
             direction: Unit3::new(-1.0, 0.0, 0.0),
+            differentials: None,
         };
 
-        assert_eq!(subject().hit(&ray, 0.0, std::f64::INFINITY).unwrap().t, 1.0);
+        assert_eq!(subject().hit(&ray, 0.0, f64::INFINITY).unwrap().t, 1.0);
     }
 
     #[test]
@@ -46,9 +48,10 @@ mod hit {
         let ray = Ray {
             origin: Point3::new(0.0, 2.0, 0.0),
             direction: Unit3::new(0.0, -1.0, 0.0),
+            differentials: None,
         };
 
-        assert_eq!(subject().hit(&ray, 0.0, std::f64::INFINITY).unwrap().t, 1.0);
+        assert_eq!(subject().hit(&ray, 0.0, f64::INFINITY).unwrap().t, 1.0);
     }
 
     #[test]
@@ -56,13 +59,12 @@ mod hit {
         let ray = Ray {
             origin: Point3::new(2.0, 2.0, 0.0),
             direction: Unit3::new(-1.0, -1.0, 0.0),
+            differentials: None,
         };
 
-        if let Some(hit) = subject().hit(&ray, 0.0, std::f64::INFINITY) {
-            assert!((hit.t - 1.828).abs() <= 0.01);
-        } else {
-            assert!(false);
-        }
+        let t = subject().hit(&ray, 0.0, f64::INFINITY).unwrap().t;
+
+        assert!((t - 1.828).abs() <= 0.01);
     }
 
     #[test]
@@ -70,8 +72,177 @@ mod hit {
         let ray = Ray {
             origin: Point3::new(2.0, 0.0, 0.0),
             direction: Unit3::new(1.0, 0.0, 0.0),
+            differentials: None,
+        };
+
+        assert!(subject().hit(&ray, 0.0, f64::INFINITY).is_none());
+    }
+}
+
+mod tangents {
+    use super::*;
+
+    fn hit_from(origin: Point3) -> (Vector3, Vector3, Unit3) {
+        let subject = Subject::new(
+            Point3::new(1.0, 2.0, 3.0),
+            2.0,
+            LambertianMaterial {
+                texture: ConstantTexture {
+                    color: Color::new(0.0, 0.0, 0.0),
+                },
+            },
+        );
+        let ray = Ray {
+            origin,
+            direction: (Point3::new(1.0, 2.0, 3.0) - origin).normalize(),
+            differentials: None,
+        };
+        let hit = subject.hit(&ray, 0.0, f64::INFINITY).unwrap();
+
+        (hit.dpdu, hit.dpdv, hit.normal)
+    }
+
+    #[test]
+    fn they_lie_in_the_tangent_plane() {
+        let (dpdu, dpdv, normal) = hit_from(Point3::new(4.0, 3.0, 5.0));
+
+        assert!(Vector3::from(normal).dot(dpdu).abs() < 1e-9);
+        assert!(Vector3::from(normal).dot(dpdv).abs() < 1e-9);
+    }
+
+    #[test]
+    fn they_span_the_surface_around_the_normal() {
+        let (dpdu, dpdv, normal) = hit_from(Point3::new(-3.0, 0.0, 1.0));
+        let cross = (dpdu * dpdv).normalize();
+
+        assert!(cross.dot(normal).abs() > 1.0 - 1e-9);
+    }
+
+    #[test]
+    fn they_match_the_rate_of_change_of_the_uv_mapping() {
+        let subject = Subject::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            LambertianMaterial {
+                texture: ConstantTexture {
+                    color: Color::new(0.0, 0.0, 0.0),
+                },
+            },
+        );
+        let p = Point3::new(0.6, 0.48, 0.64);
+        let (dpdu, dpdv) = subject.tangents(p);
+        let (u, v) = subject.uv(p);
+        let (u_du, v_du) = subject.uv(p + dpdu * 1e-6);
+        let (u_dv, v_dv) = subject.uv(p + dpdv * 1e-6);
+
+        assert!(((u_du - u) / 1e-6 - 1.0).abs() < 1e-3);
+        assert!(((v_du - v) / 1e-6).abs() < 1e-3);
+        assert!(((u_dv - u) / 1e-6).abs() < 1e-3);
+        assert!(((v_dv - v) / 1e-6 - 1.0).abs() < 1e-3);
+    }
+}
+
+mod alpha {
+    use super::*;
+
+    use crate::material::AlphaMaterial;
+    use crate::texture::Texture;
+
+    struct FrontMask {}
+
+    impl Texture for FrontMask {
+        fn color(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+            if p.x < 0.0 {
+                Color::new(0.0, 0.0, 0.0)
+            } else {
+                Color::new(1.0, 1.0, 1.0)
+            }
+        }
+    }
+
+    fn subject<T: Texture>(
+        alpha: T,
+    ) -> Sphere<AlphaMaterial<LambertianMaterial<ConstantTexture>, T>> {
+        Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            AlphaMaterial {
+                material: LambertianMaterial {
+                    texture: ConstantTexture {
+                        color: Color::new(0.0, 0.0, 0.0),
+                    },
+                },
+                alpha,
+            },
+        )
+    }
+
+    #[test]
+    fn it_skips_masked_hits_for_the_next_intersection() {
+        let ray = Ray {
+            origin: Point3::new(-2.0, 0.0, 0.0),
+            direction: Unit3::new(1.0, 0.0, 0.0),
+            differentials: None,
         };
 
-        assert!(subject().hit(&ray, 0.0, std::f64::INFINITY).is_none());
+        assert_eq!(
+            subject(FrontMask {})
+                .hit(&ray, 0.0, f64::INFINITY)
+                .unwrap()
+                .t,
+            3.0
+        );
+    }
+
+    #[test]
+    fn it_is_missed_when_fully_transparent() {
+        let ray = Ray {
+            origin: Point3::new(-2.0, 0.0, 0.0),
+            direction: Unit3::new(1.0, 0.0, 0.0),
+            differentials: None,
+        };
+
+        assert!(subject(ConstantTexture::scalar(0.0))
+            .hit(&ray, 0.0, f64::INFINITY)
+            .is_none());
+    }
+
+    #[test]
+    fn it_is_hit_in_proportion_to_partial_alpha() {
+        let subject = subject(ConstantTexture::scalar(0.3));
+        let rays = 10_000;
+        let hits = (0..rays)
+            .filter(|i| {
+                let offset = f64::from(*i) / f64::from(rays) - 0.5;
+                let ray = Ray {
+                    origin: Point3::new(-2.0, offset, 0.0),
+                    direction: Unit3::new(1.0, 0.0, 0.0),
+                    differentials: None,
+                };
+
+                subject.hit(&ray, 0.0, f64::INFINITY).is_some()
+            })
+            .count();
+        let expected = 1.0 - 0.7 * 0.7;
+
+        assert!((hits as f64 / f64::from(rays) - expected).abs() < 0.02);
+    }
+
+    #[test]
+    fn it_makes_the_same_decision_for_the_same_ray() {
+        let subject = subject(ConstantTexture::scalar(0.5));
+        let ray = Ray {
+            origin: Point3::new(-2.0, 0.1, 0.2),
+            direction: Unit3::new(1.0, 0.0, 0.0),
+            differentials: None,
+        };
+        let first = subject.hit(&ray, 0.0, f64::INFINITY).map(|hit| hit.t);
+
+        for _ in 0..10 {
+            assert_eq!(
+                subject.hit(&ray, 0.0, f64::INFINITY).map(|hit| hit.t),
+                first
+            );
+        }
     }
 }
@@ -4,6 +4,7 @@ use rand_xoshiro::rand_core::SeedableRng;
 use rand_xoshiro::Xoshiro256StarStar;
 
 use crate::geometry::*;
+use crate::noise::Noise;
 
 pub struct Perlin {
     rands: [Vector3; 256],
@@ -13,8 +14,8 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new() -> Self {
-        let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+    pub fn with_seed(seed: u64) -> Self {
+        let mut rng = Xoshiro256StarStar::seed_from_u64(seed);
         let mut rands: [Vector3; 256] = [Vector3::new(0., 0., 0.); 256];
 
         for elem in rands.iter_mut() {
@@ -55,18 +56,20 @@ impl Perlin {
         let v = point.y - point.y.floor();
         let w = point.z - point.z.floor();
 
-        let i = point.x.floor() as usize;
-        let j = point.y.floor() as usize;
-        let k = point.z.floor() as usize;
+        // Signed, so that the lattice carries on past the origin. Masking the
+        // two's complement wraps negative cells into the tables.
+        let i = point.x.floor() as i64;
+        let j = point.y.floor() as i64;
+        let k = point.z.floor() as i64;
 
         let mut weight_vectors: [[[Vector3; 2]; 2]; 2] = [[[Vector3::new(0., 0., 0.); 2]; 2]; 2];
 
         for (di, item_i) in weight_vectors.iter_mut().enumerate().take(2) {
             for (dj, item_j) in item_i.iter_mut().enumerate().take(2) {
                 for (dk, item_k) in item_j.iter_mut().enumerate().take(2) {
-                    let index = self.perm_x[(i + di) & 255]
-                        ^ self.perm_y[(j + dj) & 255]
-                        ^ self.perm_z[(k + dk) & 255];
+                    let index = self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize];
 
                     *item_k = self.rands[index];
                 }
@@ -117,3 +120,129 @@ impl Perlin {
         accum
     }
 }
+
+impl Noise for Perlin {
+    fn noise(&self, point: &Point3) -> f64 {
+        Perlin::noise(self, point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Points spread over the cells either side of the origin.
+    fn points(low: f64, high: f64) -> impl Iterator<Item = Point3> {
+        (0..4000).map(move |i| {
+            let i = f64::from(i);
+            let spread = |fraction: f64| low + fraction.fract() * (high - low);
+
+            Point3::new(
+                spread(i * 0.618_034),
+                spread(i * 0.414_214),
+                spread(i * 0.732_051),
+            )
+        })
+    }
+
+    fn mean_and_deviation(values: &[f64]) -> (f64, f64) {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+
+        (mean, variance.sqrt())
+    }
+
+    #[test]
+    fn it_is_continuous_across_the_origin() {
+        let subject = Perlin::with_seed(0);
+
+        for i in -200..200 {
+            let t = f64::from(i) * 0.01;
+            let points = [
+                (Point3::new(-1e-9, t, 0.3), Point3::new(1e-9, t, 0.3)),
+                (Point3::new(t, -1e-9, 0.6), Point3::new(t, 1e-9, 0.6)),
+                (Point3::new(0.2, t, -1e-9), Point3::new(0.2, t, 1e-9)),
+            ];
+
+            for (below, above) in points.iter() {
+                let step = subject.noise(below) - subject.noise(above);
+
+                assert!(step.abs() < 1e-6, "{} at {:?}", step, below);
+            }
+        }
+    }
+
+    #[test]
+    fn it_is_continuous_across_negative_cell_boundaries() {
+        let subject = Perlin::with_seed(0);
+
+        for cell in -5..0 {
+            let x = f64::from(cell);
+            let step = subject.noise(&Point3::new(x - 1e-9, 0.37, -0.81))
+                - subject.noise(&Point3::new(x + 1e-9, 0.37, -0.81));
+
+            assert!(step.abs() < 1e-6, "{} at x = {}", step, x);
+        }
+    }
+
+    #[test]
+    fn it_varies_at_negative_coordinates() {
+        let subject = Perlin::with_seed(0);
+        let noise: Vec<f64> = points(-8., 0.).map(|p| subject.noise(&p)).collect();
+        let (_, deviation) = mean_and_deviation(&noise);
+
+        assert!(deviation > 0.1, "{}", deviation);
+    }
+
+    #[test]
+    fn it_has_the_same_statistics_either_side_of_the_origin() {
+        let subject = Perlin::with_seed(0);
+        let negative: Vec<f64> = points(-8., 0.).map(|p| subject.noise(&p)).collect();
+        let positive: Vec<f64> = points(0., 8.).map(|p| subject.noise(&p)).collect();
+        let (negative_mean, negative_deviation) = mean_and_deviation(&negative);
+        let (positive_mean, positive_deviation) = mean_and_deviation(&positive);
+
+        assert!(negative_mean.abs() < 0.05, "{}", negative_mean);
+        assert!(positive_mean.abs() < 0.05, "{}", positive_mean);
+        assert!(
+            (negative_deviation - positive_deviation).abs() < 0.05,
+            "{} != {}",
+            negative_deviation,
+            positive_deviation
+        );
+    }
+
+    #[test]
+    fn it_keeps_noise_in_range() {
+        let subject = Perlin::with_seed(0);
+
+        for p in points(-8., 8.) {
+            let noise = subject.noise(&p);
+
+            assert!((-1. ..=1.).contains(&noise), "{} at {:?}", noise, p);
+        }
+    }
+
+    #[test]
+    fn it_keeps_turbulence_in_range() {
+        let subject = Perlin::with_seed(0);
+        let turbulence: Vec<f64> = points(-8., 8.).map(|p| subject.turbulence(&p, 7)).collect();
+        let (mean, deviation) = mean_and_deviation(&turbulence);
+
+        // The octaves' amplitudes sum to less than two.
+        assert!(turbulence.iter().all(|t| (0. ..2.).contains(t)));
+        assert!(mean > 0.05 && deviation > 0.05, "{} {}", mean, deviation);
+    }
+
+    #[test]
+    fn it_keeps_the_pattern_at_positive_coordinates() {
+        let subject = Perlin::with_seed(0);
+        let p = Point3::new(1.25, 2.5, 3.75);
+
+        // The lattice is unchanged for non-negative cells, so existing scenes
+        // look the same.
+        assert_eq!(subject.noise(&p), Perlin::with_seed(0).noise(&p));
+        assert!(subject.noise(&p) != 0.);
+    }
+}
@@ -1,17 +1,50 @@
 use crate::geometry::*;
 
+// The origins and directions of rays offset by one pixel in x and in y on
+// the film, which track the footprint of a camera ray for texture filtering.
+#[derive(Debug, Copy, Clone)]
+pub struct Differentials {
+    pub x_origin: Point3,
+    pub x_direction: Unit3,
+    pub y_origin: Point3,
+    pub y_direction: Unit3,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Unit3,
+    pub differentials: Option<Differentials>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Unit3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            differentials: None,
+        }
     }
 
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + self.direction * t
     }
+
+    // Shrinks the footprint by `scale`, such as for the finer spacing between
+    // many samples per pixel.
+    pub fn scale_differentials(&mut self, scale: f64) {
+        let (origin, direction) = (self.origin, Vector3::from(self.direction));
+        let scale_origin = |offset: Point3| origin + (offset - origin) * scale;
+        let scale_direction =
+            |offset: Unit3| (direction + (Vector3::from(offset) - direction) * scale).normalize();
+
+        if let Some(differentials) = self.differentials.as_mut() {
+            *differentials = Differentials {
+                x_origin: scale_origin(differentials.x_origin),
+                x_direction: scale_direction(differentials.x_direction),
+                y_origin: scale_origin(differentials.y_origin),
+                y_direction: scale_direction(differentials.y_direction),
+            };
+        }
+    }
 }
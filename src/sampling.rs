@@ -0,0 +1,183 @@
+use crate::geometry::*;
+
+pub fn concentric_disc(u: (f64, f64)) -> (f64, f64) {
+    let ox = 2. * u.0 - 1.;
+    let oy = 2. * u.1 - 1.;
+
+    if ox == 0. && oy == 0. {
+        return (0., 0.);
+    }
+
+    let quarter_pi = std::f64::consts::FRAC_PI_4;
+    let (r, theta) = if ox.abs() > oy.abs() {
+        (ox, quarter_pi * (oy / ox))
+    } else {
+        (oy, 2. * quarter_pi - quarter_pi * (ox / oy))
+    };
+
+    (r * theta.cos(), r * theta.sin())
+}
+
+pub fn cosine_hemisphere(u: (f64, f64)) -> Unit3 {
+    let (x, y) = concentric_disc(u);
+    let z = (1. - x * x - y * y).max(0.).sqrt();
+
+    Unit3::new(x, y, z)
+}
+
+pub fn uniform_sphere(u: (f64, f64)) -> Unit3 {
+    let z = 1. - 2. * u.0;
+    let r = (1. - z * z).max(0.).sqrt();
+    let phi = 2. * std::f64::consts::PI * u.1;
+
+    Unit3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_ball(u: [f64; 3]) -> Vector3 {
+    Vector3::from(uniform_sphere((u[1], u[2]))) * u[0].cbrt()
+}
+
+// A piecewise-constant distribution over [0, 1), proportional to `func`,
+// sampled by inverting its cumulative distribution.
+pub struct PiecewiseConstant1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl PiecewiseConstant1D {
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len() as f64;
+        let mut cdf = vec![0.; func.len() + 1];
+
+        for i in 0..func.len() {
+            cdf[i + 1] = cdf[i] + func[i].max(0.) / n;
+        }
+
+        let integral = cdf[func.len()];
+
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0. {
+                *c / integral
+            } else {
+                i as f64 / n
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    // Returns the sampled point and the index of the segment containing it.
+    pub fn sample(&self, u: f64) -> (f64, usize) {
+        let index = (self.cdf.partition_point(|&c| c <= u) - 1).min(self.func.len() - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0. {
+            (u - self.cdf[index]) / width
+        } else {
+            0.
+        };
+
+        (
+            ((index as f64 + offset) / self.func.len() as f64).min(1. - f64::EPSILON),
+            index,
+        )
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        let index = ((x * self.func.len() as f64) as usize).min(self.func.len() - 1);
+
+        if self.integral > 0. {
+            self.func[index].max(0.) / self.integral
+        } else {
+            1.
+        }
+    }
+}
+
+// A piecewise-constant distribution over [0, 1)², given row by row, sampled
+// by choosing a row from the marginal distribution and then a column within
+// it.
+pub struct PiecewiseConstant2D {
+    conditionals: Vec<PiecewiseConstant1D>,
+    marginal: PiecewiseConstant1D,
+}
+
+impl PiecewiseConstant2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        let conditionals: Vec<PiecewiseConstant1D> = func
+            .chunks_exact(width)
+            .take(height)
+            .map(|row| PiecewiseConstant1D::new(row.to_vec()))
+            .collect();
+        let marginal = PiecewiseConstant1D::new(
+            conditionals
+                .iter()
+                .map(|conditional| conditional.integral())
+                .collect(),
+        );
+
+        Self {
+            conditionals,
+            marginal,
+        }
+    }
+
+    pub fn sample(&self, u: (f64, f64)) -> (f64, f64) {
+        let (y, row) = self.marginal.sample(u.1);
+        let (x, _) = self.conditionals[row].sample(u.0);
+
+        (x, y)
+    }
+
+    pub fn pdf(&self, point: (f64, f64)) -> f64 {
+        let row =
+            ((point.1 * self.conditionals.len() as f64) as usize).min(self.conditionals.len() - 1);
+
+        self.marginal.pdf(point.1) * self.conditionals[row].pdf(point.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_piecewise_constant_distribution_samples_in_proportion() {
+        let distribution = PiecewiseConstant1D::new(vec![1., 0., 3.]);
+        let (x, index) = distribution.sample(0.5);
+
+        assert_eq!(index, 2);
+        assert!(x >= 2. / 3.);
+        assert!((distribution.pdf(x) - 2.25).abs() < 1e-9);
+        assert_eq!(distribution.pdf(0.5), 0.);
+        assert_eq!(distribution.sample(0.1).1, 0);
+    }
+
+    #[test]
+    fn a_2d_piecewise_constant_distribution_integrates_to_one() {
+        let distribution = PiecewiseConstant2D::new(&[1., 2., 0., 3., 4., 5.], 3, 2);
+        let n = 60;
+        let mut total = 0.;
+
+        for i in 0..n {
+            for j in 0..n {
+                total +=
+                    distribution.pdf(((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64));
+            }
+        }
+
+        assert!((total / (n * n) as f64 - 1.).abs() < 1e-9);
+
+        let (x, y) = distribution.sample((0.5, 0.5));
+
+        assert!(distribution.pdf((x, y)) > 0.);
+    }
+}
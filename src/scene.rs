@@ -1,23 +1,37 @@
-use image::*;
 use rand::prelude::*;
 use rand_xoshiro::rand_core::SeedableRng;
 use rand_xoshiro::Xoshiro256StarStar;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::bvh::*;
 use crate::color::*;
 use crate::geometry::*;
 use crate::hittable::*;
+use crate::library::Library;
 use crate::material::*;
+use crate::medium::*;
 use crate::object::plane::*;
 use crate::object::sphere::*;
+use crate::ray::Ray;
 use crate::texture::*;
 
 pub struct Scene {
     pub objects: Vec<Box<dyn Hittable>>,
+    // Replaces the material of every object, such as to render everything in
+    // clay for a lighting review.
+    pub override_material: Option<Arc<dyn Material>>,
+    // Objects that can be picked out by name, such as for baking.
+    pub surfaces: HashMap<String, Arc<dyn Surface>>,
 }
 
 impl Scene {
-    pub fn new(earth: DynamicImage, moon: DynamicImage) -> Self {
+    // With `transparent`, the scene is prepared for compositing over a
+    // background plate: the floor catches shadows, and the earth is held out
+    // to stand in for one in the plate. Its own materials are added to
+    // `library`, which should provide `earth`, `moon` and
+    // `moon_height` textures.
+    pub fn new(library: &mut Library, transparent: bool) -> Self {
         let glass_sphere = Sphere::new(
             Point3::new(-1.0, 0.8, 5.0),
             0.8,
@@ -26,65 +40,143 @@ impl Scene {
                     color: Color::new(1., 1., 1.),
                 },
                 refractive_index: 1.3,
+                priority: 0,
+                thin_film: None,
+                thin_walled: false,
             },
         );
-        let small_glass_sphere = Sphere::new(
-            Point3::new(1.2, 1.5, 3.0),
-            0.4,
+        let air_bubble = Sphere::new(
+            Point3::new(-0.8, 1.0, 4.8),
+            0.25,
             DielectricMaterial {
                 texture: ConstantTexture {
                     color: Color::new(1., 1., 1.),
                 },
-                refractive_index: 1.3,
+                refractive_index: 1.0,
+                priority: 1,
+                thin_film: None,
+                thin_walled: false,
             },
         );
-        let fuzzy_green_sphere = Sphere::new(
-            Point3::new(1.0, 0.8, 5.0),
-            0.8,
-            FuzzyReflectiveMaterial {
+        let soap_bubble = Sphere::new(
+            Point3::new(-1.1, 2.2, 5.2),
+            0.45,
+            DielectricMaterial {
                 texture: ConstantTexture {
-                    color: Color::new(0.2, 1., 0.4),
+                    color: Color::new(1., 1., 1.),
                 },
-                fuzz: 0.1,
+                refractive_index: 1.0,
+                priority: 0,
+                thin_film: Some(ThinFilm {
+                    thickness: Box::new(ConstantTexture::scalar(450.)),
+                    refractive_index: 1.33,
+                }),
+                thin_walled: false,
             },
         );
-        let blue_sphere = Sphere::new(
-            Point3::new(2.5, 0.8, 5.0),
-            0.8,
-            LambertianMaterial {
-                texture: CheckerboardTexture {
-                    odd: ConstantTexture {
-                        color: Color::new(0.9, 0.9, 0.9),
-                    },
-                    even: ConstantTexture {
-                        color: Color::new(0.2, 0.4, 1.),
+        let small_glass_sphere = Sphere::new(
+            Point3::new(1.2, 1.5, 3.0),
+            0.4,
+            RoughDielectricMaterial {
+                roughness: ConstantTexture {
+                    color: Color::new(0.2, 0.2, 0.2),
+                },
+                refractive_index: 1.5,
+                interior: Some(Medium::from_transmittance(Color::new(0.6, 0.8, 0.95), 0.8)),
+                priority: 0,
+                thin_film: None,
+                thin_walled: false,
+            },
+        );
+        let green_paint = library.add_material(
+            "green_paint",
+            CoatedMaterial {
+                base: MixMaterial {
+                    first: LambertianMaterial {
+                        texture: ConstantTexture {
+                            color: Color::new(0.1, 0.6, 0.2),
+                        },
                     },
-                    width: 0.05,
+                    second: ConductorMaterial::aluminium(0.4),
+                    weight: NoiseTexture::new(40.),
                 },
+                roughness: ConstantTexture::scalar(0.),
+                refractive_index: 1.5,
+                thickness: 0.,
+                absorption: None,
             },
         );
-        let blue_dot = Sphere::new(
-            Point3::new(3.5, 1.8, 7.0),
+        let green_paint_sphere =
+            Arc::new(Sphere::new(Point3::new(1.0, 0.8, 5.0), 0.8, green_paint));
+        let blue_sphere = Arc::new(Sphere::new(
+            Point3::new(2.5, 0.8, 5.0),
             0.8,
-            LambertianMaterial {
-                texture: ImageTexture::new(earth),
+            PrincipledMaterial {
+                roughness: Box::new(ConstantTexture::scalar(0.6)),
+                clearcoat: Box::new(ConstantTexture::scalar(1.)),
+                ..PrincipledMaterial::new(TransformedTexture {
+                    texture: CheckerboardTexture {
+                        odd: ConstantTexture {
+                            color: Color::new(0.9, 0.9, 0.9),
+                        },
+                        even: ConstantTexture {
+                            color: Color::new(0.2, 0.4, 1.),
+                        },
+                        width: 0.05,
+                    },
+                    transform: UvTransform {
+                        scale: (1., 2.),
+                        rotation: 45.,
+                        offset: (0., 0.),
+                    },
+                })
             },
-        );
-        let moon = Sphere::new(
+        ));
+        let blue_dot: Box<dyn BoundedHittable> = if transparent {
+            Box::new(Sphere::new(
+                Point3::new(3.5, 1.8, 7.0),
+                0.8,
+                HoldoutMaterial,
+            ))
+        } else {
+            Box::new(Sphere::new(
+                Point3::new(3.5, 1.8, 7.0),
+                0.8,
+                LambertianMaterial {
+                    texture: library.texture("earth").unwrap(),
+                },
+            ))
+        };
+        let moon = Arc::new(Sphere::new(
             Point3::new(4.5, 2.3, 6.0),
             0.2,
-            LambertianMaterial {
-                texture: ImageTexture::new(moon),
+            BumpMaterial {
+                material: OrenNayarMaterial {
+                    texture: library.texture("moon").unwrap(),
+                    sigma: 0.5,
+                },
+                bump: library.texture("moon_height").unwrap(),
+                scale: 0.005,
             },
-        );
-        let marble_sphere = Sphere::new(
-            Point3::new(0.5, 2.5, 6.2),
-            0.5,
-            LambertianMaterial {
-                texture: MarbleTexture::new(10.),
+        ));
+        let marble_texture = library.texture("marble").unwrap();
+        let marble = library.add_material(
+            "marble",
+            MixMaterial {
+                first: LambertianMaterial {
+                    texture: marble_texture.clone(),
+                },
+                second: SubsurfaceMaterial::new(
+                    Color::new(0.9, 0.88, 0.85),
+                    Color::new(0.08, 0.06, 0.05),
+                    ConstantTexture::scalar(0.1),
+                    1.5,
+                ),
+                weight: marble_texture,
             },
         );
-        let yellow_sphere = Sphere::new(
+        let marble_sphere = Arc::new(Sphere::new(Point3::new(0.5, 2.5, 6.2), 0.5, marble));
+        let yellow_sphere = Arc::new(Sphere::new(
             Point3::new(1.75, 2.5, 6.2),
             0.5,
             ReflectiveMaterial {
@@ -92,75 +184,145 @@ impl Scene {
                     color: Color::new(0.85, 0.85, 0.3),
                 },
             },
-        );
-        let checkerboard = Plane::new(
-            Point3::new(0.0, 0.0, 0.0),
-            Unit3::new(0.0, 0.0, 1.0),
-            Unit3::new(1.0, 0.0, 0.0),
-            LambertianMaterial {
-                texture: CheckerboardTexture {
-                    odd: ConstantTexture {
-                        color: Color::new(0.05, 0.05, 0.2),
+        ));
+        let cage_sphere = Arc::new(Sphere::new(
+            Point3::new(-2.5, 0.5, 7.0),
+            0.5,
+            AlphaMaterial {
+                material: TranslucentMaterial {
+                    reflectance: ConstantTexture {
+                        color: Color::new(0.6, 0.2, 0.1),
                     },
-                    even: ConstantTexture {
-                        color: Color::new(0.8, 0.8, 0.8),
+                    transmittance: ConstantTexture {
+                        color: Color::new(0.3, 0.1, 0.05),
                     },
-                    width: 1.,
+                },
+                alpha: CheckerboardTexture {
+                    odd: ConstantTexture::scalar(0.),
+                    even: ConstantTexture::scalar(1.),
+                    width: 0.05,
                 },
             },
-        );
+        ));
+        let floor: Arc<dyn Surface> = if transparent {
+            Arc::new(Plane::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Unit3::new(0.0, 0.0, 1.0),
+                Unit3::new(1.0, 0.0, 0.0),
+                ShadowCatcherMaterial {
+                    material: LambertianMaterial {
+                        texture: ConstantTexture::scalar(0.8),
+                    },
+                },
+            ))
+        } else {
+            Arc::new(Plane::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Unit3::new(0.0, 0.0, 1.0),
+                Unit3::new(1.0, 0.0, 0.0),
+                LambertianMaterial {
+                    texture: CheckerboardTexture {
+                        odd: ConstantTexture {
+                            color: Color::new(0.05, 0.05, 0.2),
+                        },
+                        even: ConstantTexture {
+                            color: Color::new(0.8, 0.8, 0.8),
+                        },
+                        width: 1.,
+                    },
+                },
+            ))
+        };
 
         let mut boundeds: Vec<Box<dyn BoundedHittable>> = vec![
             Box::new(glass_sphere),
+            Box::new(air_bubble),
+            Box::new(soap_bubble),
             Box::new(small_glass_sphere),
-            Box::new(fuzzy_green_sphere),
-            Box::new(blue_sphere),
-            Box::new(blue_dot),
-            Box::new(moon),
-            Box::new(marble_sphere),
-            Box::new(yellow_sphere),
+            Box::new(green_paint_sphere.clone()),
+            Box::new(blue_sphere.clone()),
+            blue_dot,
+            Box::new(moon.clone()),
+            Box::new(marble_sphere.clone()),
+            Box::new(yellow_sphere.clone()),
+            Box::new(cage_sphere.clone()),
+        ];
+        let named: [(&str, Arc<dyn Surface>); 7] = [
+            ("green_paint", green_paint_sphere),
+            ("checker", blue_sphere),
+            ("moon", moon),
+            ("marble", marble_sphere),
+            ("yellow", yellow_sphere),
+            ("cage", cage_sphere),
+            ("floor", floor.clone()),
+        ];
+
+        // The small spheres share a material of each kind, coloured by the
+        // colour attribute of each sphere.
+        let color = || AttributeTexture::new("color", Color::new(0.5, 0.5, 0.5));
+        let scattered_materials: [Arc<dyn Material>; 3] = [
+            Arc::new(LambertianMaterial { texture: color() }),
+            Arc::new(FuzzyReflectiveMaterial {
+                texture: color(),
+                fuzz: 0.1,
+            }),
+            Arc::new(DielectricMaterial {
+                texture: color(),
+                refractive_index: 1.3,
+                priority: 0,
+                thin_film: None,
+                thin_walled: false,
+            }),
         ];
 
         let mut rng = Xoshiro256StarStar::seed_from_u64(0);
 
-        for _ in 1..100 {
+        for id in 1..100 {
             let color_coords: [f64; 3] = rng.gen();
-            let texture = ConstantTexture {
-                color: Color::from(color_coords),
-            };
             let position = Point3::new(rng.gen_range(-5.0..5.), 0.1, rng.gen_range(2.0..10.));
             let radius = 0.1;
+            let material = scattered_materials[rng.gen_range(0u32..3) as usize].clone();
+            let attributes = Attributes {
+                id,
+                colors: vec![("color".to_string(), Color::from(color_coords))],
+                ..Attributes::default()
+            };
 
-            match rng.gen_range(0u32..3) {
-                0 => boundeds.push(Box::new(Sphere::new(
-                    position,
-                    radius,
-                    LambertianMaterial { texture },
-                ))),
-                1 => boundeds.push(Box::new(Sphere::new(
-                    position,
-                    radius,
-                    FuzzyReflectiveMaterial { texture, fuzz: 0.1 },
-                ))),
-                2 => boundeds.push(Box::new(Sphere::new(
-                    position,
-                    radius,
-                    DielectricMaterial {
-                        texture,
-                        refractive_index: 1.3,
-                    },
-                ))),
-                _ => boundeds.push(Box::new(Sphere::new(
-                    position,
-                    radius,
-                    ReflectiveMaterial { texture },
-                ))),
+            boundeds.push(Box::new(
+                Sphere::new(position, radius, material).with_attributes(attributes),
+            ));
+        }
+
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Bvh::new(boundeds)), Box::new(floor)];
+
+        Self {
+            objects,
+            override_material: None,
+            surfaces: named
+                .iter()
+                .map(|(name, surface)| (name.to_string(), surface.clone()))
+                .collect(),
+        }
+    }
+
+    // The nearest object along `ray` between `t_min` and `t_max`.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let mut result: Option<Hit> = None;
+
+        for o in &self.objects {
+            if let Some(hit) = o.hit(ray, t_min, result.as_ref().map_or(t_max, |hit| hit.t)) {
+                result = Some(hit);
             }
         }
 
-        let objects: Vec<Box<dyn Hittable>> =
-            vec![Box::new(Bvh::new(boundeds)), Box::new(checkerboard)];
+        result
+    }
+
+    // The names of the objects in `surfaces`, in order.
+    pub fn surface_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.surfaces.keys().map(String::as_str).collect();
 
-        Self { objects }
+        names.sort_unstable();
+        names
     }
 }
@@ -0,0 +1,74 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::noise::{hash, unit};
+use crate::texture::Texture;
+
+// Textures that read the attributes of the object that was hit, so that one
+// material can vary between the objects sharing it. Looked up away from an
+// object, they give their defaults.
+
+// A random colour for each object id, such as to tell apart scattered
+// objects. Different seeds give different colours for the same ids.
+pub struct ObjectRandomTexture {
+    pub seed: u64,
+}
+
+impl ObjectRandomTexture {
+    fn random(&self, id: u64) -> Color {
+        let channel = |channel| unit(hash(self.seed, &[id as i64, channel]));
+
+        Color::new(channel(0), channel(1), channel(2))
+    }
+}
+
+impl Texture for ObjectRandomTexture {
+    fn color(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.random(0)
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.random(hit.attributes.id)
+    }
+}
+
+// The float or colour attribute `name` of the object, with floats as greys,
+// or `default` where the object doesn't have it.
+pub struct AttributeTexture {
+    pub name: String,
+    pub default: Color,
+}
+
+impl AttributeTexture {
+    pub fn new(name: &str, default: Color) -> Self {
+        Self {
+            name: name.to_string(),
+            default,
+        }
+    }
+}
+
+impl Texture for AttributeTexture {
+    fn color(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.default
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        let attributes = hit.attributes;
+
+        attributes
+            .color(&self.name)
+            .or_else(|| {
+                attributes
+                    .float(&self.name)
+                    .map(|value| Color::new(value, value, value))
+            })
+            .unwrap_or(self.default)
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        hit.attributes
+            .float(&self.name)
+            .unwrap_or_else(|| self.color_at(hit).luminance())
+    }
+}
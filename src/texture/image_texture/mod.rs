@@ -0,0 +1,404 @@
+use image::codecs::hdr::HdrDecoder;
+use image::io::Reader;
+use image::{
+    DynamicImage, GenericImageView, ImageError, ImageFormat, ImageResult, Primitive, Rgb, Rgba,
+};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::mipmap::{Filter, MipMap, Texel, Wrap};
+use crate::texture::Texture;
+
+// How the values stored in an image relate to those the renderer works in.
+// Photographs and painted colours are usually sRGB, renders and HDR images
+// are linear, and data such as normal and roughness maps is used as is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+    Raw,
+}
+
+impl ColorSpace {
+    // Converts a stored value to linear, leaving alpha alone.
+    pub fn decode(self, value: f64) -> f64 {
+        match self {
+            ColorSpace::Srgb if value <= 0.04045 => value / 12.92,
+            ColorSpace::Srgb => ((value + 0.055) / 1.055).powf(2.4),
+            ColorSpace::Linear | ColorSpace::Raw => value,
+        }
+    }
+
+    // Converts a linear value to how it is stored, inverting `decode`.
+    pub fn encode(self, value: f64) -> f64 {
+        match self {
+            ColorSpace::Srgb if value <= 0.0031308 => value * 12.92,
+            ColorSpace::Srgb => 1.055 * value.powf(1. / 2.4) - 0.055,
+            ColorSpace::Linear | ColorSpace::Raw => value,
+        }
+    }
+}
+
+// The channels of an image as floats, bottom row first so that v runs up the
+// image. Integer formats are scaled to [0, 1] whatever their bit depth.
+pub struct Pixels {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<[f64; 4]>,
+}
+
+impl Pixels {
+    fn from_image(image: &DynamicImage) -> Self {
+        let image = image.flipv();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+
+        // Each depth is read as is, since the image crate widens 8-bit values
+        // to 16 bits by shifting rather than scaling them. Float images never
+        // get here: `open` reads them itself rather than let them be clamped.
+        let rgba = match image {
+            DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => normalized(image.to_rgba16().pixels(), u16::MAX),
+            DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_)
+            | DynamicImage::ImageBgr8(_)
+            | DynamicImage::ImageBgra8(_) => normalized(image.to_rgba8().pixels(), u8::MAX),
+        };
+
+        Self {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    // Reads Radiance HDR files as floats, and anything else the image crate
+    // supports, such as 8 and 16-bit PNG and TIFF, through `from_image`.
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let path = path.as_ref();
+
+        if !is_hdr(path)? {
+            return Ok(Self::from_image(&open_image(path)?));
+        }
+
+        let (width, height, pixels) = read_hdr(path)?;
+        let rgba = pixels
+            .iter()
+            .map(|pixel| {
+                [
+                    f64::from(pixel[0]),
+                    f64::from(pixel[1]),
+                    f64::from(pixel[2]),
+                    1.,
+                ]
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            rgba,
+        })
+    }
+
+    // The colours of the image decoded from `color_space`.
+    pub fn colors(&self, color_space: ColorSpace) -> Vec<Color> {
+        self.rgba
+            .iter()
+            .map(|rgba| {
+                Color::new(
+                    color_space.decode(rgba[0]),
+                    color_space.decode(rgba[1]),
+                    color_space.decode(rgba[2]),
+                )
+            })
+            .collect()
+    }
+}
+
+// Whether `path` holds a Radiance HDR file, judged by its contents before its
+// extension. The image crate tone maps any HDR file it decodes to 8 bits, so
+// these must always go through `read_hdr`.
+pub fn is_hdr(path: &Path) -> ImageResult<bool> {
+    Ok(guess_format(path)?.format() == Some(ImageFormat::Hdr))
+}
+
+// The image at `path`, in whichever format its contents are in.
+pub fn open_image(path: &Path) -> ImageResult<DynamicImage> {
+    guess_format(path)?.decode()
+}
+
+fn guess_format(path: &Path) -> io::Result<Reader<BufReader<File>>> {
+    Reader::open(path)?.with_guessed_format()
+}
+
+// The pixels of a Radiance HDR file, bottom row first.
+pub fn read_hdr(path: &Path) -> ImageResult<(usize, usize, Vec<Rgb<f32>>)> {
+    let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+    let (width, height) = {
+        let metadata = decoder.metadata();
+        (metadata.width as usize, metadata.height as usize)
+    };
+    let mut pixels = decoder.read_image_hdr()?;
+
+    // HDR files store their top row first. Reversing the whole image and
+    // then each row flips it in place.
+    pixels.reverse();
+
+    for row in pixels.chunks_mut(width.max(1)) {
+        row.reverse();
+    }
+
+    Ok((width, height, pixels))
+}
+
+fn normalized<'a, T>(pixels: impl Iterator<Item = &'a Rgba<T>>, max: T) -> Vec<[f64; 4]>
+where
+    T: Primitive + Into<f64> + 'a,
+{
+    let scale = 1. / max.into();
+
+    pixels
+        .map(|pixel| {
+            [
+                pixel[0].into() * scale,
+                pixel[1].into() * scale,
+                pixel[2].into() * scale,
+                pixel[3].into() * scale,
+            ]
+        })
+        .collect()
+}
+
+// The token in a path that stands for the tile numbers of a UDIM set.
+pub const UDIM_TOKEN: &str = "<UDIM>";
+
+// A single image, or a UDIM set of images laid side by side in texture space.
+// Tile 1001 covers the unit square, numbers rise by one for each unit along
+// u, up to ten, and by ten for each unit along v.
+enum Images<T: Texel> {
+    Single(MipMap<T>),
+    Udim(HashMap<u32, MipMap<T>>),
+}
+
+impl<T: Texel> Images<T> {
+    // Opens the image at `path`, or every tile of a UDIM set if it contains
+    // `UDIM_TOKEN`. Tiles are clamped at their edges, since the texture
+    // carries on into the next tile rather than wrapping.
+    fn open(
+        path: &Path,
+        wrap: Wrap,
+        load: impl Fn(Pixels, Wrap) -> MipMap<T>,
+    ) -> ImageResult<Self> {
+        if !path.to_string_lossy().contains(UDIM_TOKEN) {
+            return Ok(Images::Single(load(Pixels::open(path)?, wrap)));
+        }
+
+        let mut tiles = HashMap::new();
+
+        for (number, path) in udim_tiles(path)? {
+            tiles.insert(number, load(Pixels::open(path)?, Wrap::Clamp));
+        }
+
+        Ok(Images::Udim(tiles))
+    }
+
+    // Lookups outside the tiles of a UDIM set are zero.
+    fn lookup(&self, u: f64, v: f64, dx: (f64, f64), dy: (f64, f64)) -> T {
+        match self {
+            Images::Single(mipmap) => mipmap.lookup(u, v, dx, dy),
+            Images::Udim(tiles) => {
+                let (s, t) = (u.floor(), v.floor());
+
+                // Tile numbers have four digits, so stop at 9999.
+                if !(0. ..10.).contains(&s) || !(0. ..900.).contains(&t) {
+                    return T::zero();
+                }
+
+                match tiles.get(&(1001 + s as u32 + 10 * t as u32)) {
+                    Some(mipmap) => mipmap.lookup(u - s, v - t, dx, dy),
+                    None => T::zero(),
+                }
+            }
+        }
+    }
+}
+
+// The tile numbers and paths of the files matching the UDIM `pattern`.
+fn udim_tiles(pattern: &Path) -> ImageResult<Vec<(u32, PathBuf)>> {
+    let name = pattern.file_name().map(|name| name.to_string_lossy());
+    let (prefix, suffix) = match name.as_ref().and_then(|name| name.split_once(UDIM_TOKEN)) {
+        Some(affixes) => affixes,
+        None => {
+            return Err(udim_error(
+                "the UDIM token must be in the file name",
+                pattern,
+            ))
+        }
+    };
+    let directory = match pattern.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tiles = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let number = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(prefix))
+            .and_then(|name| name.strip_suffix(suffix))
+            .filter(|digits| digits.len() == 4 && digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .filter(|&number| number >= 1001);
+
+        if let Some(number) = number {
+            tiles.push((number, entry.path()));
+        }
+    }
+
+    if tiles.is_empty() {
+        return Err(udim_error("no UDIM tiles match", pattern));
+    }
+
+    Ok(tiles)
+}
+
+fn udim_error(message: &str, pattern: &Path) -> ImageError {
+    ImageError::IoError(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} '{}'", message, pattern.display()),
+    ))
+}
+
+pub struct ImageTexture {
+    images: Images<Color>,
+}
+
+impl ImageTexture {
+    // Decodes `image` from `color_space`, looks it up with `filter`, and finds
+    // texels outside it by `wrap`.
+    pub fn new(image: &DynamicImage, color_space: ColorSpace, filter: Filter, wrap: Wrap) -> Self {
+        let pixels = Pixels::from_image(image);
+
+        Self {
+            images: Images::Single(Self::mipmap(pixels, color_space, filter, wrap)),
+        }
+    }
+
+    // Opens an image, or a UDIM set if `path` contains `UDIM_TOKEN`.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        color_space: ColorSpace,
+        filter: Filter,
+        wrap: Wrap,
+    ) -> ImageResult<Self> {
+        Ok(Self {
+            images: Images::open(path.as_ref(), wrap, |pixels, wrap| {
+                Self::mipmap(pixels, color_space, filter, wrap)
+            })?,
+        })
+    }
+
+    fn mipmap(
+        pixels: Pixels,
+        color_space: ColorSpace,
+        filter: Filter,
+        wrap: Wrap,
+    ) -> MipMap<Color> {
+        let texels = pixels.colors(color_space);
+
+        MipMap::new(pixels.width, pixels.height, texels, filter, wrap)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn color(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        self.images.lookup(u, v, (0., 0.), (0., 0.))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.images
+            .lookup(hit.u, hit.v, (hit.dudx, hit.dvdx), (hit.dudy, hit.dvdy))
+    }
+}
+
+// One channel of an image, for roughness, bump and other scalar maps. The
+// channels are red, green, blue and alpha in that order, so maps packed into
+// the channels of one image can share it.
+pub struct ScalarImageTexture {
+    images: Images<f64>,
+}
+
+impl ScalarImageTexture {
+    // Opens an image, or a UDIM set if `path` contains `UDIM_TOKEN`.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        channel: usize,
+        color_space: ColorSpace,
+        filter: Filter,
+        wrap: Wrap,
+    ) -> ImageResult<Self> {
+        Ok(Self {
+            images: Images::open(path.as_ref(), wrap, |pixels, wrap| {
+                Self::mipmap(pixels, channel, color_space, filter, wrap)
+            })?,
+        })
+    }
+
+    fn mipmap(
+        pixels: Pixels,
+        channel: usize,
+        color_space: ColorSpace,
+        filter: Filter,
+        wrap: Wrap,
+    ) -> MipMap<f64> {
+        // Alpha is always linear.
+        let decode = |value| {
+            if channel == 3 {
+                value
+            } else {
+                color_space.decode(value)
+            }
+        };
+        let texels = pixels
+            .rgba
+            .iter()
+            .map(|rgba| decode(rgba[channel]))
+            .collect();
+
+        MipMap::new(pixels.width, pixels.height, texels, filter, wrap)
+    }
+}
+
+impl Texture for ScalarImageTexture {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let value = self.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> f64 {
+        self.images.lookup(u, v, (0., 0.), (0., 0.))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        let value = self.value_at(hit);
+
+        Color::new(value, value, value)
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        self.images
+            .lookup(hit.u, hit.v, (hit.dudx, hit.dvdx), (hit.dudy, hit.dvdy))
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::perlin::Perlin;
+
+pub mod attribute;
+pub mod image_texture;
+pub mod node;
+pub mod procedural;
+pub mod projection;
+pub mod tiled;
+pub mod transform;
+
+pub use attribute::*;
+pub use image_texture::*;
+pub use node::*;
+pub use procedural::*;
+pub use projection::*;
+pub use tiled::TiledTexture;
+pub use transform::*;
+
+pub trait Texture: Send + Sync {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    fn value(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        self.color(u, v, p).luminance()
+    }
+
+    // The texture over the footprint of a pixel at `hit`. Textures that can
+    // be filtered override this, and the rest are point sampled.
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.color(hit.u, hit.v, &hit.p)
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        self.color_at(hit).luminance()
+    }
+}
+
+// `hit` with its texture coordinates and their derivatives replaced, for
+// textures that look others up elsewhere.
+fn remapped<'a>(
+    hit: &Hit<'a>,
+    (u, v): (f64, f64),
+    (dudx, dvdx): (f64, f64),
+    (dudy, dvdy): (f64, f64),
+) -> Hit<'a> {
+    Hit {
+        u,
+        v,
+        dudx,
+        dudy,
+        dvdx,
+        dvdy,
+        ..*hit
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        (**self).color(u, v, p)
+    }
+
+    fn value(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        (**self).value(u, v, p)
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        (**self).color_at(hit)
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        (**self).value_at(hit)
+    }
+}
+
+pub struct ConstantTexture {
+    pub color: Color,
+}
+
+impl ConstantTexture {
+    pub fn scalar(value: f64) -> Self {
+        Self {
+            color: Color::new(value, value, value),
+        }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn color(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.color
+    }
+}
+
+pub struct CheckerboardTexture<T1: Texture, T2: Texture> {
+    pub odd: T1,
+    pub even: T2,
+    pub width: f64,
+}
+
+impl<T1: Texture, T2: Texture> CheckerboardTexture<T1, T2> {
+    fn is_odd(&self, u: f64, v: f64) -> bool {
+        let pitch = std::f64::consts::PI / self.width;
+
+        (pitch * u).sin() * (pitch * v).sin() < 0.
+    }
+}
+
+impl<T1: Texture, T2: Texture> Texture for CheckerboardTexture<T1, T2> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        if self.is_odd(u, v) {
+            self.odd.color(u, v, p)
+        } else {
+            self.even.color(u, v, p)
+        }
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        if self.is_odd(hit.u, hit.v) {
+            self.odd.color_at(hit)
+        } else {
+            self.even.color_at(hit)
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub struct UVTexture {}
+
+impl Texture for UVTexture {
+    fn color(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        Color::new(u, v, 0.5)
+    }
+}
+
+pub struct NoiseTexture {
+    perlin: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> Self {
+        Self::with_seed(scale, 0)
+    }
+
+    // Textures with different seeds have different patterns.
+    pub fn with_seed(scale: f64, seed: u64) -> Self {
+        Self {
+            perlin: Perlin::with_seed(seed),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn color(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let scaled_p = *p * self.scale;
+        let noise = self.perlin.noise(&scaled_p);
+
+        Color::new(1., 1., 1.).scale(0.5 * (1. + noise))
+    }
+}
+
+pub struct MarbleTexture {
+    perlin: Perlin,
+    scale: f64,
+}
+
+impl MarbleTexture {
+    pub fn new(scale: f64) -> Self {
+        Self::with_seed(scale, 0)
+    }
+
+    // Textures with different seeds have different patterns.
+    pub fn with_seed(scale: f64, seed: u64) -> Self {
+        Self {
+            perlin: Perlin::with_seed(seed),
+            scale,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn color(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let scaled_p = *p * self.scale;
+        let noise = self.perlin.turbulence(&scaled_p, 7);
+        let gray_scale = 0.5 * (1. + (self.scale * p.z.sin() + 10. * noise).sin());
+
+        Color::new(1., 1., 1.).scale(gray_scale)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,223 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::texture::Texture;
+
+// Textures that combine or adjust others, so that patterns can be built up
+// from the leaf textures without new code. Each looks its inputs up with the
+// same footprint, so filtered inputs stay filtered.
+
+// Blends from `first` to `second` by the value of `factor`.
+pub struct MixTexture<A: Texture, B: Texture, F: Texture> {
+    pub first: A,
+    pub second: B,
+    pub factor: F,
+}
+
+fn mix(first: Color, second: Color, factor: f64) -> Color {
+    first.scale(1. - factor).add(second.scale(factor))
+}
+
+impl<A: Texture, B: Texture, F: Texture> Texture for MixTexture<A, B, F> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        mix(
+            self.first.color(u, v, p),
+            self.second.color(u, v, p),
+            self.factor.value(u, v, p),
+        )
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        mix(
+            self.first.color_at(hit),
+            self.second.color_at(hit),
+            self.factor.value_at(hit),
+        )
+    }
+}
+
+pub struct MultiplyTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: Texture, B: Texture> Texture for MultiplyTexture<A, B> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.first.color(u, v, p).mul(self.second.color(u, v, p))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.first.color_at(hit).mul(self.second.color_at(hit))
+    }
+}
+
+pub struct AddTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: Texture, B: Texture> Texture for AddTexture<A, B> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.first.color(u, v, p).add(self.second.color(u, v, p))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.first.color_at(hit).add(self.second.color_at(hit))
+    }
+}
+
+// One minus each channel, for textures in [0, 1].
+pub struct InvertTexture<T: Texture> {
+    pub texture: T,
+}
+
+fn invert(color: Color) -> Color {
+    Color::new(1. - color.r, 1. - color.g, 1. - color.b)
+}
+
+impl<T: Texture> Texture for InvertTexture<T> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        invert(self.texture.color(u, v, p))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        invert(self.texture.color_at(hit))
+    }
+}
+
+// The value of `texture` as a grey, so that scalar textures such as noise can
+// be used where a colour is expected.
+pub struct GreyTexture<T: Texture> {
+    pub texture: T,
+}
+
+impl<T: Texture> Texture for GreyTexture<T> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let value = self.texture.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        let value = self.texture.value_at(hit);
+
+        Color::new(value, value, value)
+    }
+}
+
+// Colours at increasing positions, interpolated linearly between them and
+// held beyond the first and last.
+pub struct ColorRamp {
+    pub stops: Vec<(f64, Color)>,
+}
+
+impl ColorRamp {
+    // A ramp from `from` at `low` to `to` at `high`, which remaps a range of
+    // values when used with greys.
+    pub fn linear(low: f64, from: Color, high: f64, to: Color) -> Self {
+        Self {
+            stops: vec![(low, from), (high, to)],
+        }
+    }
+
+    pub fn at(&self, position: f64) -> Color {
+        let next = self.stops.iter().position(|&(stop, _)| stop > position);
+
+        match next {
+            None => self.stops.last().map_or(Color::new(0., 0., 0.), |s| s.1),
+            Some(0) => self.stops[0].1,
+            Some(i) => {
+                let (low, from) = self.stops[i - 1];
+                let (high, to) = self.stops[i];
+
+                mix(from, to, (position - low) / (high - low))
+            }
+        }
+    }
+}
+
+// Maps the value of `texture` through `ramp`.
+pub struct RampTexture<T: Texture> {
+    pub texture: T,
+    pub ramp: ColorRamp,
+}
+
+impl<T: Texture> Texture for RampTexture<T> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.ramp.at(self.texture.value(u, v, p))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.ramp.at(self.texture.value_at(hit))
+    }
+}
+
+// Shifts the hue of `texture` by `hue` degrees and scales its saturation and
+// value.
+pub struct HsvTexture<T: Texture> {
+    pub texture: T,
+    pub hue: f64,
+    pub saturation: f64,
+    pub value: f64,
+}
+
+impl<T: Texture> HsvTexture<T> {
+    fn adjust(&self, color: Color) -> Color {
+        let (hue, saturation, value) = color.hsv();
+
+        Color::from_hsv(
+            hue + self.hue,
+            (saturation * self.saturation).min(1.),
+            value * self.value,
+        )
+    }
+}
+
+impl<T: Texture> Texture for HsvTexture<T> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.adjust(self.texture.color(u, v, p))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.adjust(self.texture.color_at(hit))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Gradient {
+    // Along the texture coordinates.
+    U,
+    V,
+    // From `from` to `to` in world space.
+    Linear { from: Point3, to: Point3 },
+    // Outwards from `centre` to `radius`.
+    Spherical { centre: Point3, radius: f64 },
+}
+
+// A grey rising from zero to one along `gradient`, and clamped beyond it.
+pub struct GradientTexture {
+    pub gradient: Gradient,
+}
+
+impl Texture for GradientTexture {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let value = self.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn value(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        let position = match self.gradient {
+            Gradient::U => u,
+            Gradient::V => v,
+            Gradient::Linear { from, to } => {
+                let axis = to - from;
+
+                (*p - from).dot(axis) / axis.length_squared()
+            }
+            Gradient::Spherical { centre, radius } => (*p - centre).length() / radius,
+        };
+
+        position.clamp(0., 1.)
+    }
+}
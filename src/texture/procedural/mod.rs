@@ -0,0 +1,119 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::noise::{warp, Fractal, Noise, Noise4, Simplex, ValueNoise, Worley};
+use crate::texture::{ColorRamp, Texture};
+
+// Growth rings around the y axis, with the grain running along it. The rings
+// wander with noise, and darken towards the late wood at their outer edge.
+pub struct WoodTexture {
+    noise: Simplex,
+    fibres: ValueNoise,
+    pub rings: f64,
+    pub light: Color,
+    pub dark: Color,
+}
+
+impl WoodTexture {
+    // `rings` is the number of rings per unit.
+    pub fn new(seed: u64, rings: f64, light: Color, dark: Color) -> Self {
+        Self {
+            noise: Simplex::new(seed),
+            fibres: ValueNoise::new(seed),
+            rings,
+            light,
+            dark,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn color(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        // Stretched along the grain, so that the rings wander slowly along it.
+        let grain = Point3::new(p.x * 2., p.y * 0.2, p.z * 2.);
+        let wander = Fractal {
+            octaves: 3,
+            ..Fractal::default()
+        }
+        .fbm(|q| self.noise.noise(q), &grain);
+        let radius = (p.x * p.x + p.z * p.z).sqrt() + 0.1 * wander;
+        let late = (radius * self.rings).rem_euclid(1.).powi(3);
+        // Fine streaks along the fibres.
+        let fibres = 1. + 0.1 * self.fibres.noise(&Point3::new(p.x * 60., p.y, p.z * 60.));
+
+        self.light
+            .scale(1. - late)
+            .add(self.dark.scale(late))
+            .scale(fibres)
+    }
+}
+
+// Interlocking crystals with flecks between them, coloured by `ramp` from the
+// clear middle of a crystal at zero to the boundaries and flecks at one.
+pub struct GraniteTexture {
+    crystals: Worley,
+    flecks: Simplex,
+    pub scale: f64,
+    pub ramp: ColorRamp,
+}
+
+impl GraniteTexture {
+    pub fn new(seed: u64, scale: f64, ramp: ColorRamp) -> Self {
+        Self {
+            crystals: Worley::new(seed),
+            flecks: Simplex::new(seed.wrapping_add(1)),
+            scale,
+            ramp,
+        }
+    }
+}
+
+impl Texture for GraniteTexture {
+    fn color(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let scaled_p = *p * self.scale;
+        let (nearest, second) = self.crystals.distances(&scaled_p);
+        let boundary = 1. - ((second - nearest) * 4.).min(1.);
+        let flecks = Fractal::default().ridged(|q| self.flecks.noise(q), &(scaled_p * 3.));
+
+        self.ramp.at(boundary.max(flecks * flecks))
+    }
+}
+
+// Billowing cloud density in [0, 1], which drifts and changes shape as `time`
+// passes. About `coverage` of the sky is cloudy.
+pub struct CloudsTexture {
+    noise: Simplex,
+    pub scale: f64,
+    pub time: f64,
+    pub coverage: f64,
+}
+
+// How quickly clouds change, in noise units per second.
+const CLOUD_SPEED: f64 = 0.05;
+
+impl CloudsTexture {
+    pub fn new(seed: u64, scale: f64, time: f64) -> Self {
+        Self {
+            noise: Simplex::new(seed),
+            scale,
+            time,
+            coverage: 0.5,
+        }
+    }
+}
+
+impl Texture for CloudsTexture {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let value = self.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> f64 {
+        let w = self.time * CLOUD_SPEED;
+        let noise = |q: &Point3| self.noise.noise4(q, w);
+        let warped = warp(noise, &(*p * self.scale), 0.5);
+        let density = 0.5 + 0.5 * Fractal::default().fbm(noise, &warped);
+
+        ((density - (1. - self.coverage)) / self.coverage.max(1e-6)).clamp(0., 1.)
+    }
+}
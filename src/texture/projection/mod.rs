@@ -0,0 +1,178 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::texture::{remapped, Texture};
+
+// Ways of finding texture coordinates from positions, in the frame of a
+// `ProjectionTexture`.
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    // Along z onto the x-y plane, with a unit of texture per unit of
+    // distance.
+    Planar,
+    // Around z, with u running once around it and v along it.
+    Cylindrical,
+    // Around the origin, with u the longitude about z and v the latitude.
+    Spherical,
+    // Planar projections along each axis, blended by how squarely the
+    // surface faces along it. Higher `sharpness` narrows the blends.
+    Triplanar { sharpness: f64 },
+}
+
+// Looks up `texture` at coordinates projected from the hit point, for
+// objects without their own texture coordinates, or to line textures up
+// across several objects.
+pub struct ProjectionTexture<T: Texture> {
+    pub texture: T,
+    pub projection: Projection,
+    pub origin: Point3,
+    pub frame: Frame,
+}
+
+impl<T: Texture> ProjectionTexture<T> {
+    // Projects in world space, about the origin.
+    pub fn new(texture: T, projection: Projection) -> Self {
+        Self {
+            texture,
+            projection,
+            origin: Point3::new(0., 0., 0.),
+            frame: Frame {
+                s: Unit3::new(1., 0., 0.),
+                t: Unit3::new(0., 1., 0.),
+                n: Unit3::new(0., 0., 1.),
+            },
+        }
+    }
+
+    fn local(&self, p: Point3) -> Vector3 {
+        let offset = p - self.origin;
+
+        Vector3::new(
+            offset.dot(self.frame.s.into()),
+            offset.dot(self.frame.t.into()),
+            offset.dot(self.frame.n.into()),
+        )
+    }
+
+    // The coordinates of `p` on each of the planes of a projection, with
+    // their weights.
+    fn planes(&self, p: Point3, normal: Vector3) -> Vec<((f64, f64), f64)> {
+        let local = self.local(p);
+
+        match self.projection {
+            Projection::Planar => vec![((local.x, local.y), 1.)],
+            Projection::Cylindrical => vec![((longitude(local), local.z), 1.)],
+            Projection::Spherical => {
+                let latitude = 1. - (local.z / local.length()).clamp(-1., 1.).acos() / PI;
+
+                vec![((longitude(local), latitude), 1.)]
+            }
+            Projection::Triplanar { sharpness } => {
+                let weights = [
+                    normal.x.abs().powf(sharpness),
+                    normal.y.abs().powf(sharpness),
+                    normal.z.abs().powf(sharpness),
+                ];
+                let total: f64 = weights.iter().sum();
+
+                vec![
+                    ((local.y, local.z), weights[0] / total),
+                    ((local.z, local.x), weights[1] / total),
+                    ((local.x, local.y), weights[2] / total),
+                ]
+            }
+        }
+    }
+
+    // The projected coordinates of `p` and their weights, for lookups
+    // without a surface. Triplanar projections then blend by the direction
+    // from the origin, which matches the normal of a sphere about it.
+    fn points(&self, p: Point3) -> Vec<((f64, f64), f64)> {
+        self.planes(p, self.local(p))
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.)
+            .collect()
+    }
+
+    // `hit` remapped onto each plane of the projection, with its weight,
+    // carrying the footprint of the pixel across with it.
+    fn hits<'a>(&self, hit: &Hit<'a>) -> Vec<(Hit<'a>, f64)> {
+        let normal = self.local(self.origin + Vector3::from(hit.normal));
+        let dpdx = hit.dpdu * hit.dudx + hit.dpdv * hit.dvdx;
+        let dpdy = hit.dpdu * hit.dudy + hit.dpdv * hit.dvdy;
+        let wraps = matches!(
+            self.projection,
+            Projection::Cylindrical | Projection::Spherical
+        );
+        let planes = self.planes(hit.p, normal);
+        let x_planes = self.planes(hit.p + dpdx, normal);
+        let y_planes = self.planes(hit.p + dpdy, normal);
+
+        planes
+            .iter()
+            .zip(x_planes.iter().zip(y_planes.iter()))
+            .filter(|((_, weight), _)| *weight > 0.)
+            .map(|(&(uv, weight), (&(x_uv, _), &(y_uv, _)))| {
+                let hit = remapped(
+                    hit,
+                    uv,
+                    difference(x_uv, uv, wraps),
+                    difference(y_uv, uv, wraps),
+                );
+
+                (hit, weight)
+            })
+            .collect()
+    }
+}
+
+fn longitude(local: Vector3) -> f64 {
+    local.y.atan2(local.x) / (2. * PI) + 0.5
+}
+
+// The difference between two projected coordinates, taking the shorter way
+// around where u wraps around an axis.
+fn difference(a: (f64, f64), b: (f64, f64), wraps: bool) -> (f64, f64) {
+    let du = a.0 - b.0;
+
+    if wraps {
+        (du - du.round(), a.1 - b.1)
+    } else {
+        (du, a.1 - b.1)
+    }
+}
+
+impl<T: Texture> Texture for ProjectionTexture<T> {
+    fn color(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        self.points(*p)
+            .into_iter()
+            .fold(Color::new(0., 0., 0.), |color, ((u, v), weight)| {
+                color.add(self.texture.color(u, v, p).scale(weight))
+            })
+    }
+
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> f64 {
+        self.points(*p)
+            .into_iter()
+            .map(|((u, v), weight)| self.texture.value(u, v, p) * weight)
+            .sum()
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.hits(hit)
+            .into_iter()
+            .fold(Color::new(0., 0., 0.), |color, (hit, weight)| {
+                color.add(self.texture.color_at(&hit).scale(weight))
+            })
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        self.hits(hit)
+            .into_iter()
+            .map(|(hit, weight)| self.texture.value_at(&hit) * weight)
+            .sum()
+    }
+}
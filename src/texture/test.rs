@@ -0,0 +1,987 @@
+use super::*;
+
+use crate::hittable::NO_ATTRIBUTES;
+use crate::material::HoldoutMaterial;
+
+// Returns the texture coordinate derivatives of a lookup as its colour.
+struct FootprintTexture;
+
+impl Texture for FootprintTexture {
+    fn color(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(0., 0., 0.)
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        Color::new(hit.dudx, hit.dvdx, hit.dudy)
+    }
+}
+
+// Returns the u coordinate of a lookup as its value, and black as its colour.
+struct UValueTexture;
+
+impl Texture for UValueTexture {
+    fn color(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(0., 0., 0.)
+    }
+
+    fn value(&self, u: f64, _v: f64, _p: &Point3) -> f64 {
+        u
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        hit.u
+    }
+}
+
+fn hit(p: Point3, normal: Unit3) -> Hit<'static> {
+    Hit {
+        t: 1.,
+        p,
+        u: 0.25,
+        v: 0.5,
+        normal,
+        dpdu: Vector3::new(1., 0., 0.),
+        dpdv: Vector3::new(0., 1., 0.),
+        dudx: 0.1,
+        dudy: 0.,
+        dvdx: 0.,
+        dvdy: 0.1,
+        exterior_refractive_index: 1.,
+        material: &HoldoutMaterial,
+        attributes: &NO_ATTRIBUTES,
+    }
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() <= 1e-9, "{} != {}", a, b);
+}
+
+fn origin() -> Point3 {
+    Point3::new(0., 0., 0.)
+}
+
+mod transformed {
+    use super::*;
+
+    fn subject<T: Texture>(texture: T, rotation: f64) -> TransformedTexture<T> {
+        TransformedTexture {
+            texture,
+            transform: UvTransform {
+                scale: (2., 3.),
+                rotation,
+                offset: (0.5, -1.),
+            },
+        }
+    }
+
+    #[test]
+    fn it_scales_and_offsets_coordinates() {
+        let color = subject(UVTexture {}, 0.).color(0.25, 0.5, &origin());
+
+        assert_close(color.r, 1.);
+        assert_close(color.g, 0.5);
+    }
+
+    #[test]
+    fn it_rotates_coordinates_anticlockwise() {
+        let color = subject(UVTexture {}, 90.).color(0.25, 0.5, &origin());
+
+        assert_close(color.r, 0.5 - 1.5);
+        assert_close(color.g, -1. + 0.5);
+    }
+
+    #[test]
+    fn it_transforms_the_footprint() {
+        let color = subject(FootprintTexture, 90.).color_at(&hit(origin(), Unit3::new(0., 0., 1.)));
+
+        // Rotating (0.2, 0) and (0, 0.3) by a quarter turn.
+        assert_close(color.r, 0.);
+        assert_close(color.g, 0.2);
+        assert_close(color.b, -0.3);
+    }
+
+    #[test]
+    fn it_transforms_values() {
+        let subject = subject(UValueTexture, 0.);
+
+        assert_close(subject.value(0.25, 0.5, &origin()), 1.);
+        assert_close(subject.value_at(&hit(origin(), Unit3::new(0., 0., 1.))), 1.);
+    }
+}
+
+mod projection {
+    use super::*;
+
+    fn subject(projection: Projection) -> ProjectionTexture<UVTexture> {
+        ProjectionTexture {
+            origin: Point3::new(1., 1., 1.),
+            ..ProjectionTexture::new(UVTexture {}, projection)
+        }
+    }
+
+    #[test]
+    fn it_projects_onto_a_plane() {
+        let color = subject(Projection::Planar).color(0., 0., &Point3::new(1.5, 3., 7.));
+
+        assert_close(color.r, 0.5);
+        assert_close(color.g, 2.);
+    }
+
+    #[test]
+    fn it_projects_around_a_cylinder() {
+        let projection = subject(Projection::Cylindrical);
+        let color = projection.color(0., 0., &Point3::new(1., 2., 4.));
+
+        assert_close(color.r, 0.75);
+        assert_close(color.g, 3.);
+    }
+
+    #[test]
+    fn it_projects_around_a_sphere() {
+        let projection = subject(Projection::Spherical);
+        let top = projection.color(0., 0., &Point3::new(1., 1., 3.));
+        let equator = projection.color(0., 0., &Point3::new(0., 1., 1.));
+
+        assert_close(top.g, 1.);
+        assert_close(equator.r, 1.);
+        assert_close(equator.g, 0.5);
+    }
+
+    #[test]
+    fn it_projects_along_the_axis_facing_the_surface() {
+        let projection = subject(Projection::Triplanar { sharpness: 100. });
+        let p = Point3::new(1.25, 1.5, 1.75);
+        let facing_x = projection.color_at(&hit(p, Unit3::new(1., 0.1, 0.1)));
+        let facing_z = projection.color_at(&hit(p, Unit3::new(0.1, 0.1, -1.)));
+
+        assert!((facing_x.r - 0.5).abs() < 1e-6);
+        assert!((facing_x.g - 0.75).abs() < 1e-6);
+        assert!((facing_z.r - 0.25).abs() < 1e-6);
+        assert!((facing_z.g - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn it_blends_between_axes() {
+        let projection = subject(Projection::Triplanar { sharpness: 1. });
+        let p = Point3::new(2., 2., 2.);
+        let color = projection.color_at(&hit(p, Unit3::new(1., 1., 1.)));
+
+        assert_close(color.r, 1.);
+        assert_close(color.g, 1.);
+    }
+
+    #[test]
+    fn it_projects_values() {
+        let projection = ProjectionTexture {
+            origin: Point3::new(1., 1., 1.),
+            ..ProjectionTexture::new(UValueTexture, Projection::Planar)
+        };
+        let p = Point3::new(1.5, 3., 7.);
+
+        assert_close(projection.value(0., 0., &p), 0.5);
+        assert_close(projection.value_at(&hit(p, Unit3::new(0., 0., 1.))), 0.5);
+    }
+
+    #[test]
+    fn it_projects_the_footprint() {
+        let projection = ProjectionTexture {
+            texture: FootprintTexture,
+            ..ProjectionTexture::new(FootprintTexture, Projection::Planar)
+        };
+        let color = projection.color_at(&hit(origin(), Unit3::new(0., 0., 1.)));
+
+        assert_close(color.r, 0.1);
+        assert_close(color.g, 0.);
+        assert_close(color.b, 0.);
+    }
+
+    #[test]
+    fn it_wraps_the_footprint_around_the_axis() {
+        let projection = ProjectionTexture::new(FootprintTexture, Projection::Cylindrical);
+        let hit = Hit {
+            dpdu: Vector3::new(0., 1., 0.),
+            dpdv: Vector3::new(0., 0., 1.),
+            dudx: -0.01,
+            dvdy: 0.,
+            ..hit(Point3::new(-1., 0., 0.), Unit3::new(-1., 0., 0.))
+        };
+        let color = projection.color_at(&hit);
+
+        // A small step across the seam, rather than nearly a whole turn.
+        assert!(color.r.abs() < 0.01);
+        assert!(color.r != 0.);
+    }
+}
+
+mod checkerboard {
+    use super::*;
+
+    #[test]
+    fn it_passes_the_footprint_through() {
+        let subject = CheckerboardTexture {
+            odd: FootprintTexture,
+            even: FootprintTexture,
+            width: 0.1,
+        };
+
+        assert_close(
+            subject.color_at(&hit(origin(), Unit3::new(0., 0., 1.))).r,
+            0.1,
+        );
+    }
+}
+
+mod node {
+    use super::*;
+
+    fn grey(value: f64) -> ConstantTexture {
+        ConstantTexture::scalar(value)
+    }
+
+    fn red() -> ConstantTexture {
+        ConstantTexture {
+            color: Color::new(1., 0., 0.),
+        }
+    }
+
+    #[test]
+    fn it_mixes_by_the_factor() {
+        let subject = MixTexture {
+            first: red(),
+            second: grey(1.),
+            factor: grey(0.25),
+        };
+        let color = subject.color(0., 0., &origin());
+
+        assert_close(color.r, 1.);
+        assert_close(color.g, 0.25);
+    }
+
+    #[test]
+    fn it_multiplies_and_adds() {
+        let product = MultiplyTexture {
+            first: red(),
+            second: grey(0.5),
+        };
+        let sum = AddTexture {
+            first: red(),
+            second: grey(0.5),
+        };
+
+        assert_close(product.color(0., 0., &origin()).r, 0.5);
+        assert_close(product.color(0., 0., &origin()).g, 0.);
+        assert_close(sum.color(0., 0., &origin()).r, 1.5);
+        assert_close(sum.color(0., 0., &origin()).g, 0.5);
+    }
+
+    #[test]
+    fn it_inverts() {
+        let color = InvertTexture { texture: red() }.color(0., 0., &origin());
+
+        assert_close(color.r, 0.);
+        assert_close(color.b, 1.);
+    }
+
+    #[test]
+    fn it_turns_values_into_greys() {
+        let color = GreyTexture { texture: red() }.color(0., 0., &origin());
+
+        assert_close(color.r, 0.2126);
+        assert_close(color.b, 0.2126);
+    }
+
+    #[test]
+    fn it_interpolates_ramps_and_holds_their_ends() {
+        let ramp = ColorRamp {
+            stops: vec![
+                (0.2, Color::new(0., 0., 0.)),
+                (0.6, Color::new(1., 0., 0.)),
+                (0.8, Color::new(1., 1., 0.)),
+            ],
+        };
+
+        assert_close(ramp.at(0.).r, 0.);
+        assert_close(ramp.at(0.5).r, 0.75);
+        assert_close(ramp.at(0.7).g, 0.5);
+        assert_close(ramp.at(1.).g, 1.);
+    }
+
+    #[test]
+    fn it_remaps_values_through_a_ramp() {
+        let subject = RampTexture {
+            texture: GradientTexture {
+                gradient: Gradient::U,
+            },
+            ramp: ColorRamp::linear(0., grey(0.2).color, 1., grey(0.6).color),
+        };
+
+        assert_close(subject.value(0.5, 0., &origin()), 0.4);
+    }
+
+    #[test]
+    fn it_adjusts_hue_saturation_and_value() {
+        let shifted = HsvTexture {
+            texture: red(),
+            hue: 120.,
+            saturation: 1.,
+            value: 0.5,
+        }
+        .color(0., 0., &origin());
+        let desaturated = HsvTexture {
+            texture: red(),
+            hue: 0.,
+            saturation: 0.,
+            value: 1.,
+        }
+        .color(0., 0., &origin());
+
+        assert_close(shifted.r, 0.);
+        assert_close(shifted.g, 0.5);
+        assert_close(desaturated.g, 1.);
+    }
+
+    #[test]
+    fn it_round_trips_through_hsv() {
+        let color = Color::new(0.2, 0.7, 0.4);
+        let (hue, saturation, value) = color.hsv();
+        let round_trip = Color::from_hsv(hue, saturation, value);
+
+        assert_close(round_trip.r, color.r);
+        assert_close(round_trip.g, color.g);
+        assert_close(round_trip.b, color.b);
+    }
+
+    #[test]
+    fn it_clamps_gradients() {
+        let linear = GradientTexture {
+            gradient: Gradient::Linear {
+                from: Point3::new(0., 0., 0.),
+                to: Point3::new(0., 2., 0.),
+            },
+        };
+        let spherical = GradientTexture {
+            gradient: Gradient::Spherical {
+                centre: Point3::new(1., 0., 0.),
+                radius: 4.,
+            },
+        };
+
+        assert_close(linear.value(0., 0., &Point3::new(5., 1., 0.)), 0.5);
+        assert_close(linear.value(0., 0., &Point3::new(0., 3., 0.)), 1.);
+        assert_close(linear.value(0., 0., &Point3::new(0., -1., 0.)), 0.);
+        assert_close(spherical.value(0., 0., &origin()), 0.25);
+    }
+
+    #[test]
+    fn it_passes_the_footprint_through() {
+        let subject = MixTexture {
+            first: FootprintTexture,
+            second: grey(0.),
+            factor: grey(0.),
+        };
+        let color = subject.color_at(&hit(origin(), Unit3::new(0., 0., 1.)));
+
+        assert_close(color.r, 0.1);
+    }
+}
+
+mod procedural {
+    use super::*;
+
+    fn granite_ramp() -> ColorRamp {
+        ColorRamp::linear(0., Color::new(1., 1., 1.), 1., Color::new(0., 0., 0.))
+    }
+
+    #[test]
+    fn it_darkens_wood_towards_the_late_wood() {
+        let light = Color::new(0.6, 0.4, 0.2);
+        let dark = Color::new(0.2, 0.1, 0.05);
+        let subject = WoodTexture::new(0, 1., light, dark);
+
+        for i in 0..100 {
+            let p = Point3::new(f64::from(i) * 0.037 - 2., f64::from(i) * 0.11, 0.3);
+            let color = subject.color(0., 0., &p);
+
+            assert!(
+                color.r <= light.r * 1.1 && color.r >= dark.r * 0.9,
+                "{:?}",
+                color
+            );
+        }
+    }
+
+    #[test]
+    fn it_varies_granite_by_seed() {
+        let p = Point3::new(0.13, -0.52, 0.77);
+        let first = GraniteTexture::new(0, 10., granite_ramp()).color(0., 0., &p);
+        let second = GraniteTexture::new(1, 10., granite_ramp()).color(0., 0., &p);
+
+        assert!(first != second);
+    }
+
+    #[test]
+    fn it_keeps_granite_within_its_ramp() {
+        let subject = GraniteTexture::new(0, 10., granite_ramp());
+
+        for i in 0..100 {
+            let p = Point3::new(f64::from(i) * 0.031, -f64::from(i) * 0.017, 0.);
+            let value = subject.value(0., 0., &p);
+
+            assert!((0. ..=1.).contains(&value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn it_animates_clouds() {
+        let p = Point3::new(0.4, 2., -1.3);
+        let values: Vec<f64> = (0..20)
+            .map(|t| CloudsTexture::new(0, 1., f64::from(t)).value(0., 0., &p))
+            .collect();
+
+        assert!(values.iter().all(|value| (0. ..=1.).contains(value)));
+        assert!(values.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn it_covers_the_sky_by_coverage() {
+        let cloudy = |coverage| {
+            let mut subject = CloudsTexture::new(0, 1., 0.);
+
+            subject.coverage = coverage;
+            (0..400)
+                .filter(|i| {
+                    let p = Point3::new(f64::from(i % 20) * 0.7, 0., f64::from(i / 20) * 0.7);
+
+                    subject.value(0., 0., &p) > 0.
+                })
+                .count()
+        };
+
+        assert!(cloudy(0.2) < cloudy(0.8));
+    }
+
+    #[test]
+    fn it_seeds_marble() {
+        let p = Point3::new(0.3, 0.2, 0.1);
+
+        assert!(
+            MarbleTexture::with_seed(4., 1).color(0., 0., &p)
+                != MarbleTexture::with_seed(4., 2).color(0., 0., &p)
+        );
+    }
+}
+
+mod image_texture {
+    use super::*;
+    use crate::mipmap::{Filter, Wrap};
+    use image::{DynamicImage, ImageBuffer, Rgb, Rgba};
+    use std::path::PathBuf;
+
+    #[test]
+    fn it_decodes_srgb() {
+        assert_close(ColorSpace::Srgb.decode(0.), 0.);
+        assert_close(ColorSpace::Srgb.decode(0.04), 0.04 / 12.92);
+        assert_close(ColorSpace::Srgb.decode(0.5), (0.555f64 / 1.055).powf(2.4));
+        assert_close(ColorSpace::Srgb.decode(1.), 1.);
+    }
+
+    #[test]
+    fn it_leaves_linear_and_raw_values() {
+        assert_close(ColorSpace::Linear.decode(0.5), 0.5);
+        assert_close(ColorSpace::Raw.decode(0.5), 0.5);
+    }
+
+    #[test]
+    fn it_encodes_srgb_as_the_inverse_of_decoding() {
+        for &value in &[0., 0.002, 0.2, 0.5, 1.] {
+            assert_close(
+                ColorSpace::Srgb.decode(ColorSpace::Srgb.encode(value)),
+                value,
+            );
+        }
+    }
+
+    #[test]
+    fn it_decodes_srgb_images() {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(1, 1, Rgb([128, 128, 128])));
+        let subject = ImageTexture::new(&image, ColorSpace::Srgb, Filter::Nearest, Wrap::Repeat);
+
+        assert_close(
+            subject.color(0.5, 0.5, &origin()).g,
+            ColorSpace::Srgb.decode(128. / 255.),
+        );
+    }
+
+    #[test]
+    fn it_keeps_sixteen_bit_precision() {
+        let image = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(1, 1, Rgb([1, 32768, 65535])));
+        let subject = ImageTexture::new(&image, ColorSpace::Linear, Filter::Nearest, Wrap::Repeat);
+        let color = subject.color(0.5, 0.5, &origin());
+
+        assert_close(color.r, 1. / 65535.);
+        assert_close(color.g, 32768. / 65535.);
+        assert_close(color.b, 1.);
+    }
+
+    #[test]
+    fn it_puts_the_bottom_row_at_v_zero() {
+        let mut image = ImageBuffer::from_pixel(1, 2, Rgb([0u8, 0, 0]));
+
+        image.put_pixel(0, 1, Rgb([255, 255, 255]));
+
+        let subject = ImageTexture::new(
+            &DynamicImage::ImageRgb8(image),
+            ColorSpace::Raw,
+            Filter::Nearest,
+            Wrap::Clamp,
+        );
+
+        assert_close(subject.color(0.5, 0.25, &origin()).r, 1.);
+        assert_close(subject.color(0.5, 0.75, &origin()).r, 0.);
+    }
+
+    #[test]
+    fn it_reads_one_channel() {
+        let path =
+            std::env::temp_dir().join(format!("raygrass-{}-channels.png", std::process::id()));
+
+        DynamicImage::ImageRgba8(ImageBuffer::from_pixel(1, 1, Rgba([0, 255, 0, 51])))
+            .save(&path)
+            .unwrap();
+
+        let open = |channel, color_space| {
+            ScalarImageTexture::open(&path, channel, color_space, Filter::Nearest, Wrap::Repeat)
+        };
+        let (green, alpha) = (open(1, ColorSpace::Raw), open(3, ColorSpace::Srgb));
+
+        std::fs::remove_file(&path).unwrap();
+
+        let (green, alpha) = (green.unwrap(), alpha.unwrap());
+
+        assert_close(green.value(0.5, 0.5, &origin()), 1.);
+        assert_close(alpha.value(0.5, 0.5, &origin()), 0.2);
+        assert_close(alpha.color(0.5, 0.5, &origin()).b, 0.2);
+    }
+
+    #[test]
+    fn it_reads_radiance_hdr_files() {
+        let path = std::env::temp_dir().join(format!("raygrass-{}.hdr", std::process::id()));
+        let pixels = [Rgb([4f32, 2., 0.5]), Rgb([0.25f32, 0.25, 0.25])];
+
+        image::codecs::hdr::HdrEncoder::new(std::fs::File::create(&path).unwrap())
+            .encode(&pixels, 1, 2)
+            .unwrap();
+
+        let subject = ImageTexture::open(&path, ColorSpace::Linear, Filter::Nearest, Wrap::Clamp);
+
+        std::fs::remove_file(&path).unwrap();
+
+        let subject = subject.unwrap();
+        let top = subject.color(0.5, 0.75, &origin());
+
+        assert_close(top.r, 4.);
+        assert_close(top.g, 2.);
+        assert_close(top.b, 0.5);
+        assert_close(subject.color(0.5, 0.25, &origin()).r, 0.25);
+    }
+
+    #[test]
+    fn it_reads_radiance_hdr_files_by_their_contents() {
+        let path = std::env::temp_dir().join(format!("raygrass-{}.texture", std::process::id()));
+
+        image::codecs::hdr::HdrEncoder::new(std::fs::File::create(&path).unwrap())
+            .encode(&[Rgb([4f32, 2., 0.5])], 1, 1)
+            .unwrap();
+
+        let subject = ImageTexture::open(&path, ColorSpace::Linear, Filter::Nearest, Wrap::Clamp);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_close(subject.unwrap().color(0.5, 0.5, &origin()).r, 4.);
+    }
+
+    // A directory of tiles named by `name`, removed when dropped.
+    struct UdimSet(PathBuf);
+
+    impl UdimSet {
+        // Tiles `width` texels wide whose texels are their tile number, less
+        // 1000, plus their column.
+        fn new(name: &str, numbers: &[u32]) -> Self {
+            let directory =
+                std::env::temp_dir().join(format!("raygrass-{}-{}", std::process::id(), name));
+
+            std::fs::create_dir_all(&directory).unwrap();
+
+            for &number in numbers {
+                let image = ImageBuffer::from_fn(2, 1, |x, _| {
+                    let value = (number - 1000 + x * 100) as u16;
+
+                    Rgb([value, value, value])
+                });
+
+                DynamicImage::ImageRgb16(image)
+                    .save(directory.join(format!("diffuse.{}.png", number)))
+                    .unwrap();
+            }
+
+            Self(directory)
+        }
+
+        fn pattern(&self) -> PathBuf {
+            self.0.join("diffuse.<UDIM>.png")
+        }
+    }
+
+    impl Drop for UdimSet {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn udim_value(subject: &ImageTexture, u: f64, v: f64) -> f64 {
+        (subject.color(u, v, &origin()).r * 65535.).round()
+    }
+
+    #[test]
+    fn it_picks_udim_tiles_by_the_integer_part_of_uv() {
+        let set = UdimSet::new("udim_tiles", &[1001, 1002, 1013]);
+        let subject = ImageTexture::open(
+            set.pattern(),
+            ColorSpace::Raw,
+            Filter::Nearest,
+            Wrap::Repeat,
+        )
+        .unwrap();
+
+        assert_eq!(udim_value(&subject, 0.25, 0.5), 1.);
+        assert_eq!(udim_value(&subject, 1.25, 0.5), 2.);
+        assert_eq!(udim_value(&subject, 2.25, 1.5), 13.);
+    }
+
+    #[test]
+    fn it_looks_up_the_fractional_part_within_a_udim_tile() {
+        let set = UdimSet::new("udim_fraction", &[1001, 1002]);
+        let subject = ImageTexture::open(
+            set.pattern(),
+            ColorSpace::Raw,
+            Filter::Nearest,
+            Wrap::Repeat,
+        )
+        .unwrap();
+
+        assert_eq!(udim_value(&subject, 1.25, 0.5), 2.);
+        assert_eq!(udim_value(&subject, 1.75, 0.5), 102.);
+    }
+
+    #[test]
+    fn it_clamps_udim_tiles_at_their_edges() {
+        let set = UdimSet::new("udim_clamp", &[1001]);
+        let subject = ImageTexture::open(
+            set.pattern(),
+            ColorSpace::Raw,
+            Filter::Bilinear,
+            Wrap::Repeat,
+        )
+        .unwrap();
+
+        // Repeating would blend in the far edge of the tile.
+        assert_eq!(udim_value(&subject, 0.99, 0.5), 101.);
+    }
+
+    #[test]
+    fn it_is_black_outside_the_udim_tiles() {
+        let set = UdimSet::new("udim_outside", &[1001]);
+        let subject = ImageTexture::open(
+            set.pattern(),
+            ColorSpace::Raw,
+            Filter::Nearest,
+            Wrap::Repeat,
+        )
+        .unwrap();
+
+        assert_eq!(udim_value(&subject, 1.5, 0.5), 0.);
+        assert_eq!(udim_value(&subject, -0.5, 0.5), 0.);
+        assert_eq!(udim_value(&subject, 0.5, -0.5), 0.);
+        assert_eq!(udim_value(&subject, 10.5, 0.5), 0.);
+        assert_eq!(udim_value(&subject, 0.5, 900.5), 0.);
+        assert_eq!(udim_value(&subject, 0.5, 1e12), 0.);
+    }
+
+    #[test]
+    fn it_reads_scalar_udim_sets() {
+        let set = UdimSet::new("udim_scalar", &[1001, 1011]);
+        let subject = ScalarImageTexture::open(
+            set.pattern(),
+            0,
+            ColorSpace::Raw,
+            Filter::Nearest,
+            Wrap::Repeat,
+        )
+        .unwrap();
+
+        assert_close(subject.value(0.25, 1.5, &origin()), 11. / 65535.);
+    }
+
+    #[test]
+    fn it_reports_udim_sets_without_tiles() {
+        let set = UdimSet::new("udim_empty", &[]);
+        let subject = ImageTexture::open(
+            set.pattern(),
+            ColorSpace::Raw,
+            Filter::Nearest,
+            Wrap::Repeat,
+        );
+
+        assert!(subject.is_err());
+    }
+}
+
+mod tiled {
+    use super::*;
+    use crate::mipmap::{Filter, Wrap};
+    use crate::texture::tiled::Rgb32;
+    use crate::tile_cache::TileCache;
+    use image::{DynamicImage, ImageBuffer, Rgb};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    // Removes the file when dropped, so failing tests don't leave it behind.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("raygrass-{}-{}", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    // A 37 by 23 image, so that the edge tiles and the levels are ragged.
+    fn image() -> DynamicImage {
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(37, 23, |x, y| {
+            Rgb([(x * 7) as u8, (y * 11) as u8, ((x * y) % 256) as u8])
+        }))
+    }
+
+    fn tiled_copy(name: &str, tile_size: usize) -> TempFile {
+        let png = TempFile::new(&format!("{}.png", name));
+        let tiled = TempFile::new(&format!("{}.tx", name));
+
+        image().save(&png.0).unwrap();
+        crate::texture::tiled::convert(&png.0, &tiled.0, ColorSpace::Srgb, tile_size).unwrap();
+        tiled
+    }
+
+    fn assert_same(a: Color, b: Color) {
+        // Tiles hold 32-bit floats.
+        for (a, b) in [(a.r, b.r), (a.g, b.g), (a.b, b.b)].iter() {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn footprint_hit(u: f64, v: f64, footprint: f64) -> Hit<'static> {
+        let mut hit = hit(origin(), Unit3::new(0., 0., 1.));
+
+        hit.u = u;
+        hit.v = v;
+        hit.dudx = footprint;
+        hit.dvdy = footprint * 0.5;
+        hit
+    }
+
+    #[test]
+    fn it_matches_the_image_it_was_made_from() {
+        let tiled = tiled_copy("matches", 8);
+        let cache = Arc::new(TileCache::new(1 << 20));
+
+        for &filter in [
+            Filter::Nearest,
+            Filter::Bicubic,
+            Filter::Trilinear,
+            Filter::Ewa,
+        ]
+        .iter()
+        {
+            let subject = TiledTexture::open(&tiled.0, &cache, filter, Wrap::Repeat).unwrap();
+            let expected = ImageTexture::new(&image(), ColorSpace::Srgb, filter, Wrap::Repeat);
+
+            for i in 0..50 {
+                let (u, v) = (f64::from(i) * 0.0731 - 0.5, f64::from(i) * 0.0437);
+                let footprint = f64::from(i % 5) * 0.03;
+
+                assert_same(
+                    subject.color(u, v, &origin()),
+                    expected.color(u, v, &origin()),
+                );
+                assert_same(
+                    subject.color_at(&footprint_hit(u, v, footprint)),
+                    expected.color_at(&footprint_hit(u, v, footprint)),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_reads_through_a_cache_smaller_than_the_texture() {
+        let tiled = tiled_copy("small_cache", 4);
+        // Room for about one tile in each shard.
+        let cache = Arc::new(TileCache::new(16 * 16 * std::mem::size_of::<Rgb32>() * 16));
+        let subject = TiledTexture::open(&tiled.0, &cache, Filter::Ewa, Wrap::Clamp).unwrap();
+        let expected = ImageTexture::new(&image(), ColorSpace::Srgb, Filter::Ewa, Wrap::Clamp);
+
+        for i in 0..200 {
+            let (u, v) = (f64::from(i) * 0.0173 % 1., f64::from(i) * 0.0291 % 1.);
+            let hit = footprint_hit(u, v, 0.05);
+
+            assert_same(subject.color_at(&hit), expected.color_at(&hit));
+        }
+    }
+
+    #[test]
+    fn it_rejects_other_files() {
+        let cache = Arc::new(TileCache::new(1 << 20));
+        let png = TempFile::new("not_tiled.png");
+
+        image().save(&png.0).unwrap();
+
+        let error = TiledTexture::open(&png.0, &cache, Filter::Ewa, Wrap::Repeat)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_rejects_truncated_files() {
+        let tiled = tiled_copy("truncated", 8);
+        let cache = Arc::new(TileCache::new(1 << 20));
+        let length = std::fs::metadata(&tiled.0).unwrap().len();
+
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&tiled.0)
+            .unwrap()
+            .set_len(length - 1)
+            .unwrap();
+
+        let error = TiledTexture::open(&tiled.0, &cache, Filter::Ewa, Wrap::Repeat)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_rejects_oversized_tiles() {
+        let tiled = tiled_copy("oversized", 8);
+        let cache = Arc::new(TileCache::new(1 << 20));
+        let mut bytes = std::fs::read(&tiled.0).unwrap();
+
+        // The tile size follows the magic and the version.
+        bytes[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&tiled.0, &bytes).unwrap();
+
+        let error = TiledTexture::open(&tiled.0, &cache, Filter::Ewa, Wrap::Repeat)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_is_black_where_tiles_can_no_longer_be_read() {
+        let tiled = tiled_copy("shrunk", 8);
+        let cache = Arc::new(TileCache::new(1 << 20));
+        let subject = TiledTexture::open(&tiled.0, &cache, Filter::Nearest, Wrap::Repeat).unwrap();
+
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&tiled.0)
+            .unwrap()
+            .set_len(16)
+            .unwrap();
+
+        assert_same(subject.color(0.5, 0.5, &origin()), Color::new(0., 0., 0.));
+    }
+}
+
+mod attribute {
+    use super::*;
+    use crate::hittable::Attributes;
+
+    fn attributes(id: u64) -> Attributes {
+        Attributes {
+            id,
+            floats: vec![("wear".to_string(), 0.25)],
+            colors: vec![("tint".to_string(), Color::new(0.1, 0.2, 0.3))],
+        }
+    }
+
+    fn hit_with(attributes: &Attributes) -> Hit<'_> {
+        Hit {
+            attributes,
+            ..hit(origin(), Unit3::new(0., 0., 1.))
+        }
+    }
+
+    #[test]
+    fn it_reads_colour_attributes() {
+        let attributes = attributes(1);
+        let color =
+            AttributeTexture::new("tint", Color::new(1., 1., 1.)).color_at(&hit_with(&attributes));
+
+        assert_close(color.r, 0.1);
+        assert_close(color.g, 0.2);
+        assert_close(color.b, 0.3);
+    }
+
+    #[test]
+    fn it_reads_float_attributes_as_greys() {
+        let attributes = attributes(1);
+        let texture = AttributeTexture::new("wear", Color::new(1., 1., 1.));
+        let hit = hit_with(&attributes);
+
+        assert_close(texture.color_at(&hit).g, 0.25);
+        assert_close(texture.value_at(&hit), 0.25);
+    }
+
+    #[test]
+    fn it_falls_back_to_its_default() {
+        let attributes = attributes(1);
+        let texture = AttributeTexture::new("missing", Color::new(0.7, 0.7, 0.7));
+
+        assert_close(texture.color_at(&hit_with(&attributes)).r, 0.7);
+        assert_close(texture.color(0.5, 0.5, &origin()).r, 0.7);
+    }
+
+    #[test]
+    fn it_gives_each_object_its_own_random_colour() {
+        let texture = ObjectRandomTexture { seed: 0 };
+        let colors: Vec<Color> = (0..20)
+            .map(|id| texture.color_at(&hit_with(&attributes(id))))
+            .collect();
+
+        for (i, a) in colors.iter().enumerate() {
+            assert!([a.r, a.g, a.b].iter().all(|c| (0. ..1.).contains(c)));
+
+            for b in &colors[i + 1..] {
+                assert!(a.r != b.r || a.g != b.g || a.b != b.b);
+            }
+        }
+    }
+
+    #[test]
+    fn it_keeps_random_colours_for_the_same_object() {
+        let texture = ObjectRandomTexture { seed: 0 };
+        let other_seed = ObjectRandomTexture { seed: 1 };
+        let attributes = attributes(7);
+        let hit = hit_with(&attributes);
+
+        assert_eq!(texture.color_at(&hit).r, texture.color_at(&hit).r);
+        assert_ne!(texture.color_at(&hit).r, other_seed.color_at(&hit).r);
+    }
+}
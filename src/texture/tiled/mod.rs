@@ -0,0 +1,414 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer, ImageResult, Primitive, Rgba};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::mipmap::{downsample, halved, Filter, Level, MipMap, Source, Texel, Wrap};
+use crate::texture::{is_hdr, open_image, read_hdr, ColorSpace, Texture};
+use crate::tile_cache::{Tile, TileCache, TileKey};
+
+// Tiled textures are mipmapped ahead of time and stored in square tiles, so
+// that a render only reads the parts of each level it looks at. The file is
+// a header of little-endian 32-bit integers:
+//
+//   "RGTX", version, tile size, number of levels, then each level's width
+//   and height, finest first
+//
+// followed by each level's tiles, row by row from the bottom of the image.
+// Tiles are padded to full size at the edges, and hold linear red, green and
+// blue 32-bit floats.
+const MAGIC: &[u8; 4] = b"RGTX";
+const VERSION: u32 = 1;
+const TEXEL_BYTES: usize = 12;
+
+pub const DEFAULT_TILE_SIZE: usize = 64;
+// Larger tiles would defeat the point of reading textures in pieces, and
+// are only found in corrupt headers.
+pub const MAX_TILE_SIZE: usize = 4096;
+
+// The extension of tiled texture files.
+pub const EXTENSION: &str = "tx";
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn tile_count(size: usize, tile_size: usize) -> usize {
+    size.div_ceil(tile_size)
+}
+
+// Linear colours at the precision they are stored in, to keep the memory
+// needed to convert large images and cache their tiles down.
+#[derive(Clone, Copy)]
+pub struct Rgb32(pub [f32; 3]);
+
+impl Rgb32 {
+    fn color(self) -> Color {
+        Color::new(
+            f64::from(self.0[0]),
+            f64::from(self.0[1]),
+            f64::from(self.0[2]),
+        )
+    }
+}
+
+impl Texel for Rgb32 {
+    fn zero() -> Self {
+        Rgb32([0.; 3])
+    }
+
+    fn plus(self, other: Self) -> Self {
+        let (a, b) = (self.0, other.0);
+
+        Rgb32([a[0] + b[0], a[1] + b[1], a[2] + b[2]])
+    }
+
+    fn times(self, scalar: f64) -> Self {
+        let scalar = scalar as f32;
+
+        Rgb32([self.0[0] * scalar, self.0[1] * scalar, self.0[2] * scalar])
+    }
+
+    fn non_negative(self) -> Self {
+        Rgb32([self.0[0].max(0.), self.0[1].max(0.), self.0[2].max(0.)])
+    }
+
+    fn from_color(color: Color) -> Self {
+        Rgb32([color.r as f32, color.g as f32, color.b as f32])
+    }
+}
+
+// Converts the image at `input` to a tiled texture at `output`, decoding it
+// from `color_space`. Only the decoded image and one level at a time are in
+// memory: each level is written out and then halved to make the next.
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    color_space: ColorSpace,
+    tile_size: usize,
+) -> ImageResult<()> {
+    let (width, height, mut texels) = decode(input.as_ref(), color_space)?;
+    let mut sizes = vec![(width, height)];
+
+    while let Some(&(width, height)) = sizes.last().filter(|(width, height)| width * height > 1) {
+        sizes.push(halved(width, height));
+    }
+
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    write_header(&mut writer, tile_size, &sizes)?;
+
+    for (level, &(width, height)) in sizes.iter().enumerate() {
+        write_level(&mut writer, width, height, &texels, tile_size)?;
+
+        if level + 1 < sizes.len() {
+            texels = downsample(width, height, |x, y| texels[y * width + x]).2;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+// The image at `path` in linear colour, bottom row first, read without a
+// copy at a higher precision than the result.
+fn decode(path: &Path, color_space: ColorSpace) -> ImageResult<(usize, usize, Vec<Rgb32>)> {
+    if is_hdr(path)? {
+        let (width, height, pixels) = read_hdr(path)?;
+        let texels = pixels.into_iter().map(|pixel| Rgb32(pixel.0)).collect();
+
+        return Ok((width, height, texels));
+    }
+
+    let image = open_image(path)?;
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let texels = match image {
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => linear(&image.into_rgba16(), u16::MAX, color_space),
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_)
+        | DynamicImage::ImageBgr8(_)
+        | DynamicImage::ImageBgra8(_) => linear(&image.into_rgba8(), u8::MAX, color_space),
+    };
+
+    Ok((width, height, texels))
+}
+
+fn linear<T>(image: &ImageBuffer<Rgba<T>, Vec<T>>, max: T, color_space: ColorSpace) -> Vec<Rgb32>
+where
+    T: Primitive + Into<f64> + 'static,
+{
+    let scale = 1. / max.into();
+    let channel = |value: T| color_space.decode(value.into() * scale) as f32;
+
+    (0..image.height())
+        .rev()
+        .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let pixel = image.get_pixel(x, y);
+
+            Rgb32([channel(pixel[0]), channel(pixel[1]), channel(pixel[2])])
+        })
+        .collect()
+}
+
+fn write_header(
+    writer: &mut impl Write,
+    tile_size: usize,
+    sizes: &[(usize, usize)],
+) -> io::Result<()> {
+    let header = [VERSION, tile_size as u32, sizes.len() as u32];
+
+    writer.write_all(MAGIC)?;
+
+    for value in header.iter() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    for (width, height) in sizes {
+        writer.write_all(&(*width as u32).to_le_bytes())?;
+        writer.write_all(&(*height as u32).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn write_level(
+    writer: &mut impl Write,
+    width: usize,
+    height: usize,
+    texels: &[Rgb32],
+    tile_size: usize,
+) -> io::Result<()> {
+    for tile_y in 0..tile_count(height, tile_size) {
+        for tile_x in 0..tile_count(width, tile_size) {
+            for y in tile_y * tile_size..(tile_y + 1) * tile_size {
+                for x in tile_x * tile_size..(tile_x + 1) * tile_size {
+                    let texel = if x < width && y < height {
+                        texels[y * width + x]
+                    } else {
+                        Rgb32::zero()
+                    };
+
+                    for channel in texel.0.iter() {
+                        writer.write_all(&channel.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// An open tiled texture file, whose tiles are read through the cache.
+struct TiledFile {
+    file: Mutex<File>,
+    id: usize,
+    tile_size: usize,
+    cache: Arc<TileCache>,
+    // Whether a tile has failed to read, so that it is only reported once.
+    failed: AtomicBool,
+}
+
+impl TiledFile {
+    fn read_tile(&self, offset: u64) -> io::Result<Vec<Rgb32>> {
+        let mut bytes = vec![0u8; self.tile_size * self.tile_size * TEXEL_BYTES];
+        {
+            let mut file = self.file.lock().unwrap();
+
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut bytes)?;
+        }
+
+        let channel = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        Ok(bytes
+            .chunks_exact(TEXEL_BYTES)
+            .map(|texel| {
+                Rgb32([
+                    channel(&texel[0..4]),
+                    channel(&texel[4..8]),
+                    channel(&texel[8..12]),
+                ])
+            })
+            .collect())
+    }
+}
+
+// One level of a tiled texture file. Tiles that can't be read, such as when
+// the file changes during a render, are black, and the first failure is
+// reported.
+pub struct TiledLevel {
+    file: Arc<TiledFile>,
+    level: usize,
+    tiles_x: usize,
+    // Where the level's tiles start in the file.
+    offset: u64,
+}
+
+thread_local! {
+    // The tile each thread read last. The texels of a lookup nearly always
+    // share a tile, so they are read from it directly rather than through the
+    // lock on the cache.
+    static LAST_TILE: RefCell<Option<(TileKey, Tile)>> = const { RefCell::new(None) };
+}
+
+impl TiledLevel {
+    fn tile(&self, key: TileKey) -> io::Result<Tile> {
+        if let Some(tile) = LAST_TILE.with(|last| match &*last.borrow() {
+            Some((last_key, tile)) if *last_key == key => Some(tile.clone()),
+            _ => None,
+        }) {
+            return Ok(tile);
+        }
+
+        let tile_bytes = (self.file.tile_size * self.file.tile_size * TEXEL_BYTES) as u64;
+        let offset = self.offset + (key.y * self.tiles_x + key.x) as u64 * tile_bytes;
+        let tile = self.file.cache.get(key, || self.file.read_tile(offset))?;
+
+        LAST_TILE.with(|last| *last.borrow_mut() = Some((key, tile.clone())));
+
+        Ok(tile)
+    }
+}
+
+impl Source<Color> for TiledLevel {
+    fn texel(&self, x: usize, y: usize) -> Color {
+        let tile_size = self.file.tile_size;
+        let key = TileKey {
+            file: self.file.id,
+            level: self.level,
+            x: x / tile_size,
+            y: y / tile_size,
+        };
+
+        match self.tile(key) {
+            Ok(tile) => tile[(y % tile_size) * tile_size + x % tile_size].color(),
+            Err(error) => {
+                if !self.file.failed.swap(true, Ordering::Relaxed) {
+                    eprintln!("Couldn't read texture tile: {}", error);
+                }
+
+                Color::new(0., 0., 0.)
+            }
+        }
+    }
+}
+
+// A texture read from a tiled texture file through `cache`, so that only the
+// tiles it needs are in memory.
+pub struct TiledTexture {
+    mipmap: MipMap<Color, TiledLevel>,
+}
+
+impl TiledTexture {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        cache: &Arc<TileCache>,
+        filter: Filter,
+        wrap: Wrap,
+    ) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let (tile_size, sizes) = read_header(&mut BufReader::new(&mut file))?;
+        let tile_bytes = (tile_size * tile_size * TEXEL_BYTES) as u64;
+        let tiled_file = Arc::new(TiledFile {
+            file: Mutex::new(file),
+            id: cache.register(),
+            tile_size,
+            cache: cache.clone(),
+            failed: AtomicBool::new(false),
+        });
+        let mut offset = (4 + 4 * 3 + 8 * sizes.len()) as u64;
+        let mut levels = Vec::with_capacity(sizes.len());
+
+        for (level, &(width, height)) in sizes.iter().enumerate() {
+            let tiles_x = tile_count(width, tile_size);
+            let source = TiledLevel {
+                file: tiled_file.clone(),
+                level,
+                tiles_x,
+                offset,
+            };
+
+            levels.push(Level::new(width, height, source, wrap));
+            offset += (tiles_x * tile_count(height, tile_size)) as u64 * tile_bytes;
+        }
+
+        let length = tiled_file.file.lock().unwrap().metadata()?.len();
+
+        if length < offset {
+            return Err(invalid_data("tiled texture is truncated"));
+        }
+
+        Ok(Self {
+            mipmap: MipMap::from_levels(levels, filter),
+        })
+    }
+}
+
+// The tile size and the size of each level.
+fn read_header(reader: &mut impl Read) -> io::Result<(usize, Vec<(usize, usize)>)> {
+    let mut magic = [0u8; 4];
+
+    reader.read_exact(&mut magic)?;
+
+    if magic != *MAGIC {
+        return Err(invalid_data("not a tiled texture"));
+    }
+
+    let mut read_u32 = || -> io::Result<usize> {
+        let mut bytes = [0u8; 4];
+
+        reader.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes) as usize)
+    };
+
+    if read_u32()? != VERSION as usize {
+        return Err(invalid_data("unsupported tiled texture version"));
+    }
+
+    let tile_size = read_u32()?;
+    let level_count = read_u32()?;
+
+    // Each level halves the last, so even the largest images have few.
+    if tile_size == 0 || tile_size > MAX_TILE_SIZE || level_count == 0 || level_count > 64 {
+        return Err(invalid_data("tiled texture has a malformed header"));
+    }
+
+    let mut sizes = Vec::with_capacity(level_count);
+
+    for _ in 0..level_count {
+        let size = (read_u32()?, read_u32()?);
+
+        if size.0 == 0 || size.1 == 0 {
+            return Err(invalid_data("tiled texture has an empty level"));
+        }
+
+        sizes.push(size);
+    }
+
+    Ok((tile_size, sizes))
+}
+
+impl Texture for TiledTexture {
+    fn color(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        self.mipmap.lookup(u, v, (0., 0.), (0., 0.))
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.mipmap
+            .lookup(hit.u, hit.v, (hit.dudx, hit.dvdx), (hit.dudy, hit.dvdy))
+    }
+}
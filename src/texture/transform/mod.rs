@@ -0,0 +1,69 @@
+use crate::color::Color;
+use crate::geometry::*;
+use crate::hittable::Hit;
+use crate::texture::{remapped, Texture};
+
+// A placement of texture coordinates: scaled, then rotated anticlockwise by
+// `rotation` degrees, then offset.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UvTransform {
+    pub scale: (f64, f64),
+    pub rotation: f64,
+    pub offset: (f64, f64),
+}
+
+impl UvTransform {
+    // The linear part of the transform, which also maps derivatives.
+    fn linear(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (u, v) = (u * self.scale.0, v * self.scale.1);
+
+        (u * cos - v * sin, u * sin + v * cos)
+    }
+
+    pub fn apply(&self, u: f64, v: f64) -> (f64, f64) {
+        let (u, v) = self.linear((u, v));
+
+        (u + self.offset.0, v + self.offset.1)
+    }
+}
+
+// Looks up `texture` at transformed coordinates, to tile, turn or move it on
+// a surface.
+pub struct TransformedTexture<T: Texture> {
+    pub texture: T,
+    pub transform: UvTransform,
+}
+
+impl<T: Texture> TransformedTexture<T> {
+    fn transformed<'a>(&self, hit: &Hit<'a>) -> Hit<'a> {
+        remapped(
+            hit,
+            self.transform.apply(hit.u, hit.v),
+            self.transform.linear((hit.dudx, hit.dvdx)),
+            self.transform.linear((hit.dudy, hit.dvdy)),
+        )
+    }
+}
+
+impl<T: Texture> Texture for TransformedTexture<T> {
+    fn color(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let (u, v) = self.transform.apply(u, v);
+
+        self.texture.color(u, v, p)
+    }
+
+    fn value(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        let (u, v) = self.transform.apply(u, v);
+
+        self.texture.value(u, v, p)
+    }
+
+    fn color_at(&self, hit: &Hit) -> Color {
+        self.texture.color_at(&self.transformed(hit))
+    }
+
+    fn value_at(&self, hit: &Hit) -> f64 {
+        self.texture.value_at(&self.transformed(hit))
+    }
+}
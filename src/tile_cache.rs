@@ -0,0 +1,260 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::texture::tiled::Rgb32;
+
+// The cache is split into shards with their own locks and share of the
+// budget, so that render threads rarely wait on each other.
+const SHARDS: usize = 16;
+
+// File numbers are unique across caches, so that a tile kept outside one
+// can't be taken for another cache's.
+static FILES: AtomicUsize = AtomicUsize::new(0);
+
+pub type Tile = Arc<Vec<Rgb32>>;
+
+// A tile of one level of a texture file registered with the cache.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct TileKey {
+    pub file: usize,
+    pub level: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+// Tiles of textures that are too large to hold in memory, read on demand and
+// kept within a memory budget by evicting the least recently used. One cache
+// is shared by every tiled texture in a render.
+pub struct TileCache {
+    shards: Vec<Mutex<Shard>>,
+}
+
+struct Shard {
+    budget: usize,
+    used: usize,
+    clock: u64,
+    tiles: HashMap<TileKey, (Tile, u64)>,
+    // Keys by the time they were last used, oldest first.
+    recency: BTreeMap<u64, TileKey>,
+}
+
+impl TileCache {
+    // `budget` is in bytes.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            shards: (0..SHARDS)
+                .map(|_| {
+                    Mutex::new(Shard {
+                        budget: budget / SHARDS,
+                        used: 0,
+                        clock: 0,
+                        tiles: HashMap::new(),
+                        recency: BTreeMap::new(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    // A new file number for the keys of a texture's tiles.
+    pub fn register(&self) -> usize {
+        FILES.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // The tile for `key`, read with `load` if it isn't resident. Loading
+    // happens outside the lock, so other threads carry on meanwhile; if two
+    // load the same tile, the first to finish is kept.
+    pub fn get(
+        &self,
+        key: TileKey,
+        load: impl FnOnce() -> io::Result<Vec<Rgb32>>,
+    ) -> io::Result<Tile> {
+        let shard = &self.shards[Self::shard(&key)];
+
+        if let Some(tile) = shard.lock().unwrap().touch(&key) {
+            return Ok(tile);
+        }
+
+        let tile = Arc::new(load()?);
+        let mut shard = shard.lock().unwrap();
+
+        if let Some(tile) = shard.touch(&key) {
+            return Ok(tile);
+        }
+
+        shard.insert(key, tile.clone());
+
+        Ok(tile)
+    }
+
+    fn shard(key: &TileKey) -> usize {
+        let mut hasher = DefaultHasher::new();
+
+        key.hash(&mut hasher);
+        hasher.finish() as usize % SHARDS
+    }
+}
+
+impl Shard {
+    fn touch(&mut self, key: &TileKey) -> Option<Tile> {
+        let clock = self.clock + 1;
+        let (tile, used) = self.tiles.get_mut(key)?;
+
+        self.recency.remove(used);
+        self.recency.insert(clock, *key);
+        *used = clock;
+        self.clock = clock;
+
+        Some(tile.clone())
+    }
+
+    fn insert(&mut self, key: TileKey, tile: Tile) {
+        self.clock += 1;
+        self.used += Self::size(&tile);
+        self.tiles.insert(key, (tile, self.clock));
+        self.recency.insert(self.clock, key);
+
+        // The newest tile stays even if it alone is over the budget, since
+        // the caller is about to read it.
+        while self.used > self.budget && self.tiles.len() > 1 {
+            let (&oldest, &key) = self.recency.iter().next().unwrap();
+
+            self.recency.remove(&oldest);
+
+            if let Some((tile, _)) = self.tiles.remove(&key) {
+                self.used -= Self::size(&tile);
+            }
+        }
+    }
+
+    fn size(tile: &Tile) -> usize {
+        tile.len() * std::mem::size_of::<Rgb32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn key(x: usize) -> TileKey {
+        TileKey {
+            file: 0,
+            level: 0,
+            x,
+            y: 0,
+        }
+    }
+
+    fn tile(value: f32) -> Vec<Rgb32> {
+        vec![Rgb32([value; 3]); 4]
+    }
+
+    const TILE_BYTES: usize = 4 * std::mem::size_of::<Rgb32>();
+
+    #[test]
+    fn it_loads_tiles_once() {
+        let cache = TileCache::new(SHARDS * 8 * TILE_BYTES);
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(tile(0.5))
+        };
+
+        cache.get(key(3), load).unwrap();
+        let found = cache.get(key(3), load).unwrap();
+
+        assert_eq!(loads.get(), 1);
+        assert_eq!(found[0].0[0], 0.5);
+    }
+
+    #[test]
+    fn it_evicts_the_least_recently_used_tile() {
+        let cache = TileCache::new(SHARDS * 2 * TILE_BYTES);
+
+        // Keys that fall in the same shard, so that they compete for its
+        // budget of two tiles.
+        let keys: Vec<TileKey> = (0..)
+            .map(key)
+            .filter(|key| TileCache::shard(key) == 0)
+            .take(3)
+            .collect();
+
+        cache.get(keys[0], || Ok(tile(0.))).unwrap();
+        cache.get(keys[1], || Ok(tile(1.))).unwrap();
+        cache
+            .get(keys[0], || panic!("the first tile should be resident"))
+            .unwrap();
+        cache.get(keys[2], || Ok(tile(2.))).unwrap();
+
+        let reloaded = Cell::new(false);
+
+        cache
+            .get(keys[0], || panic!("the first tile was used recently"))
+            .unwrap();
+        cache
+            .get(keys[1], || {
+                reloaded.set(true);
+                Ok(tile(1.))
+            })
+            .unwrap();
+
+        assert!(reloaded.get());
+    }
+
+    #[test]
+    fn it_keeps_within_its_budget() {
+        let cache = TileCache::new(SHARDS * 3 * TILE_BYTES);
+
+        for x in 0..1000 {
+            cache.get(key(x), || Ok(tile(0.))).unwrap();
+        }
+
+        for shard in &cache.shards {
+            let shard = shard.lock().unwrap();
+
+            assert!(shard.used <= 3 * TILE_BYTES);
+            assert_eq!(shard.tiles.len(), shard.recency.len());
+        }
+    }
+
+    #[test]
+    fn it_passes_on_load_errors() {
+        let cache = TileCache::new(SHARDS * TILE_BYTES);
+        let result = cache.get(key(0), || Err(io::Error::other("gone")));
+
+        assert!(result.is_err());
+        assert!(cache.get(key(0), || Ok(tile(1.))).is_ok());
+    }
+
+    #[test]
+    fn it_is_shared_between_threads() {
+        let cache = TileCache::new(SHARDS * 4 * TILE_BYTES);
+
+        rayon::scope(|scope| {
+            for thread in 0..8 {
+                let cache = &cache;
+
+                scope.spawn(move |_| {
+                    for i in 0..500 {
+                        let x = (i * 7 + thread) % 50;
+                        let tile = cache.get(key(x), || Ok(tile(x as f32))).unwrap();
+
+                        assert_eq!(tile[0].0[0], x as f32);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn it_numbers_files() {
+        let cache = TileCache::new(0);
+
+        assert_ne!(cache.register(), cache.register());
+    }
+}